@@ -0,0 +1,675 @@
+use crate::{
+    QuadraticBezier,
+    path::{FillRule, Line, Path},
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+/// 交点を探すときに曲線を分割する回数の上限
+const MAX_INTERSECT_DEPTH: u32 = 52;
+/// 点から曲線上の最も近い位置を探すときの初期分割数
+const PROJECT_STEPS: usize = 16;
+
+/// 2 つのパスの重ね合わせ方
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    /// a から b を取り除く
+    Difference,
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+/// 集合演算の途中で使う誤差の目安
+#[derive(Clone, Copy)]
+struct Tolerance<T: Scalar> {
+    /// 交点を求める精度
+    intersect: T,
+    /// 同じ点とみなす距離
+    snap: T,
+    /// 区間の左右の内外を調べるときにずらす距離
+    probe: T,
+}
+
+/// 交点で分割した区間
+#[derive(Clone, Copy)]
+struct Piece<T: Scalar> {
+    quad: QuadraticBezier<T>,
+    /// 分割前の区間の番号と、その区間上の媒介変数の範囲 (進む向きに並べる)
+    edge: usize,
+    t0: T,
+    t1: T,
+}
+
+/// 閉じたパス同士の集合演算
+///
+/// 3 次ベジエは 2 次ベジエに変換し、交点で区間を分割してから、両側の内外が結果で変わる
+/// 区間だけをつなぎ直す。結果の輪郭は内側がすべて進行方向の `perp` の側になるよう向きを
+/// そろえるので、nonzero と even-odd のどちらで塗っても同じ形になる。
+/// 入力の開いた輪郭は始点まで直線で閉じているものとして扱う。
+pub fn boolean<T: Scalar>(a: &Path<T>, b: &Path<T>, op: BooleanOp, fill_rule: FillRule) -> Path<T> {
+    let quads_a = closed_quads(a);
+    let quads_b = closed_quads(b);
+    let edges: Vec<QuadraticBezier<T>> = quads_a.iter().chain(&quads_b).copied().collect();
+    let Some(tolerance) = tolerance(&edges) else {
+        return Path::new();
+    };
+
+    let inside = |p: Point<T>| {
+        op.apply(
+            fill_rule.is_inside(winding(&quads_a, p)),
+            fill_rule.is_inside(winding(&quads_b, p)),
+        )
+    };
+    let mut pieces = vec![];
+    for piece in split_edges(&edges, &tolerance) {
+        let mid = piece.quad.calc_point(vf(0.5)).unwrap();
+        let tangent = piece.quad.diff(vf(0.5));
+        let tangent = if tangent == Point::<T>::ZERO {
+            Point::<T>::new(piece.quad.x1 - piece.quad.x0, piece.quad.y1 - piece.quad.y0)
+        } else {
+            tangent
+        };
+        let normal = tangent.perp().normalize_or_zero() * tolerance.probe;
+        match (inside(mid + normal), inside(mid - normal)) {
+            (true, false) => pieces.push(piece),
+            (false, true) => pieces.push(Piece {
+                quad: piece.quad.reverse(),
+                t0: piece.t1,
+                t1: piece.t0,
+                ..piece
+            }),
+            _ => {}
+        }
+    }
+    remove_duplicates(&mut pieces, tolerance.snap);
+
+    let mut path = Path::new();
+    for chain in link(&edges, pieces, tolerance.snap) {
+        let mut chain = chain.into_iter();
+        let first = chain.next().unwrap();
+        path.move_to(first.x0, first.y0);
+        for q in std::iter::once(first).chain(chain) {
+            if is_flat(&q, tolerance.snap) {
+                path.line_to(q.x1, q.y1);
+            } else {
+                path.quad_to(q.cx0, q.cy0, q.x1, q.y1);
+            }
+        }
+        path.close();
+    }
+    path
+}
+
+/// すべての輪郭を閉じた 2 次ベジエの列にする
+fn closed_quads<T: Scalar>(path: &Path<T>) -> Vec<QuadraticBezier<T>> {
+    let mut quads = vec![];
+    for contour in &path.contours {
+        let (Some(first), Some(last)) = (contour.segments.first(), contour.segments.last()) else {
+            continue;
+        };
+        quads.extend(contour.segments.iter().flat_map(|s| s.to_quadratic()));
+        if first.start() != last.end() {
+            let (start, end) = (last.end(), first.start());
+            let line = Line {
+                x0: start.x(),
+                y0: start.y(),
+                x1: end.x(),
+                y1: end.y(),
+            };
+            quads.push(line.to_quadratic());
+        }
+    }
+    quads
+}
+
+fn tolerance<T: Scalar>(edges: &[QuadraticBezier<T>]) -> Option<Tolerance<T>> {
+    let (min, max) = edges
+        .iter()
+        .map(bounds)
+        .reduce(|a, b| (min_point::<T>(a.0, b.0), max_point::<T>(a.1, b.1)))?;
+    let size = max - min;
+    let scale = size.x().max(size.y()).max(max.x().abs()).max(max.y().abs());
+    let scale = scale.max(min.x().abs()).max(min.y().abs());
+    if scale.is_zero() {
+        return None;
+    }
+    let unit = scale * T::epsilon();
+    Some(Tolerance {
+        intersect: unit * vf(16.0),
+        snap: unit * vf(256.0),
+        probe: unit * vf(1024.0),
+    })
+}
+
+/// 区間同士の交点をすべて求めて分割する
+fn split_edges<T: Scalar>(edges: &[QuadraticBezier<T>], tolerance: &Tolerance<T>) -> Vec<Piece<T>> {
+    let mut splits: Vec<Vec<(T, Point<T>)>> = vec![vec![]; edges.len()];
+    let boxes: Vec<_> = edges.iter().map(bounds).collect();
+    for i in 0..edges.len() {
+        for j in i + 1..edges.len() {
+            if !overlaps(boxes[i], boxes[j], tolerance.snap) {
+                continue;
+            }
+            for (ta, tb, p) in intersections(&edges[i], &edges[j], tolerance) {
+                splits[i].push((ta, p));
+                splits[j].push((tb, p));
+            }
+        }
+    }
+
+    let mut pieces = vec![];
+    for (edge, (q, mut splits)) in edges.iter().zip(splits).enumerate() {
+        let start = Point::<T>::new(q.x0, q.y0);
+        let end = Point::<T>::new(q.x1, q.y1);
+        splits.retain(|(_, p)| {
+            p.distance(start) > tolerance.snap && p.distance(end) > tolerance.snap
+        });
+        splits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        splits.dedup_by(|a, b| a.1.distance(b.1) <= tolerance.snap);
+
+        let mut bounds = vec![(T::zero(), start)];
+        bounds.extend(splits);
+        bounds.push((T::one(), end));
+        for pair in bounds.windows(2) {
+            let ((t0, p0), (t1, p1)) = (pair[0], pair[1]);
+            let mut quad = q.subsection(t0, t1).unwrap();
+            (quad.x0, quad.y0, quad.x1, quad.y1) = (p0.x(), p0.y(), p1.x(), p1.y());
+            let control = Point::<T>::new(quad.cx0, quad.cy0);
+            if p0.distance(p1) <= tolerance.snap && p0.distance(control) <= tolerance.snap {
+                continue;
+            }
+            pieces.push(Piece { quad, edge, t0, t1 });
+        }
+    }
+    pieces
+}
+
+/// 2 つの区間の交点 (それぞれの媒介変数と位置)
+fn intersections<T: Scalar>(
+    a: &QuadraticBezier<T>,
+    b: &QuadraticBezier<T>,
+    tolerance: &Tolerance<T>,
+) -> Vec<(T, T, Point<T>)> {
+    if let Some(overlap) = coincident(a, b, tolerance.snap) {
+        return overlap;
+    }
+
+    let mut hits = vec![];
+    let whole = (T::zero(), T::one());
+    subdivide(a, whole, b, whole, tolerance, 0, &mut hits);
+    hits.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    // 交点の近くでは隣り合う小区間がまとめて見つかるので、つながっているものを 1 つにする
+    let point = |(ta, tb): (T, T)| {
+        let pa = a.calc_point(ta).unwrap();
+        let pb = b.calc_point(tb).unwrap();
+        (pa + pb) * vf(0.5)
+    };
+    let mut result: Vec<(T, T, Point<T>)> = vec![];
+    let mut group: Vec<(T, T)> = vec![];
+    for hit in hits {
+        if let Some(last) = group.last()
+            && point(*last).distance(point(hit)) > tolerance.snap
+        {
+            let (ta, tb) = group[group.len() / 2];
+            result.push((ta, tb, point((ta, tb))));
+            group.clear();
+        }
+        group.push(hit);
+    }
+    if !group.is_empty() {
+        let (ta, tb) = group[group.len() / 2];
+        result.push((ta, tb, point((ta, tb))));
+    }
+    result
+}
+
+fn subdivide<T: Scalar>(
+    a: &QuadraticBezier<T>,
+    ta: (T, T),
+    b: &QuadraticBezier<T>,
+    tb: (T, T),
+    tolerance: &Tolerance<T>,
+    depth: u32,
+    hits: &mut Vec<(T, T)>,
+) {
+    if !overlaps(bounds(a), bounds(b), tolerance.intersect) {
+        return;
+    }
+
+    // どちらも直線とみなせるなら線分の交点を直接求める
+    if is_flat(a, tolerance.intersect) && is_flat(b, tolerance.intersect) {
+        let (pa, da) = (
+            Point::<T>::new(a.x0, a.y0),
+            Point::<T>::new(a.x1 - a.x0, a.y1 - a.y0),
+        );
+        let (pb, db) = (
+            Point::<T>::new(b.x0, b.y0),
+            Point::<T>::new(b.x1 - b.x0, b.y1 - b.y0),
+        );
+        let denominator = da.perp_dot(db);
+        if denominator.is_zero() {
+            return;
+        }
+        let s = (pb - pa).perp_dot(db) / denominator;
+        let u = (pb - pa).perp_dot(da) / denominator;
+        let margin: T = vf(1e-9);
+        if (-margin..=T::one() + margin).contains(&s) && (-margin..=T::one() + margin).contains(&u)
+        {
+            let s = s.max(T::zero()).min(T::one());
+            let u = u.max(T::zero()).min(T::one());
+            hits.push((ta.0 + (ta.1 - ta.0) * s, tb.0 + (tb.1 - tb.0) * u));
+        }
+        return;
+    }
+
+    let half: T = vf(0.5);
+    let (size_a, size_b) = (size(a), size(b));
+    if depth >= MAX_INTERSECT_DEPTH
+        || (size_a <= tolerance.intersect && size_b <= tolerance.intersect)
+    {
+        hits.push(((ta.0 + ta.1) * half, (tb.0 + tb.1) * half));
+        return;
+    }
+
+    // 大きい方を半分にする
+    if size_a >= size_b {
+        let (a0, a1) = a.split(half).unwrap();
+        let mid = (ta.0 + ta.1) * half;
+        subdivide(&a0, (ta.0, mid), b, tb, tolerance, depth + 1, hits);
+        subdivide(&a1, (mid, ta.1), b, tb, tolerance, depth + 1, hits);
+    } else {
+        let (b0, b1) = b.split(half).unwrap();
+        let mid = (tb.0 + tb.1) * half;
+        subdivide(a, ta, &b0, (tb.0, mid), tolerance, depth + 1, hits);
+        subdivide(a, ta, &b1, (mid, tb.1), tolerance, depth + 1, hits);
+    }
+}
+
+/// 2 つの区間が重なっている場合は、重なりの両端を交点として返す
+fn coincident<T: Scalar>(
+    a: &QuadraticBezier<T>,
+    b: &QuadraticBezier<T>,
+    snap: T,
+) -> Option<Vec<(T, T, Point<T>)>> {
+    let (a0, a1) = (Point::<T>::new(a.x0, a.y0), Point::<T>::new(a.x1, a.y1));
+    let (b0, b1) = (Point::<T>::new(b.x0, b.y0), Point::<T>::new(b.x1, b.y1));
+    let mut ends = vec![];
+    for (ta, p) in [(T::zero(), a0), (T::one(), a1)] {
+        if let Some(tb) = project(b, p, snap) {
+            ends.push((ta, tb, p));
+        }
+    }
+    for (tb, p) in [(T::zero(), b0), (T::one(), b1)] {
+        if let Some(ta) = project(a, p, snap) {
+            ends.push((ta, tb, p));
+        }
+    }
+    ends.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+    let (first, last) = (*ends.first()?, *ends.last()?);
+    if first.2.distance(last.2) <= snap {
+        return None;
+    }
+    let half: T = vf(0.5);
+    let mid = a.calc_point((first.0 + last.0) * half).unwrap();
+    project(b, mid, snap)?;
+    Some(vec![first, last])
+}
+
+/// 曲線上で p から snap 以内にある位置の媒介変数
+fn project<T: Scalar>(q: &QuadraticBezier<T>, p: Point<T>, snap: T) -> Option<T> {
+    let step: T = vf(1.0 / PROJECT_STEPS as f64);
+    let mut t = (0..=PROJECT_STEPS)
+        .map(|i| vf::<T>(i as f64) * step)
+        .min_by(|x, y| {
+            let dx = q.calc_point(*x).unwrap().distance(p);
+            let dy = q.calc_point(*y).unwrap().distance(p);
+            dx.partial_cmp(&dy).unwrap()
+        })
+        .unwrap();
+    // Newton 法で最も近い位置に寄せる
+    let second = Point::<T>::new(
+        vf::<T>(2.0) * (q.x0 + q.x1 - vf::<T>(2.0) * q.cx0),
+        vf::<T>(2.0) * (q.y0 + q.y1 - vf::<T>(2.0) * q.cy0),
+    );
+    for _ in 0..8 {
+        let d = q.calc_point(t).unwrap() - p;
+        let first = q.diff(t);
+        let denominator = first.dot(first) + d.dot(second);
+        if denominator.is_zero() {
+            break;
+        }
+        t = (t - d.dot(first) / denominator)
+            .max(T::zero())
+            .min(T::one());
+    }
+    (q.calc_point(t).unwrap().distance(p) <= snap).then_some(t)
+}
+
+/// 向きまで同じ区間が重なっていれば 1 つだけ残す
+fn remove_duplicates<T: Scalar>(pieces: &mut Vec<Piece<T>>, snap: T) {
+    let key = |q: &QuadraticBezier<T>| {
+        [
+            Point::<T>::new(q.x0, q.y0),
+            q.calc_point(vf(0.5)).unwrap(),
+            Point::<T>::new(q.x1, q.y1),
+        ]
+    };
+    let mut kept: Vec<Piece<T>> = vec![];
+    for piece in pieces.drain(..) {
+        let k = key(&piece.quad);
+        let duplicate = kept.iter().any(|other| {
+            key(&other.quad)
+                .iter()
+                .zip(&k)
+                .all(|(a, b)| a.distance(*b) <= snap)
+        });
+        if !duplicate {
+            kept.push(piece);
+        }
+    }
+    *pieces = kept;
+}
+
+/// 終点と始点が一致する区間をつないで閉じた輪郭にする
+fn link<T: Scalar>(
+    edges: &[QuadraticBezier<T>],
+    mut pieces: Vec<Piece<T>>,
+    snap: T,
+) -> Vec<Vec<QuadraticBezier<T>>> {
+    let start = |p: &Piece<T>| Point::<T>::new(p.quad.x0, p.quad.y0);
+    let end = |p: &Piece<T>| Point::<T>::new(p.quad.x1, p.quad.y1);
+
+    let mut chains = vec![];
+    while let Some(first) = pieces.pop() {
+        let mut chain: Vec<Piece<T>> = vec![first];
+        loop {
+            let current = *chain.last().unwrap();
+            if chain.len() > 1 && end(&current).distance(start(&chain[0])) <= snap {
+                break;
+            }
+            // 同じ区間の続きがあればそれを優先し、なければ最も近い始点を選ぶ
+            let next = pieces
+                .iter()
+                .position(|p| p.edge == current.edge && p.t0 == current.t1)
+                .or_else(|| {
+                    pieces
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| (i, start(p).distance(end(&current))))
+                        .filter(|(_, d)| *d <= snap)
+                        .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap())
+                        .map(|(i, _)| i)
+                });
+            let Some(next) = next else {
+                break;
+            };
+            chain.push(pieces.swap_remove(next));
+        }
+
+        // 同じ区間から続けて切り出した部分は元の曲線に戻す
+        let mut merged: Vec<Piece<T>> = vec![];
+        for piece in chain {
+            match merged.last_mut() {
+                Some(last) if last.edge == piece.edge && last.t1 == piece.t0 => {
+                    let (t0, t1) = (last.t0, piece.t1);
+                    let mut quad = if t0 < t1 {
+                        edges[piece.edge].subsection(t0, t1).unwrap()
+                    } else {
+                        edges[piece.edge].subsection(t1, t0).unwrap().reverse()
+                    };
+                    (quad.x0, quad.y0) = (last.quad.x0, last.quad.y0);
+                    (quad.x1, quad.y1) = (piece.quad.x1, piece.quad.y1);
+                    *last = Piece { quad, t1, ..*last };
+                }
+                _ => merged.push(piece),
+            }
+        }
+
+        // 一直線に並んだ直線をまとめ、つなぎ目の座標をそろえる
+        let mut quads: Vec<QuadraticBezier<T>> = vec![];
+        for piece in merged {
+            let mut quad = piece.quad;
+            if let Some(last) = quads.last_mut() {
+                (quad.x0, quad.y0) = (last.x1, last.y1);
+                if is_flat(last, snap) && is_flat(&quad, snap) && is_collinear(last, &quad, snap) {
+                    (last.x1, last.y1) = (quad.x1, quad.y1);
+                    (last.cx0, last.cy0) =
+                        ((last.x0 + last.x1) * vf(0.5), (last.y0 + last.y1) * vf(0.5));
+                    continue;
+                }
+            }
+            quads.push(quad);
+        }
+        if quads.len() > 1 {
+            let (first, last) = (quads[0], quads[quads.len() - 1]);
+            if is_flat(&first, snap) && is_flat(&last, snap) && is_collinear(&last, &first, snap) {
+                let last = quads.pop().unwrap();
+                (quads[0].x0, quads[0].y0) = (last.x0, last.y0);
+                (quads[0].cx0, quads[0].cy0) = (
+                    (quads[0].x0 + quads[0].x1) * vf(0.5),
+                    (quads[0].y0 + quads[0].y1) * vf(0.5),
+                );
+            }
+            let n = quads.len();
+            (quads[n - 1].x1, quads[n - 1].y1) = (quads[0].x0, quads[0].y0);
+        }
+        chains.push(quads);
+    }
+    chains
+}
+
+fn winding<T: Scalar>(quads: &[QuadraticBezier<T>], p: Point<T>) -> i32 {
+    quads.iter().map(|q| q.winding(p)).sum()
+}
+
+fn bounds<T: Scalar>(q: &QuadraticBezier<T>) -> (Point<T>, Point<T>) {
+    let points = [
+        Point::<T>::new(q.x0, q.y0),
+        Point::<T>::new(q.cx0, q.cy0),
+        Point::<T>::new(q.x1, q.y1),
+    ];
+    (
+        points.into_iter().reduce(min_point::<T>).unwrap(),
+        points.into_iter().reduce(max_point::<T>).unwrap(),
+    )
+}
+
+fn min_point<T: Scalar>(a: Point<T>, b: Point<T>) -> Point<T> {
+    Point::<T>::new(a.x().min(b.x()), a.y().min(b.y()))
+}
+
+fn max_point<T: Scalar>(a: Point<T>, b: Point<T>) -> Point<T> {
+    Point::<T>::new(a.x().max(b.x()), a.y().max(b.y()))
+}
+
+fn overlaps<T: Scalar>(a: (Point<T>, Point<T>), b: (Point<T>, Point<T>), margin: T) -> bool {
+    a.0.x() <= b.1.x() + margin
+        && b.0.x() <= a.1.x() + margin
+        && a.0.y() <= b.1.y() + margin
+        && b.0.y() <= a.1.y() + margin
+}
+
+fn size<T: Scalar>(q: &QuadraticBezier<T>) -> T {
+    let (min, max) = bounds(q);
+    (max.x() - min.x()).max(max.y() - min.y())
+}
+
+/// 制御点が始点と終点を結ぶ線分から tolerance 以内にあるかどうか
+fn is_flat<T: Scalar>(q: &QuadraticBezier<T>, tolerance: T) -> bool {
+    let p0 = Point::<T>::new(q.x0, q.y0);
+    let chord = Point::<T>::new(q.x1, q.y1) - p0;
+    let control = Point::<T>::new(q.cx0, q.cy0) - p0;
+    let length = chord.length();
+    if length.is_zero() {
+        return control.length() <= tolerance;
+    }
+    let along = control.dot(chord) / length;
+    (control.perp_dot(chord) / length).abs() <= tolerance
+        && along >= -tolerance
+        && along <= length + tolerance
+}
+
+/// 2 つの直線が同じ向きで一直線に並んでいるかどうか
+fn is_collinear<T: Scalar>(a: &QuadraticBezier<T>, b: &QuadraticBezier<T>, tolerance: T) -> bool {
+    let da = Point::<T>::new(a.x1 - a.x0, a.y1 - a.y0);
+    let db = Point::<T>::new(b.x1 - b.x0, b.y1 - b.y0);
+    let length = da.length().max(db.length());
+    !length.is_zero() && (da.perp_dot(db) / length).abs() <= tolerance && da.dot(db) > T::zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::*;
+    use crate::{path::Segment, svg::parse_path};
+
+    /// 輪郭の符号付き面積
+    fn area(path: &Path<f64>) -> f64 {
+        path.contours
+            .iter()
+            .flat_map(|c| c.segments.iter().flat_map(|s| s.to_quadratic()))
+            .map(|q| {
+                let chord = q.x0 * q.y1 - q.x1 * q.y0;
+                let tri = (q.cx0 - q.x0) * (q.y1 - q.y0) - (q.x1 - q.x0) * (q.cy0 - q.y0);
+                chord / 2.0 + tri / 3.0
+            })
+            .sum()
+    }
+
+    fn path(d: &str) -> Path<f64> {
+        parse_path(d).unwrap()
+    }
+
+    /// 格子上の点で、結果の内外が入力の内外から決まるものと一致するか確かめる
+    fn assert_matches_op(a: &Path<f64>, b: &Path<f64>, op: BooleanOp, fill_rule: FillRule) {
+        let result = boolean(a, b, op, fill_rule);
+        let quads_a = closed_quads(a);
+        let quads_b = closed_quads(b);
+        let quads_r = closed_quads(&result);
+        for y in 0..60 {
+            for x in 0..60 {
+                let p = DVec2::new(x as f64 * 2.0 + 0.37, y as f64 * 2.0 + 0.61);
+                let near = quads_a.iter().chain(&quads_b).any(|q| {
+                    (0..=64).any(|i| q.calc_point(i as f64 / 64.0).unwrap().distance(p) < 0.2)
+                });
+                if near {
+                    continue;
+                }
+                let expected = op.apply(
+                    fill_rule.is_inside(winding(&quads_a, p)),
+                    fill_rule.is_inside(winding(&quads_b, p)),
+                );
+                let w = winding(&quads_r, p);
+                assert!(w == 0 || w == 1, "{op:?} {p} winding {w}");
+                assert_eq!(w == 1, expected, "{op:?} {fill_rule:?} {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let a = path("M0 0 H2 V2 H0 Z");
+        let b = path("M1 1 H3 V3 H1 Z");
+        let expected = [
+            (BooleanOp::Union, 7.0),
+            (BooleanOp::Intersection, 1.0),
+            (BooleanOp::Difference, 3.0),
+            (BooleanOp::Xor, 6.0),
+        ];
+        for (op, expected) in expected {
+            let result = boolean(&a, &b, op, FillRule::NonZero);
+            assert!((area(&result) - expected).abs() < 1e-9, "{op:?}");
+        }
+        let union = boolean(&a, &b, BooleanOp::Union, FillRule::NonZero);
+        assert_eq!(union.contours.len(), 1);
+        assert_eq!(union.contours[0].segments.len(), 8);
+        assert!(union.contours[0].closed);
+
+        let a = parse_path::<f32>("M0 0 H2 V2 H0 Z").unwrap();
+        let b = parse_path::<f32>("M1 1 H3 V3 H1 Z").unwrap();
+        let union = boolean(&a, &b, BooleanOp::Union, FillRule::NonZero);
+        assert_eq!(union.contours[0].segments.len(), 8);
+    }
+
+    #[test]
+    fn shared_edges_are_merged() {
+        let a = path("M0 0 H2 V2 H0 Z");
+        let b = path("M2 0 H4 V2 H2 Z");
+        let union = boolean(&a, &b, BooleanOp::Union, FillRule::NonZero);
+        assert_eq!(union.contours.len(), 1);
+        assert_eq!(union.contours[0].segments.len(), 4);
+        assert!((area(&union) - 8.0).abs() < 1e-9);
+
+        let intersection = boolean(&a, &b, BooleanOp::Intersection, FillRule::NonZero);
+        assert!(intersection.contours.is_empty());
+    }
+
+    #[test]
+    fn difference_makes_hole() {
+        let a = path("M0 0 H10 V10 H0 Z");
+        let b = path("M3 3 H7 V7 H3 Z");
+        let result = boolean(&a, &b, BooleanOp::Difference, FillRule::NonZero);
+        assert_eq!(result.contours.len(), 2);
+        assert!((area(&result) - 84.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn curves_are_kept() {
+        let a = path("M10 60 A50 50 0 0 1 110 60 A50 50 0 0 1 10 60 Z");
+        let b = path("M40 20 Q90 140 110 20 Z M20 80 H100 V100 H20 Z");
+        for op in [
+            BooleanOp::Union,
+            BooleanOp::Intersection,
+            BooleanOp::Difference,
+            BooleanOp::Xor,
+        ] {
+            assert_matches_op(&a, &b, op, FillRule::NonZero);
+        }
+        let result = boolean(&a, &b, BooleanOp::Union, FillRule::NonZero);
+        assert!(
+            result.contours[0]
+                .segments
+                .iter()
+                .any(|s| matches!(s, Segment::Quadratic(_)))
+        );
+    }
+
+    #[test]
+    fn fill_rules() {
+        // 同じ向きに重なった 2 つの正方形
+        let a = path("M10 10 H70 V70 H10 Z M40 40 H100 V100 H40 Z");
+        let b = path("M0 50 H110 V60 H0 Z");
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            for op in [BooleanOp::Union, BooleanOp::Difference, BooleanOp::Xor] {
+                assert_matches_op(&a, &b, op, fill_rule);
+            }
+        }
+        let even_odd = boolean(&a, &Path::new(), BooleanOp::Union, FillRule::EvenOdd);
+        assert!((area(&even_odd).abs() - (3600.0 * 2.0 - 900.0 * 2.0)).abs() < 1e-6);
+        let nonzero = boolean(&a, &Path::new(), BooleanOp::Union, FillRule::NonZero);
+        assert!((area(&nonzero).abs() - (3600.0 * 2.0 - 900.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn empty_inputs() {
+        let empty = Path::<f32>::new();
+        assert!(
+            boolean(&empty, &empty, BooleanOp::Union, FillRule::NonZero)
+                .contours
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    CubicBezier,
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+/// 媒介変数を修正し直す回数の上限
+const MAX_REPARAMETERIZE: usize = 4;
+
+/// 曲線当てはめの設定
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FitOptions<T: Scalar = f32> {
+    /// 入力点と曲線の距離の許容値
+    pub tolerance: T,
+    /// 進行方向がこの角度 (ラジアン) より大きく曲がる点を角として分割する
+    pub corner_angle: T,
+}
+
+impl<T: Scalar> Default for FitOptions<T> {
+    fn default() -> Self {
+        Self {
+            tolerance: T::one(),
+            corner_angle: T::FRAC_PI_3(),
+        }
+    }
+}
+
+/// 点列を通る滑らかな 3 次ベジエの列を求める (Schneider の方法)
+///
+/// 角で分割した区間ごとに最小二乗法で 3 次ベジエを当てはめ、許容値を超える点があれば
+/// その点で分割して再帰する。結果は `CubicBezier::to_quadratic` でそのまま 2 次ベジエにできる。
+pub fn fit_curve<T: Scalar>(points: &[Point<T>], options: &FitOptions<T>) -> Vec<CubicBezier<T>> {
+    let mut points = points.to_vec();
+    points.dedup();
+    let mut curves = vec![];
+    if points.len() < 2 {
+        return curves;
+    }
+    let window = options.tolerance;
+    let corners = find_corners::<T>(&points, window, options.corner_angle);
+    for run in corners.windows(2) {
+        let run = &points[run[0]..=run[1]];
+        let t1 = direction::<T>(run, 0, 1, window);
+        let t2 = direction::<T>(run, run.len() - 1, -1, window);
+        let error = options.tolerance * options.tolerance;
+        fit_cubic::<T>(&mut curves, run, t1, t2, error, window);
+    }
+    curves
+}
+
+/// 区間の境目になる添字 (両端と角) を返す
+fn find_corners<T: Scalar>(points: &[Point<T>], window: T, corner_angle: T) -> Vec<usize> {
+    let last = points.len() - 1;
+    let angles: Vec<T> = (0..=last)
+        .map(|i| {
+            if i == 0 || i == last {
+                return T::zero();
+            }
+            let incoming = -direction::<T>(points, i, -1, window);
+            let outgoing = direction::<T>(points, i, 1, window);
+            incoming.angle_to(outgoing).abs()
+        })
+        .collect();
+
+    let mut corners = vec![0];
+    for i in 1..last {
+        // 角の前後の数点も大きく曲がって見えるので、極大の点だけを角とする
+        let from = i.saturating_sub(2).max(1);
+        let to = (i + 2).min(last - 1);
+        let is_peak =
+            (from..=to).all(|j| angles[j] < angles[i] || (angles[j] == angles[i] && j >= i));
+        if angles[i] > corner_angle && is_peak {
+            corners.push(i);
+        }
+    }
+    corners.push(last);
+    corners
+}
+
+/// i 番目の点から step の向きに window 以上離れた点への単位ベクトル
+///
+/// 隣の点だけを見るとノイズで向きが大きくぶれるので、ある程度離れた点を使う。
+fn direction<T: Scalar>(points: &[Point<T>], i: usize, step: isize, window: T) -> Point<T> {
+    let origin = points[i];
+    let mut j = i as isize + step;
+    let mut target = origin;
+    while 0 <= j && (j as usize) < points.len() {
+        target = points[j as usize];
+        if origin.distance(target) >= window {
+            break;
+        }
+        j += step;
+    }
+    (target - origin).normalize_or_zero()
+}
+
+fn fit_cubic<T: Scalar>(
+    curves: &mut Vec<CubicBezier<T>>,
+    points: &[Point<T>],
+    t1: Point<T>,
+    t2: Point<T>,
+    error: T,
+    window: T,
+) {
+    let first = points[0];
+    let last = points[points.len() - 1];
+    if points.len() == 2 {
+        let d = first.distance(last) / vf(3.0);
+        curves.push(to_cubic::<T>([first, first + t1 * d, last + t2 * d, last]));
+        return;
+    }
+
+    let mut u = chord_length_parameterize::<T>(points);
+    let mut bezier = generate_bezier::<T>(points, &u, t1, t2);
+    let (mut max_error, mut split) = compute_max_error::<T>(points, &bezier, &u);
+    if max_error < error {
+        curves.push(to_cubic::<T>(bezier));
+        return;
+    }
+
+    // 誤差が小さければ媒介変数を修正して当てはめ直す
+    if max_error < error * vf(4.0) {
+        for _ in 0..MAX_REPARAMETERIZE {
+            u = reparameterize::<T>(points, &u, &bezier);
+            bezier = generate_bezier::<T>(points, &u, t1, t2);
+            (max_error, split) = compute_max_error::<T>(points, &bezier, &u);
+            if max_error < error {
+                curves.push(to_cubic::<T>(bezier));
+                return;
+            }
+        }
+    }
+
+    let center = (direction::<T>(points, split, -1, window)
+        - direction::<T>(points, split, 1, window))
+    .normalize_or_zero();
+    fit_cubic::<T>(curves, &points[..=split], t1, center, error, window);
+    fit_cubic::<T>(curves, &points[split..], -center, t2, error, window);
+}
+
+fn chord_length_parameterize<T: Scalar>(points: &[Point<T>]) -> Vec<T> {
+    let mut u = vec![T::zero()];
+    for pair in points.windows(2) {
+        let prev = *u.last().unwrap();
+        u.push(prev + pair[0].distance(pair[1]));
+    }
+    let total = *u.last().unwrap();
+    u.iter().map(|v| *v / total).collect()
+}
+
+/// 端点と端点での向きを固定し、制御点までの距離を最小二乗法で求める
+fn generate_bezier<T: Scalar>(
+    points: &[Point<T>],
+    u: &[T],
+    t1: Point<T>,
+    t2: Point<T>,
+) -> [Point<T>; 4] {
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let mut c = [[T::zero(); 2]; 2];
+    let mut x = [T::zero(); 2];
+    for (p, u) in points.iter().zip(u) {
+        let [b0, b1, b2, b3] = bernstein(*u);
+        let a1 = t1 * b1;
+        let a2 = t2 * b2;
+        c[0][0] = c[0][0] + a1.dot(a1);
+        c[0][1] = c[0][1] + a1.dot(a2);
+        c[1][1] = c[1][1] + a2.dot(a2);
+        let tmp = *p - (first * (b0 + b1) + last * (b2 + b3));
+        x[0] = x[0] + a1.dot(tmp);
+        x[1] = x[1] + a2.dot(tmp);
+    }
+    c[1][0] = c[0][1];
+
+    let det = c[0][0] * c[1][1] - c[0][1] * c[1][0];
+    let (alpha1, alpha2) = if det.is_zero() {
+        (T::zero(), T::zero())
+    } else {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    };
+
+    // 制御点が端点に重なったり逆向きになったりする場合は弦の 1/3 の位置に置く
+    let length = first.distance(last);
+    let epsilon = length * vf(1e-6);
+    if alpha1 < epsilon || alpha2 < epsilon {
+        let d = length / vf(3.0);
+        return [first, first + t1 * d, last + t2 * d, last];
+    }
+    [first, first + t1 * alpha1, last + t2 * alpha2, last]
+}
+
+/// Newton 法で各点に最も近い曲線上の位置へ媒介変数を動かす
+fn reparameterize<T: Scalar>(points: &[Point<T>], u: &[T], bezier: &[Point<T>; 4]) -> Vec<T> {
+    let three: T = vf(3.0);
+    let two: T = vf(2.0);
+    let d1 = [0, 1, 2].map(|i| (bezier[i + 1] - bezier[i]) * three);
+    let d2 = [0, 1].map(|i| (d1[i + 1] - d1[i]) * two);
+    points
+        .iter()
+        .zip(u)
+        .map(|(p, u)| {
+            let q = eval::<T>(bezier, *u);
+            let q1 = eval_quadratic::<T>(&d1, *u);
+            let q2 = d2[0].lerp(d2[1], *u);
+            let diff = q - *p;
+            let numerator = diff.dot(q1);
+            let denominator = q1.dot(q1) + diff.dot(q2);
+            if denominator.is_zero() {
+                *u
+            } else {
+                (*u - numerator / denominator).max(T::zero()).min(T::one())
+            }
+        })
+        .collect()
+}
+
+/// 最大の二乗誤差とその点の添字
+fn compute_max_error<T: Scalar>(
+    points: &[Point<T>],
+    bezier: &[Point<T>; 4],
+    u: &[T],
+) -> (T, usize) {
+    let mut max = T::zero();
+    let mut split = points.len() / 2;
+    for i in 1..points.len() - 1 {
+        let d = eval::<T>(bezier, u[i]) - points[i];
+        let error = d.dot(d);
+        if error >= max {
+            max = error;
+            split = i;
+        }
+    }
+    (max, split)
+}
+
+fn bernstein<T: Scalar>(t: T) -> [T; 4] {
+    let three: T = vf(3.0);
+    let s = T::one() - t;
+    [s * s * s, three * s * s * t, three * s * t * t, t * t * t]
+}
+
+fn eval<T: Scalar>(bezier: &[Point<T>; 4], t: T) -> Point<T> {
+    let [b0, b1, b2, b3] = bernstein(t);
+    bezier[0] * b0 + bezier[1] * b1 + bezier[2] * b2 + bezier[3] * b3
+}
+
+fn eval_quadratic<T: Scalar>(bezier: &[Point<T>; 3], t: T) -> Point<T> {
+    let s = T::one() - t;
+    bezier[0] * (s * s) + bezier[1] * (vf::<T>(2.0) * s * t) + bezier[2] * (t * t)
+}
+
+fn to_cubic<T: Scalar>(bezier: [Point<T>; 4]) -> CubicBezier<T> {
+    CubicBezier {
+        x0: bezier[0].x(),
+        y0: bezier[0].y(),
+        cx0: bezier[1].x(),
+        cy0: bezier[1].y(),
+        cx1: bezier[2].x(),
+        cy1: bezier[2].y(),
+        x1: bezier[3].x(),
+        y1: bezier[3].y(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::*;
+
+    /// 再現性のあるノイズ (xorshift)
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+        }
+    }
+
+    /// 点から曲線列までの距離 (曲線を細かい折れ線にして測る)
+    fn distance(curves: &[CubicBezier<f64>], p: DVec2) -> f64 {
+        curves
+            .iter()
+            .flat_map(|c| (0..=200).map(|i| c.calc_point(i as f64 / 200.0).unwrap()))
+            .map(|q| q.distance(p))
+            .fold(f64::MAX, f64::min)
+    }
+
+    fn assert_connected(curves: &[CubicBezier<f64>], points: &[DVec2]) {
+        assert_eq!(DVec2::new(curves[0].x0, curves[0].y0), points[0]);
+        let last = curves.last().unwrap();
+        assert_eq!(DVec2::new(last.x1, last.y1), *points.last().unwrap());
+        for pair in curves.windows(2) {
+            assert_eq!((pair[0].x1, pair[0].y1), (pair[1].x0, pair[1].y0));
+        }
+    }
+
+    #[test]
+    fn noisy_circle_arc_is_smooth() {
+        let mut noise = Noise(0x9e37_79b9_7f4a_7c15);
+        let points: Vec<DVec2> = (0..=200)
+            .map(|i| {
+                let a = i as f64 / 200.0 * std::f64::consts::PI * 1.5;
+                DVec2::new(a.cos(), a.sin()) * 100.0 + DVec2::new(noise.next(), noise.next()) * 0.4
+            })
+            .collect();
+        let options = FitOptions {
+            tolerance: 1.0,
+            ..Default::default()
+        };
+        let curves = fit_curve(&points, &options);
+        assert_connected(&curves, &points);
+        assert!(curves.len() < 10, "{}", curves.len());
+        // つなぎ目で向きがそろっている
+        for pair in curves.windows(2) {
+            let incoming = DVec2::new(pair[0].x1 - pair[0].cx1, pair[0].y1 - pair[0].cy1);
+            let outgoing = DVec2::new(pair[1].cx0 - pair[1].x0, pair[1].cy0 - pair[1].y0);
+            assert!(incoming.angle_to(outgoing).abs() < 1e-6);
+        }
+        for p in &points {
+            assert!(distance(&curves, *p) < 1.0);
+        }
+        // 曲線側も円から離れない
+        for c in &curves {
+            for i in 0..=20 {
+                let q = c.calc_point(i as f64 / 20.0).unwrap();
+                assert!((q.length() - 100.0).abs() < 1.5, "{q}");
+            }
+        }
+    }
+
+    #[test]
+    fn corners_split_curves() {
+        let mut points = vec![];
+        for i in 0..=50 {
+            points.push(DVec2::new(i as f64, 0.0));
+        }
+        for i in 1..=50 {
+            points.push(DVec2::new(50.0, i as f64));
+        }
+        let curves = fit_curve(&points, &FitOptions::default());
+        assert_connected(&curves, &points);
+        assert_eq!(curves.len(), 2);
+        assert_eq!((curves[0].x1, curves[0].y1), (50.0, 0.0));
+        for p in &points {
+            assert!(distance(&curves, *p) < 1.0);
+        }
+    }
+
+    #[test]
+    fn s_curve_is_split_where_error_is_large() {
+        let points: Vec<DVec2> = (0..=300)
+            .map(|i| {
+                let x = i as f64;
+                DVec2::new(x, (x / 300.0 * std::f64::consts::TAU * 2.0).sin() * 40.0)
+            })
+            .collect();
+        let options = FitOptions {
+            tolerance: 0.5,
+            ..Default::default()
+        };
+        let curves = fit_curve(&points, &options);
+        assert_connected(&curves, &points);
+        assert!(curves.len() > 1);
+        for p in &points {
+            assert!(distance(&curves, *p) < 0.5);
+        }
+    }
+
+    #[test]
+    fn few_points() {
+        let options = FitOptions::<f32>::default();
+        assert!(fit_curve(&[], &options).is_empty());
+        assert!(fit_curve(&[glam::Vec2::ONE, glam::Vec2::ONE], &options).is_empty());
+        let curves = fit_curve(&[glam::Vec2::ZERO, glam::Vec2::new(3.0, 0.0)], &options);
+        assert_eq!(curves.len(), 1);
+        assert_eq!((curves[0].cx0, curves[0].cx1), (1.0, 2.0));
+    }
+}
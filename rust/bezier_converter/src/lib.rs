@@ -1,157 +1,311 @@
-use glam::Vec2;
+use scalar::{Point, Scalar, Vector, vf};
+use transform::Affine;
+
+pub mod boolean;
+pub mod fit;
+pub mod path;
+pub mod scalar;
+pub mod stroke;
+pub mod svg;
+pub mod transform;
+pub mod triangulate;
+pub mod winding;
 
 /// 以下のサイトで提示されている 3 次ベジエ → 2 次ベジエへの 変換を実装している
 /// http://nutsu.com/blog/2008/021520_as_bezierconvert.html
-pub struct QuadraticBezier {
-    pub x0: f32,
-    pub y0: f32,
-    pub x1: f32,
-    pub y1: f32,
-
-    pub cx0: f32,
-    pub cy0: f32,
+///
+/// 座標の型は f32 と f64 から選べる。省略した場合は f32 になる。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuadraticBezier<T: Scalar = f32> {
+    pub x0: T,
+    pub y0: T,
+    pub x1: T,
+    pub y1: T,
+
+    pub cx0: T,
+    pub cy0: T,
 }
 
-impl QuadraticBezier {
-    pub fn calc_point(&self, t: f32) -> Option<Vec2> {
-        if !(0.0..=1.0).contains(&t) {
+impl<T: Scalar> QuadraticBezier<T> {
+    /// t = 0 で始点、t = 1 で終点になる曲線上の点を返す
+    pub fn calc_point(&self, t: T) -> Option<Point<T>> {
+        if !(T::zero()..=T::one()).contains(&t) {
             return None;
         }
 
-        let t_rest = 1.0 - t;
+        let t_rest = T::one() - t;
 
-        let mx0 = self.x0 * t + (self.cx0 * t_rest);
-        let my0 = self.y0 * t + (self.cy0 * t_rest);
+        let mx0 = self.x0 * t_rest + (self.cx0 * t);
+        let my0 = self.y0 * t_rest + (self.cy0 * t);
 
-        let mx1 = self.cx0 * t + (self.x1 * t_rest);
-        let my1 = self.cy0 * t + (self.y1 * t_rest);
+        let mx1 = self.cx0 * t_rest + (self.x1 * t);
+        let my1 = self.cy0 * t_rest + (self.y1 * t);
 
-        Some(Vec2 {
-            x: mx0 * t + mx1 * t_rest,
-            y: my0 * t + my1 * t_rest,
-        })
+        Some(Point::<T>::new(
+            mx0 * t_rest + mx1 * t,
+            my0 * t_rest + my1 * t,
+        ))
     }
 
-    fn diff(&self, t: f32) -> Vec2 {
-        Vec2::new(
-            2.0 * (t * (self.x0 + self.x1 - 2.0 * self.cx0) - self.x0 + self.cx0),
-            2.0 * (t * (self.y0 + self.y1 - 2.0 * self.cy0) - self.y0 + self.cy0),
+    fn diff(&self, t: T) -> Point<T> {
+        let two: T = vf(2.0);
+        Point::<T>::new(
+            two * (t * (self.x0 + self.x1 - two * self.cx0) - self.x0 + self.cx0),
+            two * (t * (self.y0 + self.y1 - two * self.cy0) - self.y0 + self.cy0),
         )
     }
-    fn length(&self) -> f32 {
-        let kx = self.x0 + self.x1 - 2.0 * self.cx0;
-        let ky = self.y0 + self.y1 - 2.0 * self.cy0;
+
+    /// 曲線の長さ
+    pub fn length(&self) -> T {
+        let two: T = vf(2.0);
+        let kx = self.x0 + self.x1 - two * self.cx0;
+        let ky = self.y0 + self.y1 - two * self.cy0;
         let ax = -self.x0 + self.cx0;
         let ay = -self.y0 + self.cy0;
-        if kx == 0.0 && ky == 0.0 {
-            return 0.0;
+        if kx.is_zero() && ky.is_zero() {
+            // 制御点が中点にある直線
+            return two * (ax * ax + ay * ay).sqrt();
         }
 
         let xy = kx * kx + ky * ky;
         let b = (ax * kx + ay * ky) / xy;
         let c = (ax * ax + ay * ay) / xy - b * b;
-        let (c, cs, cs2) = if c > 1e-10 {
+        let (c, cs, cs2) = if c > vf(1e-10) {
             let cs = (c).sqrt();
-            let cs2 = 0.0;
+            let cs2 = T::zero();
             (c, cs, cs2)
         } else {
-            let cs = 1.0;
-            let cs2 = 1.0;
-            (0.0, cs, cs2)
+            let cs = T::one();
+            let cs2 = T::one();
+            (T::zero(), cs, cs2)
         };
 
         //長さ
-        let init = Self::integrate_f(0.0, b, c, xy, cs, cs2);
+        let init = Self::integrate_f(T::zero(), b, c, xy, cs, cs2);
 
-        Self::integrate(1.0, init, b, c, xy, cs, cs2)
+        Self::integrate(T::one(), init, b, c, xy, cs, cs2)
     }
 
-    fn integrate(t: f32, init: f32, b: f32, c: f32, xy: f32, cs: f32, cs2: f32) -> f32 {
+    fn integrate(t: T, init: T, b: T, c: T, xy: T, cs: T, cs2: T) -> T {
         Self::integrate_f(t, b, c, xy, cs, cs2) - init
     }
 
-    fn integrate_f(t: f32, b: f32, c: f32, xy: f32, cs: f32, cs2: f32) -> f32 {
-        let bt: f32 = b + t;
-        let bts: f32 = (bt * bt + c).sqrt();
-        (xy).sqrt() * (bts * bt + c * ((bt + bts) / cs + cs2).log10())
+    fn integrate_f(t: T, b: T, c: T, xy: T, cs: T, cs2: T) -> T {
+        let bt: T = b + t;
+        let bts: T = (bt * bt + c).sqrt();
+        (xy).sqrt() * (bts * bt + c * ((bt + bts) / cs + cs2).ln())
     }
 }
 
-pub struct CubicBezier {
-    pub x0: f32,
-    pub y0: f32,
-    pub x1: f32,
-    pub y1: f32,
+impl<T: Scalar> QuadraticBezier<T> {
+    /// t の位置で前後 2 つの 2 次ベジエに分割する
+    pub fn split(&self, t: T) -> Option<(QuadraticBezier<T>, QuadraticBezier<T>)> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+
+        let tp = T::one() - t;
+        let ax = self.x0 * tp + self.cx0 * t;
+        let ay = self.y0 * tp + self.cy0 * t;
+        let bx = self.cx0 * tp + self.x1 * t;
+        let by = self.cy0 * tp + self.y1 * t;
+        let px = ax * tp + bx * t;
+        let py = ay * tp + by * t;
+        Some((
+            QuadraticBezier {
+                x0: self.x0,
+                y0: self.y0,
+                x1: px,
+                y1: py,
+                cx0: ax,
+                cy0: ay,
+            },
+            QuadraticBezier {
+                x0: px,
+                y0: py,
+                x1: self.x1,
+                y1: self.y1,
+                cx0: bx,
+                cy0: by,
+            },
+        ))
+    }
+
+    /// t0 から t1 までの区間を切り出す
+    pub fn subsection(&self, t0: T, t1: T) -> Option<QuadraticBezier<T>> {
+        if t0 < T::zero() || t0 > t1 || t1 > T::one() {
+            return None;
+        }
+        let (head, _) = self.split(t1)?;
+        if t1.is_zero() {
+            return Some(head);
+        }
+        head.split(t0 / t1).map(|(_, tail)| tail)
+    }
+
+    /// 始点と終点を入れ替える
+    pub fn reverse(&self) -> QuadraticBezier<T> {
+        QuadraticBezier {
+            x0: self.x1,
+            y0: self.y1,
+            x1: self.x0,
+            y1: self.y0,
+            cx0: self.cx0,
+            cy0: self.cy0,
+        }
+    }
+
+    /// 同じ曲線を表す 3 次ベジエに次数を上げる
+    pub fn elevate(&self) -> CubicBezier<T> {
+        let k: T = vf(2.0 / 3.0);
+        CubicBezier {
+            x0: self.x0,
+            y0: self.y0,
+            x1: self.x1,
+            y1: self.y1,
+            cx0: self.x0 + (self.cx0 - self.x0) * k,
+            cy0: self.y0 + (self.cy0 - self.y0) * k,
+            cx1: self.x1 + (self.cx0 - self.x1) * k,
+            cy1: self.y1 + (self.cy0 - self.y1) * k,
+        }
+    }
 
-    pub cx0: f32,
-    pub cy0: f32,
-    pub cx1: f32,
-    pub cy1: f32,
+    /// すべての点にアフィン変換を適用する
+    pub fn transform(&self, m: &Affine<T>) -> QuadraticBezier<T> {
+        let (x0, y0) = m.apply_xy(self.x0, self.y0);
+        let (x1, y1) = m.apply_xy(self.x1, self.y1);
+        let (cx0, cy0) = m.apply_xy(self.cx0, self.cy0);
+        QuadraticBezier {
+            x0,
+            y0,
+            x1,
+            y1,
+            cx0,
+            cy0,
+        }
+    }
 }
 
-impl CubicBezier {
-    pub fn calc_point(&self, t: f32) -> Option<Vec2> {
-        if !(0.0..=1.0).contains(&t) {
+impl<T: Scalar> QuadraticBezier<T> {
+    /// t での単位接線ベクトル
+    ///
+    /// 制御点が端点と重なって微分が 0 になる点では、近づく向きの極限を返す。
+    pub fn tangent(&self, t: T) -> Option<Point<T>> {
+        if !(T::zero()..=T::one()).contains(&t) {
             return None;
         }
+        Some(tangent_direction(t, &[self.diff(t), self.diff2()]))
+    }
 
-        let t_rest = 1.0 - t;
+    /// t での単位法線ベクトル (接線を `perp` で 90° 回したもの)
+    pub fn normal(&self, t: T) -> Option<Point<T>> {
+        self.tangent(t).map(|v| v.perp())
+    }
 
-        let mx0 = self.x0 * t + (self.cx0 * t_rest);
-        let my0 = self.y0 * t + (self.cy0 * t_rest);
+    /// t での符号付き曲率。法線の側に曲がるときに正になる
+    pub fn curvature(&self, t: T) -> Option<T> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+        Some(signed_curvature(self.diff(t), self.diff2()))
+    }
 
-        let mx1 = self.cx0 * t + (self.cx1 * t_rest);
-        let my1 = self.cy0 * t + (self.cy1 * t_rest);
+    /// 変曲点の媒介変数。2 次ベジエは曲がる向きが変わらないので常に空になる
+    pub fn inflections(&self) -> Vec<T> {
+        vec![]
+    }
 
-        let mx2 = self.cx1 * t + (self.x1 * t_rest);
-        let my2 = self.cy1 * t + (self.y1 * t_rest);
+    fn diff2(&self) -> Point<T> {
+        let two: T = vf(2.0);
+        Point::<T>::new(
+            two * (self.x0 + self.x1 - two * self.cx0),
+            two * (self.y0 + self.y1 - two * self.cy0),
+        )
+    }
+}
 
-        let nx0 = mx0 * t + (mx1 * t_rest);
-        let ny0 = my0 * t + (my1 * t_rest);
+/// 3 次ベジエ。座標の型は QuadraticBezier と同じく f32 と f64 から選べる
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicBezier<T: Scalar = f32> {
+    pub x0: T,
+    pub y0: T,
+    pub x1: T,
+    pub y1: T,
 
-        let nx1 = mx1 * t + (mx2 * t_rest);
-        let ny1 = my1 * t + (my2 * t_rest);
+    pub cx0: T,
+    pub cy0: T,
+    pub cx1: T,
+    pub cy1: T,
+}
 
-        Some(Vec2 {
-            x: nx0 * t + nx1 * t_rest,
-            y: ny0 * t + ny1 * t_rest,
-        })
+impl<T: Scalar> CubicBezier<T> {
+    /// t = 0 で始点、t = 1 で終点になる曲線上の点を返す
+    pub fn calc_point(&self, t: T) -> Option<Point<T>> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+
+        let t_rest = T::one() - t;
+
+        let mx0 = self.x0 * t_rest + (self.cx0 * t);
+        let my0 = self.y0 * t_rest + (self.cy0 * t);
+
+        let mx1 = self.cx0 * t_rest + (self.cx1 * t);
+        let my1 = self.cy0 * t_rest + (self.cy1 * t);
+
+        let mx2 = self.cx1 * t_rest + (self.x1 * t);
+        let my2 = self.cy1 * t_rest + (self.y1 * t);
+
+        let nx0 = mx0 * t_rest + (mx1 * t);
+        let ny0 = my0 * t_rest + (my1 * t);
+
+        let nx1 = mx1 * t_rest + (mx2 * t);
+        let ny1 = my1 * t_rest + (my2 * t);
+
+        Some(Point::<T>::new(
+            nx0 * t_rest + nx1 * t,
+            ny0 * t_rest + ny1 * t,
+        ))
     }
 
-    fn diff(&self, t: f32) -> Vec2 {
-        Vec2::new(
-            3.0 * (self.x1 - self.x0 - 3.0 * self.cx1 + 3.0 * self.cx0) * t * t
-                + 6.0 * (self.x0 + self.cx1 - 2.0 * self.cx0) * t
-                - 3.0 * self.x0
-                + 3.0 * self.cx0,
-            3.0 * (self.y1 - self.y0 - 3.0 * self.cy1 + 3.0 * self.cy0) * t * t
-                + 6.0 * (self.y0 + self.cy1 - 2.0 * self.cy0) * t
-                - 3.0 * self.y0
-                + 3.0 * self.cy0,
+    fn diff(&self, t: T) -> Point<T> {
+        let three: T = vf(3.0);
+        let six: T = vf(6.0);
+        Point::<T>::new(
+            three * (self.x1 - self.x0 - three * self.cx1 + three * self.cx0) * t * t
+                + six * (self.x0 + self.cx1 - vf::<T>(2.0) * self.cx0) * t
+                - three * self.x0
+                + three * self.cx0,
+            three * (self.y1 - self.y0 - three * self.cy1 + three * self.cy0) * t * t
+                + six * (self.y0 + self.cy1 - vf::<T>(2.0) * self.cy0) * t
+                - three * self.y0
+                + three * self.cy0,
         )
     }
 
-    const LENGTH_CAL_STEP: f32 = 100.0;
+    const LENGTH_CAL_STEP: f64 = 100.0;
 
-    fn length(&self) -> f32 {
-        let k = 1.0 / Self::LENGTH_CAL_STEP;
+    /// 曲線の長さ (100 分割した折れ線で近似する)
+    pub fn length(&self) -> T {
+        let three: T = vf(3.0);
+        let k: T = vf(1.0 / Self::LENGTH_CAL_STEP);
         let mut px0 = self.x0;
         let mut py0 = self.y0;
-        let mut length = 0.0;
+        let mut length = T::zero();
         (0..=100).for_each(|idx| {
-            let t = idx as f32 * k;
-            let tp = 1.0 - t;
+            let t = vf::<T>(idx as f64) * k;
+            let tp = T::one() - t;
             let px1 = self.x0 * tp * tp * tp
-                + 3.0 * self.cx0 * t * tp * tp
-                + 3.0 * self.cx1 * t * t * tp
+                + three * self.cx0 * t * tp * tp
+                + three * self.cx1 * t * t * tp
                 + self.x1 * t * t * t;
             let py1 = self.y0 * tp * tp * tp
-                + 3.0 * self.cy0 * t * tp * tp
-                + 3.0 * self.cy1 * t * t * tp
+                + three * self.cy0 * t * tp * tp
+                + three * self.cy1 * t * t * tp
                 + self.y1 * t * t * t;
             let dx = px1 - px0;
             let dy = py1 - py0;
-            length += (dx * dx + dy * dy).sqrt();
+            length = length + (dx * dx + dy * dy).sqrt();
             px0 = px1;
             py0 = py1;
         });
@@ -159,16 +313,37 @@ impl CubicBezier {
     }
 }
 
-impl CubicBezier {
-    pub fn to_quadratic(&self) -> Vec<QuadraticBezier> {
-        let err: f32 = 0.3 * 0.3;
+impl<T: Scalar> CubicBezier<T> {
+    /// 8 分割でも近似しきれない場合に半分に分けて再帰する回数。これを超えると誤差の上限から分割数を決める
+    const MAX_DEVIDE_DEPTH: u32 = 4;
+    /// 誤差の上限から等分するときに半分に分ける回数の上限。座標が NaN や無限大のときに止めるためのもの
+    const MAX_BOUND_HALVINGS: u32 = 10;
+    /// `to_quadratic` で使う元の曲線からのずれの許容値
+    pub const TOLERANCE: f64 = 0.3;
+    /// 近似の誤差を測るときに 2 次ベジエ 1 本あたりで調べる点の数
+    const DEVIATION_SAMPLES: usize = 8;
+
+    /// 2 次ベジエの列に変換する。元の曲線からのずれはおおむね 0.3 以内に収まる
+    pub fn to_quadratic(&self) -> Vec<QuadraticBezier<T>> {
+        self.to_quadratic_with_tolerance(vf(Self::TOLERANCE))
+    }
+
+    /// 元の曲線からのずれがおおむね `tolerance` 以内に収まるように 2 次ベジエの列に変換する
+    ///
+    /// 座標の大きさに合わせて選ぶ。小さくするほど分割が増える。
+    pub fn to_quadratic_with_tolerance(&self, tolerance: T) -> Vec<QuadraticBezier<T>> {
+        self.convert_with_depth(tolerance, 0)
+    }
+
+    fn convert_with_depth(&self, tolerance: T, depth: u32) -> Vec<QuadraticBezier<T>> {
+        let half: T = vf(0.5);
 
-        let diff = self.diff(0.5);
+        let diff = self.diff(half);
 
         // 単純置換
         if let Some(qb) = self.convert_quadratic_bezier()
-            && Self::compare_diff(diff, qb.diff(0.5))
-            && (self.length() - qb.length()).abs() < err
+            && Self::compare_diff(diff, qb.diff(half))
+            && self.fits(&[qb], tolerance)
         {
             return vec![qb];
         }
@@ -176,22 +351,20 @@ impl CubicBezier {
         // 2分割
         {
             let (q0, q1) = self.devide_quadratic_bezier();
-            if Self::compare_diff(diff, q0.diff(1.0))
-                && (self.length() - q0.length() - q1.length()).abs() < err
-            {
+            if Self::compare_diff(diff, q0.diff(T::one())) && self.fits(&[q0, q1], tolerance) {
                 return vec![q0, q1];
             }
         }
 
         // 4分割
         {
-            let (c0, c1) = self.split(0.5).unwrap();
+            let (c0, c1) = self.split(half).unwrap();
             let (q0, q1) = c0.devide_quadratic_bezier();
             let (q2, q3) = c1.devide_quadratic_bezier();
-            if Self::compare_diff(c0.diff(0.5), q0.diff(1.0))
-                && Self::compare_diff(c1.diff(0.5), q2.diff(1.0))
-                && (self.length() - q0.length() - q1.length() - q2.length() - q3.length()).abs()
-                    < err
+            if Self::compare_diff(c0.diff(half), q0.diff(T::one()))
+                && Self::compare_diff(c1.diff(half), q2.diff(T::one()))
+                && c0.fits(&[q0, q1], tolerance)
+                && c1.fits(&[q2, q3], tolerance)
             {
                 return vec![q0, q1, q2, q3];
             }
@@ -199,23 +372,17 @@ impl CubicBezier {
 
         // 6分割
         {
-            let (c0, c_rest) = self.split(0.333).unwrap();
-            let (c1, c2) = c_rest.split(0.5).unwrap();
+            let (c0, c_rest) = self.split(vf(0.333)).unwrap();
+            let (c1, c2) = c_rest.split(half).unwrap();
             let (q0, q1) = c0.devide_quadratic_bezier();
             let (q2, q3) = c1.devide_quadratic_bezier();
-            let (q4, q5) = c1.devide_quadratic_bezier();
-            if Self::compare_diff(c0.diff(0.5), q0.diff(1.0))
-                && Self::compare_diff(c1.diff(0.5), q2.diff(1.0))
-                && Self::compare_diff(c2.diff(0.5), q4.diff(1.0))
-                && (self.length()
-                    - q0.length()
-                    - q1.length()
-                    - q2.length()
-                    - q3.length()
-                    - q4.length()
-                    - q5.length())
-                .abs()
-                    < err
+            let (q4, q5) = c2.devide_quadratic_bezier();
+            if Self::compare_diff(c0.diff(half), q0.diff(T::one()))
+                && Self::compare_diff(c1.diff(half), q2.diff(T::one()))
+                && Self::compare_diff(c2.diff(half), q4.diff(T::one()))
+                && c0.fits(&[q0, q1], tolerance)
+                && c1.fits(&[q2, q3], tolerance)
+                && c2.fits(&[q4, q5], tolerance)
             {
                 return vec![q0, q1, q2, q3, q4, q5];
             }
@@ -223,26 +390,88 @@ impl CubicBezier {
 
         // 8分割
         {
-            let (c_rest0, c_rest1) = self.split(0.5).unwrap();
-            let (c0, c1) = c_rest0.split(0.5).unwrap();
-            let (c2, c3) = c_rest1.split(0.5).unwrap();
+            let (c_rest0, c_rest1) = self.split(half).unwrap();
+            let (c0, c1) = c_rest0.split(half).unwrap();
+            let (c2, c3) = c_rest1.split(half).unwrap();
             let (q0, q1) = c0.devide_quadratic_bezier();
             let (q2, q3) = c1.devide_quadratic_bezier();
             let (q4, q5) = c2.devide_quadratic_bezier();
             let (q6, q7) = c3.devide_quadratic_bezier();
-            vec![q0, q1, q2, q3, q4, q5, q6, q7]
+            if Self::compare_diff(c0.diff(half), q0.diff(T::one()))
+                && Self::compare_diff(c1.diff(half), q2.diff(T::one()))
+                && Self::compare_diff(c2.diff(half), q4.diff(T::one()))
+                && Self::compare_diff(c3.diff(half), q6.diff(T::one()))
+                && c0.fits(&[q0, q1], tolerance)
+                && c1.fits(&[q2, q3], tolerance)
+                && c2.fits(&[q4, q5], tolerance)
+                && c3.fits(&[q6, q7], tolerance)
+            {
+                return vec![q0, q1, q2, q3, q4, q5, q6, q7];
+            }
         }
+
+        if depth >= Self::MAX_DEVIDE_DEPTH {
+            return self.devide_by_error_bound(tolerance);
+        }
+
+        // 半分ずつ近似し直す
+        let (c0, c1) = self.split(half).unwrap();
+        let mut quads = c0.convert_with_depth(tolerance, depth + 1);
+        quads.extend(c1.convert_with_depth(tolerance, depth + 1));
+        quads
     }
 
-    fn devide_quadratic_bezier(&self) -> (QuadraticBezier, QuadraticBezier) {
-        let temp_point = 1.0 - 0.25;
+    /// 誤差の上限から決めた数に等分し、それぞれを `reduce` で近似する
+    ///
+    /// `reduce` のずれは √3/36 |P3 - 3P2 + 3P1 - P0| 以下で、半分に分けるごとに 1/8 になる。
+    fn devide_by_error_bound(&self, tolerance: T) -> Vec<QuadraticBezier<T>> {
+        let three: T = vf(3.0);
+        let dx = self.x1 - three * self.cx1 + three * self.cx0 - self.x0;
+        let dy = self.y1 - three * self.cy1 + three * self.cy0 - self.y0;
+        let mut bound = vf::<T>(3.0_f64.sqrt() / 36.0) * (dx * dx + dy * dy).sqrt();
+        let mut pieces = vec![*self];
+        let mut halvings = 0;
+        while bound > tolerance && halvings < Self::MAX_BOUND_HALVINGS {
+            pieces = pieces
+                .iter()
+                .flat_map(|c| {
+                    let (c0, c1) = c.split(vf(0.5)).unwrap();
+                    [c0, c1]
+                })
+                .collect();
+            bound = bound / vf(8.0);
+            halvings += 1;
+        }
+        pieces.iter().map(|c| c.reduce()).collect()
+    }
 
-        let f0x = self.cx0 * temp_point + self.x0 / 4.0;
-        let f0y = self.cy0 * temp_point + self.y0 / 4.0;
-        let f1x = self.cx1 * temp_point + self.x1 / 4.0;
-        let f1y = self.cy1 * temp_point + self.y1 / 4.0;
-        let f2x = f1x * 0.5 + f0x * 0.5;
-        let f2y = f1y * 0.5 + f0y * 0.5;
+    /// 曲線を等分した区間を 2 次ベジエで置き換えたときのずれが許容範囲かどうか
+    ///
+    /// パラメータをそろえた点同士の距離で比べるので、曲線同士の距離はこれより小さくなる。
+    fn fits(&self, quads: &[QuadraticBezier<T>], tolerance: T) -> bool {
+        let err = tolerance * tolerance;
+        let count: T = vf(quads.len() as f64);
+        quads.iter().enumerate().all(|(i, q)| {
+            (1..Self::DEVIATION_SAMPLES).all(|step| {
+                let s: T = vf(step as f64 / Self::DEVIATION_SAMPLES as f64);
+                let t = (vf::<T>(i as f64) + s) / count;
+                let d = self.calc_point(t).unwrap() - q.calc_point(s).unwrap();
+                d.dot(d) < err
+            })
+        })
+    }
+
+    fn devide_quadratic_bezier(&self) -> (QuadraticBezier<T>, QuadraticBezier<T>) {
+        let temp_point: T = vf(1.0 - 0.25);
+        let four: T = vf(4.0);
+        let half: T = vf(0.5);
+
+        let f0x = self.cx0 * temp_point + self.x0 / four;
+        let f0y = self.cy0 * temp_point + self.y0 / four;
+        let f1x = self.cx1 * temp_point + self.x1 / four;
+        let f1y = self.cy1 * temp_point + self.y1 / four;
+        let f2x = f1x * half + f0x * half;
+        let f2y = f1y * half + f0y * half;
 
         let b1 = QuadraticBezier {
             x0: self.x0,
@@ -263,21 +492,26 @@ impl CubicBezier {
         (b1, b2)
     }
 
-    fn convert_quadratic_bezier(&self) -> Option<QuadraticBezier> {
+    fn convert_quadratic_bezier(&self) -> Option<QuadraticBezier<T>> {
         let vx0 = self.cx0 - self.x0;
         let vy0 = self.cy0 - self.y0;
         let vx1 = self.cx1 - self.x1;
         let vy1 = self.cy1 - self.y1;
         let c = vx0 * vy1 - vx1 * vy0;
 
-        if c == 0.0 {
+        if c.is_zero() {
             return None;
         }
 
         let a = (vx1 * self.y0 - vx1 * self.y1 + vy1 * self.x1 - vy1 * self.x0) / c;
-        let b = (a * vx0 + self.x0 - self.x1) / vx1;
+        // 0 除算を避けるため、大きい方の成分から b を求める
+        let b = if vx1.abs() > vy1.abs() {
+            (a * vx0 + self.x0 - self.x1) / vx1
+        } else {
+            (a * vy0 + self.y0 - self.y1) / vy1
+        };
 
-        if a > 0.0 && b > 0.0 {
+        if a > T::zero() && b > T::zero() {
             Some(QuadraticBezier {
                 x0: self.x0,
                 y0: self.y0,
@@ -291,30 +525,97 @@ impl CubicBezier {
         }
     }
 
-    fn compare_diff(dc: Vec2, dp: Vec2) -> bool {
-        let dc = dc.normalize();
-        let dp = dp.normalize();
-        (dp.x - dc.x).abs() < 0.01 && (dp.y - dc.y).abs() < 0.01
+    fn compare_diff(dc: Point<T>, dp: Point<T>) -> bool {
+        // 制御点が重なると微分が 0 になるので、向きが決まらない場合は両方 0 のときだけ一致とみなす
+        let dc = dc.normalize_or_zero();
+        let dp = dp.normalize_or_zero();
+        if dc == Point::<T>::ZERO || dp == Point::<T>::ZERO {
+            return dc == dp;
+        }
+        let limit: T = vf(0.01);
+        (dp.x() - dc.x()).abs() < limit && (dp.y() - dc.y()).abs() < limit
     }
 
-    fn split(&self, t: f32) -> Option<(CubicBezier, CubicBezier)> {
-        if !(0.0..=1.0).contains(&t) {
+    /// 始点と終点を入れ替える
+    pub fn reverse(&self) -> CubicBezier<T> {
+        CubicBezier {
+            x0: self.x1,
+            y0: self.y1,
+            x1: self.x0,
+            y1: self.y0,
+            cx0: self.cx1,
+            cy0: self.cy1,
+            cx1: self.cx0,
+            cy1: self.cy0,
+        }
+    }
+
+    /// すべての点にアフィン変換を適用する
+    pub fn transform(&self, m: &Affine<T>) -> CubicBezier<T> {
+        let (x0, y0) = m.apply_xy(self.x0, self.y0);
+        let (x1, y1) = m.apply_xy(self.x1, self.y1);
+        let (cx0, cy0) = m.apply_xy(self.cx0, self.cy0);
+        let (cx1, cy1) = m.apply_xy(self.cx1, self.cy1);
+        CubicBezier {
+            x0,
+            y0,
+            x1,
+            y1,
+            cx0,
+            cy0,
+            cx1,
+            cy1,
+        }
+    }
+
+    /// 1 本の 2 次ベジエで近似する
+    ///
+    /// 制御点は最小二乗の意味で最適な位置に置く。`QuadraticBezier::elevate` で作った 3 次ベジエは元に戻る。
+    /// 精度が必要な場合は分割して近似する `to_quadratic` を使う。
+    pub fn reduce(&self) -> QuadraticBezier<T> {
+        let three: T = vf(3.0);
+        let four: T = vf(4.0);
+        QuadraticBezier {
+            x0: self.x0,
+            y0: self.y0,
+            x1: self.x1,
+            y1: self.y1,
+            cx0: (three * (self.cx0 + self.cx1) - self.x0 - self.x1) / four,
+            cy0: (three * (self.cy0 + self.cy1) - self.y0 - self.y1) / four,
+        }
+    }
+
+    /// t0 から t1 までの区間を切り出す
+    pub fn subsection(&self, t0: T, t1: T) -> Option<CubicBezier<T>> {
+        if t0 < T::zero() || t0 > t1 || t1 > T::one() {
             return None;
         }
+        let (head, _) = self.split(t1)?;
+        if t1.is_zero() {
+            return Some(head);
+        }
+        head.split(t0 / t1).map(|(_, tail)| tail)
+    }
 
-        let tp: f32 = 1.0 - t;
-        let mx: f32 = self.cx0 * tp + self.cx1 * t;
-        let my: f32 = self.cy0 * tp + self.cy1 * t;
-        let ax0: f32 = self.x0 * tp + self.cx0 * t;
-        let ay0: f32 = self.y0 * tp + self.cy0 * t;
-        let ax1: f32 = ax0 * tp + mx * t;
-        let ay1: f32 = ay0 * tp + my * t;
-        let bx1: f32 = self.cx1 * tp + self.x1 * t;
-        let by1: f32 = self.cy1 * tp + self.y1 * t;
-        let bx0: f32 = mx * tp + bx1 * t;
-        let by0: f32 = my * tp + by1 * t;
-        let px: f32 = ax1 * tp + bx0 * t;
-        let py: f32 = ay1 * tp + by0 * t;
+    /// t の位置で前後 2 つの 3 次ベジエに分割する
+    pub fn split(&self, t: T) -> Option<(CubicBezier<T>, CubicBezier<T>)> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+
+        let tp: T = T::one() - t;
+        let mx: T = self.cx0 * tp + self.cx1 * t;
+        let my: T = self.cy0 * tp + self.cy1 * t;
+        let ax0: T = self.x0 * tp + self.cx0 * t;
+        let ay0: T = self.y0 * tp + self.cy0 * t;
+        let ax1: T = ax0 * tp + mx * t;
+        let ay1: T = ay0 * tp + my * t;
+        let bx1: T = self.cx1 * tp + self.x1 * t;
+        let by1: T = self.cy1 * tp + self.y1 * t;
+        let bx0: T = mx * tp + bx1 * t;
+        let by0: T = my * tp + by1 * t;
+        let px: T = ax1 * tp + bx0 * t;
+        let py: T = ay1 * tp + by0 * t;
         Some((
             CubicBezier {
                 x0: self.x0,
@@ -339,3 +640,531 @@ impl CubicBezier {
         ))
     }
 }
+
+impl<T: Scalar> CubicBezier<T> {
+    /// t での単位接線ベクトル
+    ///
+    /// 制御点が端点と重なって微分が 0 になる点では、近づく向きの極限を返す。
+    pub fn tangent(&self, t: T) -> Option<Point<T>> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+        Some(tangent_direction(
+            t,
+            &[self.diff(t), self.diff2(t), self.diff3()],
+        ))
+    }
+
+    /// t での単位法線ベクトル (接線を `perp` で 90° 回したもの)
+    pub fn normal(&self, t: T) -> Option<Point<T>> {
+        self.tangent(t).map(|v| v.perp())
+    }
+
+    /// t での符号付き曲率。法線の側に曲がるときに正になる
+    pub fn curvature(&self, t: T) -> Option<T> {
+        if !(T::zero()..=T::one()).contains(&t) {
+            return None;
+        }
+        Some(signed_curvature(self.diff(t), self.diff2(t)))
+    }
+
+    /// 0 < t < 1 にある変曲点の媒介変数を小さい順に返す
+    pub fn inflections(&self) -> Vec<T> {
+        let p0 = Point::<T>::new(self.x0, self.y0);
+        let p1 = Point::<T>::new(self.cx0, self.cy0);
+        let p2 = Point::<T>::new(self.cx1, self.cy1);
+        let p3 = Point::<T>::new(self.x1, self.y1);
+        let a = p1 - p0;
+        let b = p2 - p1 * vf(2.0) + p0;
+        let c = p3 - p2 * vf(3.0) + p1 * vf(3.0) - p0;
+        // B'(t) × B''(t) は t の 2 次式になる
+        let mut ts = winding::solve_quadratic(b.perp_dot(c), a.perp_dot(c), a.perp_dot(b));
+        ts.retain(|t| T::zero() < *t && *t < T::one());
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        ts.dedup();
+        ts
+    }
+
+    fn diff2(&self, t: T) -> Point<T> {
+        let six: T = vf(6.0);
+        let two: T = vf(2.0);
+        let three: T = vf(3.0);
+        Point::<T>::new(
+            six * ((self.x1 - self.x0 - three * self.cx1 + three * self.cx0) * t + self.x0
+                - two * self.cx0
+                + self.cx1),
+            six * ((self.y1 - self.y0 - three * self.cy1 + three * self.cy0) * t + self.y0
+                - two * self.cy0
+                + self.cy1),
+        )
+    }
+
+    fn diff3(&self) -> Point<T> {
+        let six: T = vf(6.0);
+        let three: T = vf(3.0);
+        Point::<T>::new(
+            six * (self.x1 - self.x0 - three * self.cx1 + three * self.cx0),
+            six * (self.y1 - self.y0 - three * self.cy1 + three * self.cy0),
+        )
+    }
+}
+
+/// 微分が 0 になる点では、0 でない高次の微分から接線の向きを決める
+fn tangent_direction<T: Scalar>(t: T, derivatives: &[Point<T>]) -> Point<T> {
+    for (order, d) in derivatives.iter().enumerate() {
+        if *d != Point::<T>::ZERO {
+            // t = 1 に手前から近づくときは B'(1 - h) ≈ -h B''(1) なので 2 階微分の向きが逆になる
+            let flip = t == T::one() && order == 1;
+            return if flip { -d.normalize() } else { d.normalize() };
+        }
+    }
+    Point::<T>::ZERO
+}
+
+/// 速度が 0 の点では無限大になる
+fn signed_curvature<T: Scalar>(d1: Point<T>, d2: Point<T>) -> T {
+    let speed = d1.length();
+    if speed.is_zero() {
+        return T::infinity();
+    }
+    d1.perp_dot(d2) / (speed * speed * speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(c: &CubicBezier<f64>, d: f64) -> CubicBezier<f64> {
+        CubicBezier {
+            x0: c.x0 + d,
+            y0: c.y0 + d,
+            x1: c.x1 + d,
+            y1: c.y1 + d,
+            cx0: c.cx0 + d,
+            cy0: c.cy0 + d,
+            cx1: c.cx1 + d,
+            cy1: c.cy1 + d,
+        }
+    }
+
+    #[test]
+    fn f64_keeps_precision_for_large_coordinates() {
+        let offset = 1.0e7;
+        let origin = CubicBezier::<f64> {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 100.0,
+            y1: 0.0,
+            cx0: 10.0,
+            cy0: 80.0,
+            cx1: 90.0,
+            cy1: 60.0,
+        };
+        let near = origin.to_quadratic();
+        let far = translate(&origin, offset).to_quadratic();
+        assert_eq!(near.len(), far.len());
+        for (n, f) in near.iter().zip(far.iter()) {
+            assert!((f.x0 - offset - n.x0).abs() < 1e-6);
+            assert!((f.cy0 - offset - n.cy0).abs() < 1e-6);
+            assert!((f.x1 - offset - n.x1).abs() < 1e-6);
+        }
+    }
+
+    /// 標準的なパラメータ付けでの 3 次ベジエ上の点
+    fn cubic_point(c: &CubicBezier<f64>, t: f64) -> (f64, f64) {
+        let tp = 1.0 - t;
+        let (a, b, d, e) = (tp * tp * tp, 3.0 * t * tp * tp, 3.0 * t * t * tp, t * t * t);
+        (
+            c.x0 * a + c.cx0 * b + c.cx1 * d + c.x1 * e,
+            c.y0 * a + c.cy0 * b + c.cy1 * d + c.y1 * e,
+        )
+    }
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn sample() -> CubicBezier<f64> {
+        CubicBezier {
+            x0: 10.0,
+            y0: 10.0,
+            x1: 240.0,
+            y1: 240.0,
+            cx0: 10.0,
+            cy0: 180.0,
+            cx1: 180.0,
+            cy1: 10.0,
+        }
+    }
+
+    #[test]
+    fn split_and_subsection_follow_curve() {
+        let c = sample();
+        let (head, tail) = c.split(0.3).unwrap();
+        assert_close((head.x1, head.y1), cubic_point(&c, 0.3));
+        assert_close(cubic_point(&head, 0.5), cubic_point(&c, 0.15));
+        assert_close(cubic_point(&tail, 0.5), cubic_point(&c, 0.65));
+
+        let sub = c.subsection(0.2, 0.6).unwrap();
+        assert_close((sub.x0, sub.y0), cubic_point(&c, 0.2));
+        assert_close((sub.x1, sub.y1), cubic_point(&c, 0.6));
+        assert_close(cubic_point(&sub, 0.5), cubic_point(&c, 0.4));
+
+        assert!(c.split(1.5).is_none());
+        assert!(c.subsection(0.6, 0.2).is_none());
+    }
+
+    #[test]
+    fn reverse_runs_backwards() {
+        let c = sample();
+        let r = c.reverse();
+        assert_close(cubic_point(&r, 0.25), cubic_point(&c, 0.75));
+        assert_eq!(r.reverse(), c);
+    }
+
+    #[test]
+    fn elevate_is_exact_and_reduce_restores() {
+        let q = QuadraticBezier::<f64> {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 100.0,
+            y1: 20.0,
+            cx0: 30.0,
+            cy0: 90.0,
+        };
+        let c = q.elevate();
+        for t in [0.0, 0.25, 0.5, 0.9] {
+            let (h, _) = q.split(t).unwrap();
+            assert_close((h.x1, h.y1), cubic_point(&c, t));
+        }
+        let back = c.reduce();
+        assert_close((back.cx0, back.cy0), (q.cx0, q.cy0));
+    }
+
+    #[test]
+    fn transform_commutes_with_split() {
+        let m = Affine::rotate(0.5)
+            .then(&Affine::scale(2.0, 3.0))
+            .then(&Affine::translate(5.0, -7.0));
+        let c = sample();
+        let (head, _) = c.split(0.4).unwrap();
+        let (moved_head, _) = c.transform(&m).split(0.4).unwrap();
+        assert_close((moved_head.x1, moved_head.y1), m.apply_xy(head.x1, head.y1));
+        assert_eq!(Affine::<f64>::identity() * m, m);
+    }
+
+    /// 再現性のある乱数列 (xorshift)
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn coordinate(&mut self) -> f64 {
+            self.next() * 256.0
+        }
+    }
+
+    fn cubic(points: [(f64, f64); 4]) -> CubicBezier<f64> {
+        CubicBezier {
+            x0: points[0].0,
+            y0: points[0].1,
+            cx0: points[1].0,
+            cy0: points[1].1,
+            cx1: points[2].0,
+            cy1: points[2].1,
+            x1: points[3].0,
+            y1: points[3].1,
+        }
+    }
+
+    const SAMPLES: usize = 100;
+
+    /// 点から折れ線までの距離
+    fn distance_to_polyline(p: Point<f64>, polyline: &[Point<f64>]) -> f64 {
+        polyline
+            .windows(2)
+            .map(|w| {
+                let d = w[1] - w[0];
+                let t = if d.length_squared() > 0.0 {
+                    ((p - w[0]).dot(d) / d.length_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                p.distance(w[0] + d * t)
+            })
+            .fold(f64::MAX, f64::min)
+    }
+
+    /// 3 次ベジエと 2 次ベジエ列を細かい折れ線にして Hausdorff 距離を測る
+    fn max_deviation(c: &CubicBezier<f64>, quads: &[QuadraticBezier<f64>]) -> f64 {
+        let cubic_points: Vec<Point<f64>> = (0..=SAMPLES)
+            .map(|i| c.calc_point(i as f64 / SAMPLES as f64).unwrap())
+            .collect();
+        let quad_points: Vec<Point<f64>> = quads
+            .iter()
+            .flat_map(|q| (0..=SAMPLES).map(|i| q.calc_point(i as f64 / SAMPLES as f64).unwrap()))
+            .collect();
+        let one_side = |from: &[Point<f64>], to: &[Point<f64>]| {
+            from.iter()
+                .map(|p| distance_to_polyline(*p, to))
+                .fold(0.0, f64::max)
+        };
+        one_side(&cubic_points, &quad_points).max(one_side(&quad_points, &cubic_points))
+    }
+
+    fn assert_connected(c: &CubicBezier<f64>, quads: &[QuadraticBezier<f64>]) {
+        assert_eq!((quads[0].x0, quads[0].y0), (c.x0, c.y0));
+        let last = quads.last().unwrap();
+        assert_eq!((last.x1, last.y1), (c.x1, c.y1));
+        for pair in quads.windows(2) {
+            assert_eq!((pair[0].x1, pair[0].y1), (pair[1].x0, pair[1].y0));
+        }
+        for q in quads {
+            assert!(
+                [q.x0, q.y0, q.x1, q.y1, q.cx0, q.cy0]
+                    .iter()
+                    .all(|v| v.is_finite())
+            );
+        }
+    }
+
+    /// t = 0 は始点 (x0, y0) で、t が小さいほど始点に近い
+    #[test]
+    fn calc_point_starts_and_ends_at_endpoints() {
+        let c = sample();
+        let (start, end) = (Point::<f64>::new(c.x0, c.y0), Point::<f64>::new(c.x1, c.y1));
+        assert_eq!(c.calc_point(0.0), Some(start));
+        assert_eq!(c.calc_point(1.0), Some(end));
+        let near = c.calc_point(0.05).unwrap();
+        assert!(near.distance(start) < near.distance(end));
+
+        let q = c.reduce();
+        let (start, end) = (Point::<f64>::new(q.x0, q.y0), Point::<f64>::new(q.x1, q.y1));
+        assert_eq!(q.calc_point(0.0), Some(start));
+        assert_eq!(q.calc_point(1.0), Some(end));
+        let near = q.calc_point(0.05).unwrap();
+        assert!(near.distance(start) < near.distance(end));
+        assert_close(
+            (c.calc_point(0.3).unwrap().x, c.calc_point(0.3).unwrap().y),
+            cubic_point(&c, 0.3),
+        );
+    }
+
+    #[test]
+    fn quadratic_length() {
+        let line = QuadraticBezier::<f64> {
+            x0: 0.0,
+            y0: 0.0,
+            cx0: 3.0,
+            cy0: 4.0,
+            x1: 6.0,
+            y1: 8.0,
+        };
+        assert!((line.length() - 10.0).abs() < 1e-9);
+
+        let q = sample().reduce();
+        assert!((q.length() - q.elevate().length()).abs() < 1e-2);
+    }
+
+    #[test]
+    fn random_cubics_stay_within_tolerance() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        let mut counts = std::collections::BTreeSet::new();
+        for _ in 0..300 {
+            let c = cubic([
+                (random.coordinate(), random.coordinate()),
+                (random.coordinate(), random.coordinate()),
+                (random.coordinate(), random.coordinate()),
+                (random.coordinate(), random.coordinate()),
+            ]);
+            let quads = c.to_quadratic();
+            assert_connected(&c, &quads);
+            let d = max_deviation(&c, &quads);
+            assert!(d < 0.3, "{c:?} deviates by {d}");
+            counts.insert(quads.len());
+        }
+        // 6 分割を含むそれぞれの分割が使われている
+        for n in [4, 6, 8] {
+            assert!(counts.contains(&n), "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn degenerate_cubics_stay_within_tolerance() {
+        let cases = [
+            // 直線
+            [(0.0, 0.0), (10.0, 10.0), (20.0, 20.0), (30.0, 30.0)],
+            // 同一直線上で折り返す
+            [(0.0, 0.0), (40.0, 0.0), (-10.0, 0.0), (30.0, 0.0)],
+            // 水平・垂直
+            [(0.0, 5.0), (10.0, 5.0), (20.0, 5.0), (30.0, 5.0)],
+            [(5.0, 0.0), (5.0, 10.0), (5.0, 20.0), (5.0, 30.0)],
+            // 尖点
+            [(0.0, 0.0), (100.0, 100.0), (0.0, 100.0), (100.0, 0.0)],
+            // ループ
+            [(0.0, 0.0), (150.0, 100.0), (-50.0, 100.0), (100.0, 0.0)],
+            // 制御点が端点と重なる
+            [(0.0, 0.0), (0.0, 0.0), (100.0, 50.0), (100.0, 0.0)],
+            [(0.0, 0.0), (50.0, 80.0), (100.0, 0.0), (100.0, 0.0)],
+            [(0.0, 0.0), (0.0, 0.0), (100.0, 100.0), (100.0, 100.0)],
+            // 始点と終点が一致する
+            [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 0.0)],
+            // すべての点が一致する
+            [(7.0, 7.0), (7.0, 7.0), (7.0, 7.0), (7.0, 7.0)],
+        ];
+        for points in cases {
+            let c = cubic(points);
+            let quads = c.to_quadratic();
+            assert_connected(&c, &quads);
+            let d = max_deviation(&c, &quads);
+            assert!(d < 0.3, "{points:?} deviates by {d}");
+        }
+    }
+
+    /// 曲線 `f` 上で `p` に最も近い点までの距離。粗く調べてから黄金分割探索で詰める
+    fn distance_to_curve(p: Point<f64>, f: impl Fn(f64) -> Point<f64>) -> f64 {
+        let step = 1.0 / SAMPLES as f64;
+        let best = (0..=SAMPLES)
+            .min_by(|a, b| {
+                let da = f(*a as f64 * step).distance(p);
+                let db = f(*b as f64 * step).distance(p);
+                da.total_cmp(&db)
+            })
+            .unwrap() as f64
+            * step;
+        let (mut lo, mut hi) = ((best - step).max(0.0), (best + step).min(1.0));
+        let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..100 {
+            let a = hi - (hi - lo) * ratio;
+            let b = lo + (hi - lo) * ratio;
+            if f(a).distance(p) < f(b).distance(p) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+        f((lo + hi) / 2.0).distance(p)
+    }
+
+    #[test]
+    fn huge_cubics_stay_within_tolerance() {
+        for s in [1e7, 1e8] {
+            let c = cubic([(0.0, 0.0), (s, s), (0.0, s), (s, 0.0)]);
+            let quads = c.to_quadratic();
+            assert_connected(&c, &quads);
+            // パラメータ付けの違いを見ないように、2 次ベジエ上の点から 3 次ベジエまでの距離で測る
+            let on_cubic = |t: f64| c.calc_point(t).unwrap();
+            let mut d: f64 = 0.0;
+            for q in &quads {
+                for i in 0..=8 {
+                    let p = q.calc_point(i as f64 / 8.0).unwrap();
+                    d = d.max(distance_to_curve(p, on_cubic));
+                }
+            }
+            assert!(d < 0.3, "{s}: {} quads deviate by {d}", quads.len());
+        }
+    }
+
+    #[test]
+    fn tolerance_scales_with_coordinates() {
+        let points = [(0.0, 0.0), (150.0, 100.0), (-50.0, 100.0), (100.0, 0.0)];
+        let mut counts = vec![];
+        for k in [1e-2, 1.0, 1e7] {
+            let c = cubic(points.map(|(x, y)| (x * k, y * k)));
+            let tolerance = 0.3 * k;
+            let quads = c.to_quadratic_with_tolerance(tolerance);
+            assert_connected(&c, &quads);
+            let d = quads
+                .iter()
+                .flat_map(|q| (0..=8).map(|i| q.calc_point(i as f64 / 8.0).unwrap()))
+                .map(|p| distance_to_curve(p, |t| c.calc_point(t).unwrap()))
+                .fold(0.0, f64::max);
+            assert!(d < tolerance, "{k}: deviates by {d}");
+            counts.push(quads.len());
+        }
+        // 座標と許容値を同じ倍率にすれば分割数は変わらない
+        assert!(counts.iter().all(|n| *n == counts[0]), "{counts:?}");
+        assert_eq!(
+            cubic(points).to_quadratic(),
+            cubic(points).to_quadratic_with_tolerance(0.3)
+        );
+    }
+
+    #[test]
+    fn curvature_of_circle_arc() {
+        // 半径 10 の 4 分円を近似する 3 次ベジエ
+        let k = 10.0 * 0.5522847498;
+        let c = cubic([(10.0, 0.0), (10.0, k), (k, 10.0), (0.0, 10.0)]);
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            // 4 分円の近似は端で 2% ほど曲率がずれる
+            assert!((c.curvature(t).unwrap() - 0.1).abs() < 3e-3);
+            // 法線は円の中心を向く
+            let p = c.calc_point(t).unwrap();
+            let n = c.normal(t).unwrap();
+            assert!((n.length() - 1.0).abs() < 1e-12);
+            assert!((p + n * 10.0).length() < 1e-2);
+            assert!(c.tangent(t).unwrap().dot(n).abs() < 1e-12);
+        }
+        assert!(c.reverse().curvature(0.5).unwrap() < 0.0);
+        assert!(c.inflections().is_empty());
+        assert_eq!(c.curvature(1.5), None);
+
+        let q = QuadraticBezier {
+            x0: 0.0,
+            y0: 0.0,
+            cx0: 1.0,
+            cy0: 1.0,
+            x1: 2.0,
+            y1: 0.0,
+        };
+        // 頂点での曲率は |B' × B''| / |B'|³ = 8 / 8
+        assert!((q.curvature(0.5).unwrap() + 1.0_f64).abs() < 1e-12);
+        assert!(q.inflections().is_empty());
+    }
+
+    #[test]
+    fn tangent_at_collapsed_handles() {
+        let c = cubic([(0.0, 0.0), (0.0, 0.0), (10.0, 10.0), (10.0, 0.0)]);
+        let expected = Point::<f64>::new(1.0, 1.0).normalize();
+        assert!((c.tangent(0.0).unwrap() - expected).length() < 1e-12);
+        let c = c.reverse();
+        assert!((c.tangent(1.0).unwrap() + expected).length() < 1e-12);
+
+        let q = QuadraticBezier {
+            x0: 0.0,
+            y0: 0.0,
+            cx0: 4.0,
+            cy0: 0.0,
+            x1: 4.0,
+            y1: 0.0,
+        };
+        assert_eq!(q.tangent(1.0), Some(Point::<f64>::new(1.0, 0.0)));
+        assert_eq!(q.curvature(1.0), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn inflection_of_s_curve() {
+        let c = cubic([(0.0, 0.0), (1.0, 1.0), (1.0, -1.0), (2.0, 0.0)]);
+        let inflections = c.inflections();
+        assert_eq!(inflections.len(), 1);
+        assert!((inflections[0] - 0.5).abs() < 1e-12);
+        assert!(c.curvature(0.25).unwrap() * c.curvature(0.75).unwrap() < 0.0);
+
+        // 2 つの変曲点を持つ曲線
+        let c = cubic([(0.0, 0.0), (-2.0, -2.0), (-2.0, -1.0), (3.0, 0.0)]);
+        let inflections = c.inflections();
+        assert_eq!(inflections.len(), 2);
+        assert!((inflections[0] - 0.236_700_683_814_454_8).abs() < 1e-12);
+        assert!((inflections[1] - 0.563_299_316_185_545_3).abs() < 1e-12);
+        let signs = [0.1, 0.4, 0.8].map(|t| c.curvature(t).unwrap().signum());
+        assert!(signs[0] != signs[1] && signs[1] != signs[2]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    CubicBezier, QuadraticBezier,
+    scalar::{Point, Scalar, Vector, vf},
+    transform::Affine,
+};
+
+/// 直線
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line<T: Scalar = f32> {
+    pub x0: T,
+    pub y0: T,
+    pub x1: T,
+    pub y1: T,
+}
+
+impl<T: Scalar> Line<T> {
+    /// 制御点を中点に置いた 2 次ベジエとして表現する
+    pub fn to_quadratic(&self) -> QuadraticBezier<T> {
+        QuadraticBezier {
+            x0: self.x0,
+            y0: self.y0,
+            x1: self.x1,
+            y1: self.y1,
+            cx0: (self.x0 + self.x1) / vf(2.0),
+            cy0: (self.y0 + self.y1) / vf(2.0),
+        }
+    }
+
+    pub fn transform(&self, m: &Affine<T>) -> Line<T> {
+        let (x0, y0) = m.apply_xy(self.x0, self.y0);
+        let (x1, y1) = m.apply_xy(self.x1, self.y1);
+        Line { x0, y0, x1, y1 }
+    }
+}
+
+/// パスを構成する 1 区間
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment<T: Scalar = f32> {
+    Line(Line<T>),
+    Quadratic(QuadraticBezier<T>),
+    Cubic(CubicBezier<T>),
+}
+
+impl<T: Scalar> Segment<T> {
+    pub fn start(&self) -> Point<T> {
+        match self {
+            Segment::Line(l) => Point::<T>::new(l.x0, l.y0),
+            Segment::Quadratic(q) => Point::<T>::new(q.x0, q.y0),
+            Segment::Cubic(c) => Point::<T>::new(c.x0, c.y0),
+        }
+    }
+
+    pub fn end(&self) -> Point<T> {
+        match self {
+            Segment::Line(l) => Point::<T>::new(l.x1, l.y1),
+            Segment::Quadratic(q) => Point::<T>::new(q.x1, q.y1),
+            Segment::Cubic(c) => Point::<T>::new(c.x1, c.y1),
+        }
+    }
+
+    /// すべての区間を 2 次ベジエに変換する
+    pub fn to_quadratic(&self) -> Vec<QuadraticBezier<T>> {
+        match self {
+            Segment::Line(l) => vec![l.to_quadratic()],
+            Segment::Quadratic(q) => vec![*q],
+            Segment::Cubic(c) => c.to_quadratic(),
+        }
+    }
+
+    /// 3 次ベジエのずれを `tolerance` 以内にして、すべての区間を 2 次ベジエに変換する
+    pub fn to_quadratic_with_tolerance(&self, tolerance: T) -> Vec<QuadraticBezier<T>> {
+        match self {
+            Segment::Cubic(c) => c.to_quadratic_with_tolerance(tolerance),
+            other => other.to_quadratic(),
+        }
+    }
+
+    pub fn transform(&self, m: &Affine<T>) -> Segment<T> {
+        match self {
+            Segment::Line(l) => Segment::Line(l.transform(m)),
+            Segment::Quadratic(q) => Segment::Quadratic(q.transform(m)),
+            Segment::Cubic(c) => Segment::Cubic(c.transform(m)),
+        }
+    }
+}
+
+/// 巻き数から内側かどうかを決める規則
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// 始点から連続する区間の列
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contour<T: Scalar = f32> {
+    pub segments: Vec<Segment<T>>,
+    pub closed: bool,
+}
+
+/// 複数の輪郭からなるパス
+///
+/// `ttf_parser::OutlineBuilder` と同じ要領で `move_to` / `line_to` / `quad_to` /
+/// `curve_to` / `close` を呼び出して組み立てる。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path<T: Scalar = f32> {
+    pub contours: Vec<Contour<T>>,
+    current: Point<T>,
+    start: Point<T>,
+}
+
+impl<T: Scalar> Path<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(&mut self, x: T, y: T) {
+        self.contours.push(Contour::default());
+        self.current = Point::<T>::new(x, y);
+        self.start = self.current;
+    }
+
+    pub fn line_to(&mut self, x: T, y: T) {
+        let segment = Segment::Line(Line {
+            x0: self.current.x(),
+            y0: self.current.y(),
+            x1: x,
+            y1: y,
+        });
+        self.push(segment);
+    }
+
+    pub fn quad_to(&mut self, x1: T, y1: T, x: T, y: T) {
+        let segment = Segment::Quadratic(QuadraticBezier {
+            x0: self.current.x(),
+            y0: self.current.y(),
+            x1: x,
+            y1: y,
+            cx0: x1,
+            cy0: y1,
+        });
+        self.push(segment);
+    }
+
+    pub fn curve_to(&mut self, x1: T, y1: T, x2: T, y2: T, x: T, y: T) {
+        let segment = Segment::Cubic(CubicBezier {
+            x0: self.current.x(),
+            y0: self.current.y(),
+            x1: x,
+            y1: y,
+            cx0: x1,
+            cy0: y1,
+            cx1: x2,
+            cy1: y2,
+        });
+        self.push(segment);
+    }
+
+    /// 現在の輪郭を閉じる。終点と始点が離れていれば直線でつなぐ
+    pub fn close(&mut self) {
+        if self.current != self.start {
+            self.line_to(self.start.x(), self.start.y());
+        }
+        if let Some(contour) = self.contours.last_mut() {
+            contour.closed = true;
+        }
+        self.current = self.start;
+    }
+
+    /// 3 次ベジエを `CubicBezier::to_quadratic` で 2 次ベジエに置き換えたパスを返す
+    pub fn to_quadratic(&self) -> Path<T> {
+        self.to_quadratic_with_tolerance(vf(CubicBezier::<T>::TOLERANCE))
+    }
+
+    /// 3 次ベジエを `CubicBezier::to_quadratic_with_tolerance` で 2 次ベジエに置き換えたパスを返す
+    pub fn to_quadratic_with_tolerance(&self, tolerance: T) -> Path<T> {
+        let contours = self
+            .contours
+            .iter()
+            .map(|contour| Contour {
+                segments: contour
+                    .segments
+                    .iter()
+                    .flat_map(|segment| match segment {
+                        Segment::Cubic(c) => c
+                            .to_quadratic_with_tolerance(tolerance)
+                            .into_iter()
+                            .map(Segment::Quadratic)
+                            .collect(),
+                        other => vec![*other],
+                    })
+                    .collect(),
+                closed: contour.closed,
+            })
+            .collect();
+        Path {
+            contours,
+            current: self.current,
+            start: self.start,
+        }
+    }
+
+    /// すべての輪郭にアフィン変換を適用する
+    pub fn transform(&self, m: &Affine<T>) -> Path<T> {
+        let contours = self
+            .contours
+            .iter()
+            .map(|contour| Contour {
+                segments: contour.segments.iter().map(|s| s.transform(m)).collect(),
+                closed: contour.closed,
+            })
+            .collect();
+        Path {
+            contours,
+            current: m.apply(self.current),
+            start: m.apply(self.start),
+        }
+    }
+
+    fn push(&mut self, segment: Segment<T>) {
+        // 閉じた輪郭の後に続く区間は、同じ始点から新しい輪郭として始める
+        if self.contours.last().is_none_or(|contour| contour.closed) {
+            self.contours.push(Contour::default());
+        }
+        self.current = segment.end();
+        self.contours.last_mut().unwrap().segments.push(segment);
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use glam::{DVec2, Vec2};
+use num_traits::{Float, FloatConst};
+
+/// 曲線の座標に使う浮動小数点型
+///
+/// f32 は `glam::Vec2`、f64 は `glam::DVec2` を座標の型として使う。
+pub trait Scalar: Float + FloatConst + Debug + Display + Default + FromStr + 'static {
+    type Vec2: Vector<Self>;
+}
+
+/// Scalar に対応する座標の型
+pub type Point<T> = <T as Scalar>::Vec2;
+
+/// `glam::Vec2` と `glam::DVec2` に共通する演算
+pub trait Vector<T>:
+    Copy
+    + Debug
+    + Default
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<T, Output = Self>
+    + Div<T, Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+
+    fn new(x: T, y: T) -> Self;
+    fn x(self) -> T;
+    fn y(self) -> T;
+    fn dot(self, rhs: Self) -> T;
+    fn perp(self) -> Self;
+    fn perp_dot(self, rhs: Self) -> T;
+    fn length(self) -> T;
+    fn distance(self, rhs: Self) -> T;
+    fn normalize(self) -> Self;
+    fn normalize_or_zero(self) -> Self;
+    fn lerp(self, rhs: Self, s: T) -> Self;
+    fn rotate(self, rhs: Self) -> Self;
+    fn from_angle(angle: T) -> Self;
+    fn angle_to(self, rhs: Self) -> T;
+}
+
+macro_rules! impl_scalar {
+    ($scalar:ty, $vec:ty) => {
+        impl Scalar for $scalar {
+            type Vec2 = $vec;
+        }
+
+        impl Vector<$scalar> for $vec {
+            const ZERO: Self = <$vec>::ZERO;
+
+            #[inline]
+            fn new(x: $scalar, y: $scalar) -> Self {
+                <$vec>::new(x, y)
+            }
+
+            #[inline]
+            fn x(self) -> $scalar {
+                self.x
+            }
+
+            #[inline]
+            fn y(self) -> $scalar {
+                self.y
+            }
+
+            #[inline]
+            fn dot(self, rhs: Self) -> $scalar {
+                <$vec>::dot(self, rhs)
+            }
+
+            #[inline]
+            fn perp(self) -> Self {
+                <$vec>::perp(self)
+            }
+
+            #[inline]
+            fn perp_dot(self, rhs: Self) -> $scalar {
+                <$vec>::perp_dot(self, rhs)
+            }
+
+            #[inline]
+            fn length(self) -> $scalar {
+                <$vec>::length(self)
+            }
+
+            #[inline]
+            fn distance(self, rhs: Self) -> $scalar {
+                <$vec>::distance(self, rhs)
+            }
+
+            #[inline]
+            fn normalize(self) -> Self {
+                <$vec>::normalize(self)
+            }
+
+            #[inline]
+            fn normalize_or_zero(self) -> Self {
+                <$vec>::normalize_or_zero(self)
+            }
+
+            #[inline]
+            fn lerp(self, rhs: Self, s: $scalar) -> Self {
+                <$vec>::lerp(self, rhs, s)
+            }
+
+            #[inline]
+            fn rotate(self, rhs: Self) -> Self {
+                <$vec>::rotate(self, rhs)
+            }
+
+            #[inline]
+            fn from_angle(angle: $scalar) -> Self {
+                <$vec>::from_angle(angle)
+            }
+
+            #[inline]
+            fn angle_to(self, rhs: Self) -> $scalar {
+                <$vec>::angle_to(self, rhs)
+            }
+        }
+    };
+}
+
+impl_scalar!(f32, Vec2);
+impl_scalar!(f64, DVec2);
+
+#[inline]
+pub(crate) fn vf<T: Scalar>(x: f64) -> T {
+    T::from(x).unwrap()
+}
@@ -0,0 +1,786 @@
+use std::ops::Deref;
+
+use crate::{
+    CubicBezier, QuadraticBezier,
+    path::{Contour, Path, Segment},
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+/// 内部計算用の 2 次ベジエ (始点, 制御点, 終点)
+#[derive(Clone, Copy)]
+struct Quad<T: Scalar>([Point<T>; 3]);
+
+impl<T: Scalar> Deref for Quad<T> {
+    type Target = [Point<T>; 3];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+const EPSILON: f64 = 1e-5;
+const MAX_OFFSET_DEPTH: u32 = 8;
+const DASH_TABLE_STEP: usize = 16;
+const MAX_CUBIC_DEPTH: u32 = 8;
+const CUBIC_SAMPLES: usize = 8;
+
+/// 線分同士のつなぎ目の形状
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// 開いた線の端の形状
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+/// 線の描画スタイル
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle<T: Scalar = f32> {
+    pub width: T,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// 線幅に対するマイター長の上限。超えた場合はベベルになる
+    pub miter_limit: T,
+    /// 実線と空白の長さを交互に並べたもの。空なら実線。長さ 0 の実線は端の形状だけの点になる
+    pub dash_array: Vec<T>,
+    pub dash_offset: T,
+    /// 輪郭が元の線から幅の半分の距離からずれてよい量。曲線の変換、オフセット、円弧の近似を合わせたもの
+    pub tolerance: T,
+}
+
+impl<T: Scalar> StrokeStyle<T> {
+    pub fn new(width: T) -> Self {
+        Self {
+            width,
+            ..Default::default()
+        }
+    }
+}
+
+impl<T: Scalar> Default for StrokeStyle<T> {
+    fn default() -> Self {
+        Self {
+            width: T::one(),
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: vf(4.0),
+            dash_array: Vec::new(),
+            dash_offset: T::zero(),
+            tolerance: vf(0.1),
+        }
+    }
+}
+
+/// パスの線を塗りつぶし用の輪郭に変換する
+///
+/// 戻り値は閉じた輪郭ごとの 2 次ベジエの列で、nonzero ルールで塗りつぶすことを前提としている。
+pub fn stroke<T: Scalar>(path: &Path<T>, style: &StrokeStyle<T>) -> Vec<Vec<QuadraticBezier<T>>> {
+    let mut result = Vec::new();
+    for contour in path.contours.iter() {
+        let quads = contour_to_quads(contour, style.tolerance);
+        if quads.is_empty() {
+            continue;
+        }
+
+        let dash_total = style
+            .dash_array
+            .iter()
+            .fold(T::zero(), |total, d| total + *d);
+        if dash_total > T::zero() {
+            for dash in dash(&quads, contour.closed, style) {
+                match dash {
+                    Dash::Quads(quads) => result.extend(stroke_open(&quads, style)),
+                    Dash::Dot(p, t) => result.extend(stroke_dot(p, t, style)),
+                }
+            }
+        } else if contour.closed {
+            result.extend(stroke_closed(&quads, style));
+        } else {
+            result.extend(stroke_open(&quads, style));
+        }
+    }
+    result
+        .into_iter()
+        .map(|quads| quads.iter().map(from_quad).collect())
+        .collect()
+}
+
+/// 3 次ベジエは許容誤差の半分に収まるまで分けてから 2 次ベジエにする
+fn contour_to_quads<T: Scalar>(contour: &Contour<T>, tolerance: T) -> Vec<Quad<T>> {
+    let mut quads = Vec::new();
+    for segment in &contour.segments {
+        match segment {
+            Segment::Cubic(c) => cubic_to_quads(&mut quads, c, tolerance / vf(2.0), 0),
+            _ => quads.extend(segment.to_quadratic()),
+        }
+    }
+    quads
+        .iter()
+        .map(to_quad)
+        .filter(|q| !is_degenerate(q))
+        .collect()
+}
+
+fn cubic_to_quads<T: Scalar>(
+    out: &mut Vec<QuadraticBezier<T>>,
+    c: &CubicBezier<T>,
+    tolerance: T,
+    depth: u32,
+) {
+    let quads = c.to_quadratic();
+    let count: T = vf(quads.len() as f64);
+    let fits = quads.iter().enumerate().all(|(i, q)| {
+        (1..CUBIC_SAMPLES).all(|step| {
+            let s: T = vf(step as f64 / CUBIC_SAMPLES as f64);
+            let t = (vf::<T>(i as f64) + s) / count;
+            match (c.calc_point(t), q.calc_point(s)) {
+                (Some(a), Some(b)) => a.distance(b) <= tolerance,
+                _ => true,
+            }
+        })
+    });
+    if fits || depth >= MAX_CUBIC_DEPTH {
+        out.extend(quads);
+        return;
+    }
+    let (c0, c1) = c.split(vf(0.5)).unwrap();
+    cubic_to_quads(out, &c0, tolerance, depth + 1);
+    cubic_to_quads(out, &c1, tolerance, depth + 1);
+}
+
+fn stroke_open<T: Scalar>(quads: &[Quad<T>], style: &StrokeStyle<T>) -> Option<Vec<Quad<T>>> {
+    let quads: Vec<Quad<T>> = quads
+        .iter()
+        .copied()
+        .filter(|q| !is_degenerate(q))
+        .collect();
+    let (first, last) = (quads.first()?, quads.last()?);
+    let h = style.width / vf(2.0);
+    let reversed = reverse(&quads);
+
+    let mut out = offset_side(&quads, h, false, style);
+    cap(&mut out, last[2], end_tangent(last), h, style);
+    out.extend(offset_side(&reversed, h, false, style));
+    cap(&mut out, first[0], -start_tangent(first), h, style);
+    Some(out)
+}
+
+/// 長さ 0 の線には両側の端だけを付ける。端を付けない `LineCap::Butt` では何も描かない
+fn stroke_dot<T: Scalar>(p: Point<T>, t: Point<T>, style: &StrokeStyle<T>) -> Option<Vec<Quad<T>>> {
+    if style.cap == LineCap::Butt || t == Point::<T>::ZERO {
+        return None;
+    }
+    let h = style.width / vf(2.0);
+    let mut out = Vec::new();
+    cap(&mut out, p, t, h, style);
+    cap(&mut out, p, -t, h, style);
+    Some(out)
+}
+
+fn stroke_closed<T: Scalar>(quads: &[Quad<T>], style: &StrokeStyle<T>) -> Vec<Vec<Quad<T>>> {
+    let h = style.width / vf(2.0);
+    vec![
+        offset_side(quads, h, true, style),
+        offset_side(&reverse(quads), h, true, style),
+    ]
+}
+
+/// 進行方向の左側に h だけずらした線を作る
+fn offset_side<T: Scalar>(
+    quads: &[Quad<T>],
+    h: T,
+    closed: bool,
+    style: &StrokeStyle<T>,
+) -> Vec<Quad<T>> {
+    let mut out = Vec::new();
+    for (i, q) in quads.iter().enumerate() {
+        if i > 0 {
+            join(&mut out, &quads[i - 1], q, h, style);
+        }
+        // 残りの半分は 3 次ベジエを 2 次ベジエにするときの誤差に充てる
+        offset_quad(&mut out, q, h, style.tolerance / vf(2.0), 0);
+    }
+    if closed && let (Some(first), Some(last)) = (quads.first(), quads.last()) {
+        join(&mut out, last, first, h, style);
+    }
+    out
+}
+
+/// Tiller-Hanson 法で 2 次ベジエのオフセットを近似する。誤差が大きい場合は分割する
+fn offset_quad<T: Scalar>(out: &mut Vec<Quad<T>>, q: &Quad<T>, h: T, tolerance: T, depth: u32) {
+    let half: T = vf(0.5);
+    let t0 = start_tangent(q);
+    let t1 = end_tangent(q);
+    if t0.dot(t1) < half && depth < MAX_OFFSET_DEPTH {
+        let (q0, q1) = split(q, half);
+        offset_quad(out, &q0, h, tolerance, depth + 1);
+        offset_quad(out, &q1, h, tolerance, depth + 1);
+        return;
+    }
+
+    let a = q[0] + t0.perp() * h;
+    let b = q[2] + t1.perp() * h;
+    let denom = t0.perp_dot(t1);
+    let c = if denom.abs() < vf(EPSILON) {
+        (a + b) * half
+    } else {
+        a + t0 * ((b - a).perp_dot(t1) / denom)
+    };
+    let offset = Quad([a, c, b]);
+
+    // 中点だけでなく 1/4 と 3/4 の点でもずれを測る
+    let too_far = [0.25, 0.5, 0.75].into_iter().any(|t| {
+        let t: T = vf(t);
+        let normal = derivative(q, t).normalize_or_zero().perp();
+        let expected = eval(q, t) + normal * h;
+        normal != Point::<T>::ZERO && expected.distance(eval(&offset, t)) > tolerance
+    });
+    if too_far && depth < MAX_OFFSET_DEPTH {
+        let (q0, q1) = split(q, half);
+        offset_quad(out, &q0, h, tolerance, depth + 1);
+        offset_quad(out, &q1, h, tolerance, depth + 1);
+        return;
+    }
+    out.push(offset);
+}
+
+/// 前の区間の終端と次の区間の始端のオフセット点をつなぐ
+fn join<T: Scalar>(
+    out: &mut Vec<Quad<T>>,
+    prev: &Quad<T>,
+    next: &Quad<T>,
+    h: T,
+    style: &StrokeStyle<T>,
+) {
+    let v = next[0];
+    let d_in = end_tangent(prev);
+    let d_out = start_tangent(next);
+    let a = v + d_in.perp() * h;
+    let b = v + d_out.perp() * h;
+    if a.distance(b) < vf(EPSILON) {
+        return;
+    }
+    // 曲線を分けた継ぎ目のようにほとんど曲がらない所では、中心を経由せずに直接つなぐ
+    if a.distance(b) <= style.tolerance {
+        line(out, a, b);
+        return;
+    }
+
+    // 曲がる向きと反対側が外側になる
+    let turn = d_in.perp_dot(d_out);
+    if turn * h >= T::zero() {
+        line(out, a, v);
+        line(out, v, b);
+        return;
+    }
+
+    match style.join {
+        LineJoin::Bevel => line(out, a, b),
+        LineJoin::Miter => {
+            let cos_half = ((T::one() + d_in.dot(d_out)) / vf(2.0)).sqrt();
+            if cos_half < vf(EPSILON) || T::one() / cos_half > style.miter_limit {
+                line(out, a, b);
+            } else {
+                let m = v + (a + b - v * vf(2.0)).normalize() * (h.abs() / cos_half);
+                line(out, a, m);
+                line(out, m, b);
+            }
+        }
+        LineJoin::Round => {
+            let from = a - v;
+            let sweep = from.angle_to(b - v);
+            arc(out, v, from, sweep, style.tolerance);
+        }
+    }
+}
+
+/// 端点 p に t の向きへ向かう端を付ける。p の左側から右側へつなぐ
+fn cap<T: Scalar>(out: &mut Vec<Quad<T>>, p: Point<T>, t: Point<T>, h: T, style: &StrokeStyle<T>) {
+    let n = t.perp() * h;
+    let a = p + n;
+    let b = p - n;
+    match style.cap {
+        LineCap::Butt => line(out, a, b),
+        LineCap::Square => {
+            let ext = t * h;
+            line(out, a, a + ext);
+            line(out, a + ext, b + ext);
+            line(out, b + ext, b);
+        }
+        LineCap::Round => arc(out, p, n, -T::PI(), style.tolerance),
+    }
+}
+
+/// center を中心に from の位置から sweep だけ回転する円弧を 45 度以下の 2 次ベジエで近似する
+///
+/// 中心角 θ の 2 次ベジエは半径 r の円から r (1 - cos(θ/2))² / (2 cos(θ/2)) だけずれるので、
+/// これが tolerance を超える間は分割数を倍にする。
+fn arc<T: Scalar>(
+    out: &mut Vec<Quad<T>>,
+    center: Point<T>,
+    from: Point<T>,
+    sweep: T,
+    tolerance: T,
+) {
+    let r = from.length();
+    let mut count = (sweep.abs() / T::FRAC_PI_4()).ceil().max(T::one());
+    for _ in 0..MAX_OFFSET_DEPTH {
+        let c = (sweep / count / vf(2.0)).cos();
+        if r * (T::one() - c) * (T::one() - c) / (c * vf(2.0)) <= tolerance {
+            break;
+        }
+        count = count * vf(2.0);
+    }
+    let step = sweep / count;
+    let control_scale = T::one() / (step / vf(2.0)).cos();
+    let mut start = from;
+    for i in 1..=count.to_usize().unwrap() {
+        let end = Point::<T>::from_angle(step * vf(i as f64)).rotate(from);
+        let control = Point::<T>::from_angle(step / vf(2.0)).rotate(start) * control_scale;
+        out.push(Quad([center + start, center + control, center + end]));
+        start = end;
+    }
+}
+
+fn line<T: Scalar>(out: &mut Vec<Quad<T>>, a: Point<T>, b: Point<T>) {
+    if a.distance(b) >= vf(EPSILON) {
+        out.push(Quad([a, (a + b) / vf(2.0), b]));
+    }
+}
+
+/// 破線パターンで切り出した 1 本
+enum Dash<T: Scalar> {
+    Quads(Vec<Quad<T>>),
+    /// 長さ 0 の破線。端を付ける位置と、そこでの線の向き
+    Dot(Point<T>, Point<T>),
+}
+
+impl<T: Scalar> Dash<T> {
+    /// 切り出した区間がすべて潰れていれば、`q` 上の `t` の位置の点にする
+    fn new(quads: Vec<Quad<T>>, q: &Quad<T>, t: T) -> Self {
+        if quads.iter().all(|q| is_degenerate(q)) {
+            let tangent = derivative(q, t).normalize_or_zero();
+            let tangent = if tangent == Point::<T>::ZERO {
+                start_tangent(q)
+            } else {
+                tangent
+            };
+            Dash::Dot(eval(q, t), tangent)
+        } else {
+            Dash::Quads(quads)
+        }
+    }
+}
+
+/// 破線パターンに従ってパスを切り出す
+fn dash<T: Scalar>(quads: &[Quad<T>], closed: bool, style: &StrokeStyle<T>) -> Vec<Dash<T>> {
+    let mut pattern: Vec<T> = style.dash_array.iter().map(|d| d.max(T::zero())).collect();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(&pattern.clone());
+    }
+    let total = pattern.iter().fold(T::zero(), |total, d| total + *d);
+
+    let mut index = 0;
+    let mut remaining = pattern[0];
+    let mut offset = style.dash_offset % total;
+    if offset < T::zero() {
+        offset = offset + total;
+    }
+    while offset > T::zero() {
+        if offset >= remaining {
+            offset = offset - remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        } else {
+            remaining = remaining - offset;
+            offset = T::zero();
+        }
+    }
+    let starts_on = index % 2 == 0;
+
+    let mut dashes = Vec::new();
+    let mut current = Vec::new();
+    for q in quads.iter() {
+        let table = length_table(q);
+        let length = *table.last().unwrap();
+        let mut pos = T::zero();
+        while length - pos > remaining {
+            let t0 = t_at(&table, pos);
+            pos = pos + remaining;
+            if index % 2 == 0 {
+                let t1 = t_at(&table, pos);
+                current.push(subsection(q, t0, t1));
+                dashes.push(Dash::new(std::mem::take(&mut current), q, t1));
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        if index % 2 == 0 {
+            current.push(subsection(q, t_at(&table, pos), T::one()));
+        }
+        remaining = remaining - (length - pos);
+    }
+
+    let ends_on = !current.is_empty();
+    if let Some(last) = quads.last()
+        && ends_on
+    {
+        dashes.push(Dash::new(current, last, T::one()));
+    }
+    // 閉じた輪郭では最後の破線と最初の破線がつながる。片方が点なら、もう片方に含まれる
+    if closed && starts_on && ends_on && dashes.len() > 1 {
+        match (dashes.pop().unwrap(), &mut dashes[0]) {
+            (Dash::Quads(mut last), Dash::Quads(first)) => {
+                last.append(first);
+                *first = last;
+            }
+            (last @ Dash::Quads(_), first) => *first = last,
+            (Dash::Dot(..), _) => {}
+        }
+    }
+    dashes
+}
+
+/// t を等間隔に区切った点までの累積長
+fn length_table<T: Scalar>(q: &Quad<T>) -> Vec<T> {
+    let mut table = Vec::with_capacity(DASH_TABLE_STEP + 1);
+    let mut length = T::zero();
+    let mut prev = q[0];
+    table.push(T::zero());
+    for i in 1..=DASH_TABLE_STEP {
+        let p = eval(q, vf(i as f64 / DASH_TABLE_STEP as f64));
+        length = length + prev.distance(p);
+        table.push(length);
+        prev = p;
+    }
+    table
+}
+
+fn t_at<T: Scalar>(table: &[T], length: T) -> T {
+    let i = table
+        .partition_point(|l| *l < length)
+        .clamp(1, table.len() - 1);
+    let (l0, l1) = (table[i - 1], table[i]);
+    let ratio = if l1 - l0 > T::zero() {
+        ((length - l0) / (l1 - l0)).max(T::zero()).min(T::one())
+    } else {
+        T::zero()
+    };
+    (vf::<T>(i as f64 - 1.0) + ratio) / vf((table.len() - 1) as f64)
+}
+
+fn to_quad<T: Scalar>(qb: &QuadraticBezier<T>) -> Quad<T> {
+    Quad([
+        Point::<T>::new(qb.x0, qb.y0),
+        Point::<T>::new(qb.cx0, qb.cy0),
+        Point::<T>::new(qb.x1, qb.y1),
+    ])
+}
+
+fn from_quad<T: Scalar>(q: &Quad<T>) -> QuadraticBezier<T> {
+    QuadraticBezier {
+        x0: q[0].x(),
+        y0: q[0].y(),
+        x1: q[2].x(),
+        y1: q[2].y(),
+        cx0: q[1].x(),
+        cy0: q[1].y(),
+    }
+}
+
+fn reverse<T: Scalar>(quads: &[Quad<T>]) -> Vec<Quad<T>> {
+    quads
+        .iter()
+        .rev()
+        .map(|q| Quad([q[2], q[1], q[0]]))
+        .collect()
+}
+
+fn is_degenerate<T: Scalar>(q: &Quad<T>) -> bool {
+    q[0].distance(q[1]) < vf(EPSILON) && q[1].distance(q[2]) < vf(EPSILON)
+}
+
+fn eval<T: Scalar>(q: &Quad<T>, t: T) -> Point<T> {
+    let tp = T::one() - t;
+    q[0] * (tp * tp) + q[1] * (vf::<T>(2.0) * t * tp) + q[2] * (t * t)
+}
+
+fn derivative<T: Scalar>(q: &Quad<T>, t: T) -> Point<T> {
+    let two: T = vf(2.0);
+    (q[1] - q[0]) * (two * (T::one() - t)) + (q[2] - q[1]) * (two * t)
+}
+
+fn start_tangent<T: Scalar>(q: &Quad<T>) -> Point<T> {
+    if q[0].distance(q[1]) < vf(EPSILON) {
+        (q[2] - q[0]).normalize()
+    } else {
+        (q[1] - q[0]).normalize()
+    }
+}
+
+fn end_tangent<T: Scalar>(q: &Quad<T>) -> Point<T> {
+    if q[1].distance(q[2]) < vf(EPSILON) {
+        (q[2] - q[0]).normalize()
+    } else {
+        (q[2] - q[1]).normalize()
+    }
+}
+
+fn split<T: Scalar>(q: &Quad<T>, t: T) -> (Quad<T>, Quad<T>) {
+    let a = q[0].lerp(q[1], t);
+    let b = q[1].lerp(q[2], t);
+    let p = a.lerp(b, t);
+    (Quad([q[0], a, p]), Quad([p, b, q[2]]))
+}
+
+fn subsection<T: Scalar>(q: &Quad<T>, t0: T, t1: T) -> Quad<T> {
+    let (head, _) = split(q, t1);
+    if t1 <= T::zero() {
+        return Quad([q[0]; 3]);
+    }
+    split(&head, t0 / t1).1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use glam::Vec2;
+
+    use super::*;
+
+    fn polyline(points: &[(f32, f32)], closed: bool) -> Path {
+        let mut path = Path::new();
+        path.move_to(points[0].0, points[0].1);
+        for (x, y) in points.iter().skip(1) {
+            path.line_to(*x, *y);
+        }
+        if closed {
+            path.close();
+        }
+        path
+    }
+
+    fn is_continuous(outline: &[QuadraticBezier]) -> bool {
+        outline
+            .iter()
+            .zip(outline.iter().cycle().skip(1))
+            .all(|(a, b)| Vec2::new(a.x1, a.y1).distance(Vec2::new(b.x0, b.y0)) < 1e-3)
+    }
+
+    /// 折れ線の囲む面積 (符号付き)
+    fn area(outline: &[QuadraticBezier]) -> f32 {
+        outline
+            .iter()
+            .map(|q| {
+                // 2 次ベジエの符号付き面積は制御点を含めた多角形の 2/3 を足し引きして求められる
+                let chord = q.x0 * q.y1 - q.x1 * q.y0;
+                let tri = (q.cx0 - q.x0) * (q.y1 - q.y0) - (q.x1 - q.x0) * (q.cy0 - q.y0);
+                chord / 2.0 + tri / 3.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn butt_line_becomes_rectangle() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let outlines = stroke(&path, &StrokeStyle::new(2.0));
+        assert_eq!(outlines.len(), 1);
+        assert!(is_continuous(&outlines[0]));
+        assert!((area(&outlines[0]).abs() - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn square_cap_extends_line() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let style = StrokeStyle {
+            cap: LineCap::Square,
+            ..StrokeStyle::new(2.0)
+        };
+        let outlines = stroke(&path, &style);
+        assert!((area(&outlines[0]).abs() - 24.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn round_cap_adds_half_circles() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let style = StrokeStyle {
+            cap: LineCap::Round,
+            ..StrokeStyle::new(2.0)
+        };
+        let outlines = stroke(&path, &style);
+        assert!(is_continuous(&outlines[0]));
+        assert!((area(&outlines[0]).abs() - (20.0 + PI)).abs() < 0.05);
+    }
+
+    /// 2 次ベジエを折れ線に分割して nonzero の巻き数を求める
+    fn winding(outlines: &[Vec<QuadraticBezier>], p: Vec2) -> i32 {
+        let mut winding = 0;
+        for q in outlines.iter().flatten() {
+            let q = to_quad(q);
+            for i in 0..32 {
+                let a = eval(&q, i as f32 / 32.0);
+                let b = eval(&q, (i + 1) as f32 / 32.0);
+                if (a.y <= p.y) != (b.y <= p.y) {
+                    let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                    if x > p.x {
+                        winding += if b.y > a.y { 1 } else { -1 };
+                    }
+                }
+            }
+        }
+        winding
+    }
+
+    #[test]
+    fn closed_square_has_outer_and_inner_contours() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], true);
+        let outlines = stroke(&path, &StrokeStyle::new(2.0));
+        assert_eq!(outlines.len(), 2);
+        assert!(outlines.iter().all(|o| is_continuous(o)));
+        assert_eq!(winding(&outlines, Vec2::new(5.0, 5.0)), 0);
+        assert_eq!(winding(&outlines, Vec2::new(20.0, 5.0)), 0);
+        assert_ne!(winding(&outlines, Vec2::new(0.5, 5.0)), 0);
+        assert_ne!(winding(&outlines, Vec2::new(-0.5, 5.0)), 0);
+        // マイター結合なので角も埋まる
+        assert_ne!(winding(&outlines, Vec2::new(10.9, 10.9)), 0);
+    }
+
+    #[test]
+    fn bevel_join_cuts_corner() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], true);
+        let style = StrokeStyle {
+            join: LineJoin::Bevel,
+            ..StrokeStyle::new(2.0)
+        };
+        let outlines = stroke(&path, &style);
+        assert_eq!(winding(&outlines, Vec2::new(10.9, 10.9)), 0);
+        assert_ne!(winding(&outlines, Vec2::new(10.4, 10.4)), 0);
+    }
+
+    #[test]
+    fn dash_splits_line() {
+        let path = polyline(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let style = StrokeStyle {
+            dash_array: vec![2.0, 1.0],
+            ..StrokeStyle::new(1.0)
+        };
+        let outlines = stroke(&path, &style);
+        // 0-2, 3-5, 6-8, 9-10
+        assert_eq!(outlines.len(), 4);
+        let total: f32 = outlines.iter().map(|o| area(o).abs()).sum();
+        assert!((total - 7.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn zero_length_dashes_become_dots() {
+        // 0, 4 (角), 8 の位置に長さ 0 の破線がある
+        let path = polyline(&[(0.0, 0.0), (4.0, 0.0), (4.0, 6.0)], false);
+        let dots = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 4.0),
+        ];
+        let style = |cap| StrokeStyle {
+            cap,
+            dash_array: vec![0.0, 4.0],
+            tolerance: 0.01,
+            ..StrokeStyle::new(2.0)
+        };
+
+        let outlines = stroke(&path, &style(LineCap::Round));
+        assert_eq!(outlines.len(), 3);
+        for (outline, center) in outlines.iter().zip(dots) {
+            assert!(is_continuous(outline));
+            // 円弧は外側に最大で許容値だけふくらむ
+            assert!(
+                (area(outline).abs() - PI).abs() < 2.0 * PI * 0.01,
+                "{}",
+                area(outline)
+            );
+            // 弧の継ぎ目を通らないように中心から少しずらして調べる
+            assert_ne!(winding(&outlines, center + Vec2::splat(0.1)), 0);
+            for q in outline {
+                assert!((Vec2::new(q.x0, q.y0).distance(center) - 1.0).abs() < 1e-3);
+            }
+        }
+
+        let outlines = stroke(&path, &style(LineCap::Square));
+        assert_eq!(outlines.len(), 3);
+        for outline in &outlines {
+            assert!((area(outline).abs() - 4.0).abs() < 1e-3);
+        }
+
+        assert!(stroke(&path, &style(LineCap::Butt)).is_empty());
+    }
+
+    /// 点から 3 次ベジエまでの距離。細かく分けた折れ線で測る
+    fn distance_to_cubic(p: Vec2, c: [Vec2; 4]) -> f32 {
+        const STEPS: usize = 4000;
+        let at = |t: f32| {
+            let s = 1.0 - t;
+            c[0] * (s * s * s)
+                + c[1] * (3.0 * s * s * t)
+                + c[2] * (3.0 * s * t * t)
+                + c[3] * (t * t * t)
+        };
+        (0..STEPS)
+            .map(|i| {
+                let (a, b) = (
+                    at(i as f32 / STEPS as f32),
+                    at((i + 1) as f32 / STEPS as f32),
+                );
+                let t = ((p - a).dot(b - a) / (b - a).length_squared()).clamp(0.0, 1.0);
+                p.distance(a + (b - a) * t)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn curved_outline_stays_within_tolerance() {
+        let c = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 50.0),
+            Vec2::new(100.0, 50.0),
+            Vec2::new(100.0, 0.0),
+        ];
+        let mut path = Path::new();
+        path.move_to(c[0].x, c[0].y);
+        path.curve_to(c[1].x, c[1].y, c[2].x, c[2].y, c[3].x, c[3].y);
+        for tolerance in [0.1, 0.01] {
+            // 端も丸めると、輪郭上のどの点も曲線から幅の半分だけ離れる
+            let style = StrokeStyle {
+                cap: LineCap::Round,
+                tolerance,
+                ..StrokeStyle::new(10.0)
+            };
+            let outlines = stroke(&path, &style);
+            assert_eq!(outlines.len(), 1);
+            assert!(is_continuous(&outlines[0]));
+            let mut worst = 0.0f32;
+            for q in &outlines[0] {
+                let (p0, p1, p2) = (
+                    Vec2::new(q.x0, q.y0),
+                    Vec2::new(q.cx0, q.cy0),
+                    Vec2::new(q.x1, q.y1),
+                );
+                for i in 0..=16 {
+                    let t = i as f32 / 16.0;
+                    let p = p0 * (1.0 - t) * (1.0 - t) + p1 * (2.0 * t * (1.0 - t)) + p2 * (t * t);
+                    let error = (distance_to_cubic(p, c) - style.width / 2.0).abs();
+                    worst = worst.max(error);
+                }
+            }
+            assert!(worst <= tolerance, "{worst} > {tolerance}");
+        }
+    }
+}
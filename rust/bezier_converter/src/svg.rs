@@ -0,0 +1,546 @@
+use std::{
+    error::Error,
+    f64::consts::{FRAC_PI_2, PI},
+    fmt::{Display, Formatter},
+};
+
+use glam::DVec2;
+
+use crate::{
+    path::{Path, Segment},
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+/// SVG のパスデータの解析に失敗した理由。position は入力文字列中のバイト位置
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedCharacter { position: usize, character: char },
+    ExpectedNumber { position: usize },
+    ExpectedFlag { position: usize },
+    MissingMoveTo { position: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedCharacter {
+                position,
+                character,
+            } => write!(f, "unexpected character '{character}' at {position}"),
+            ParseError::ExpectedNumber { position } => write!(f, "expected number at {position}"),
+            ParseError::ExpectedFlag { position } => write!(f, "expected flag at {position}"),
+            ParseError::MissingMoveTo { position } => {
+                write!(f, "path data must start with moveto at {position}")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// SVG の `d` 属性の文字列を解析してパスを作る。円弧は 3 次ベジエに変換する
+pub fn parse_path<T: Scalar>(d: &str) -> Result<Path<T>, ParseError> {
+    let mut parser = Parser {
+        data: d.as_bytes(),
+        pos: 0,
+    };
+    let mut path = Path::new();
+    let mut current = Point::<T>::ZERO;
+    let mut start = Point::<T>::ZERO;
+    // S / T で反転させる直前の制御点
+    let mut cubic_control: Option<Point<T>> = None;
+    let mut quad_control: Option<Point<T>> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        parser.skip_separator();
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        let position = parser.pos;
+        let cmd = if c.is_ascii_alphabetic() {
+            if !b"MmLlHhVvCcSsQqTtAaZz".contains(&c) {
+                return Err(ParseError::UnexpectedCharacter {
+                    position,
+                    character: c as char,
+                });
+            }
+            parser.pos += 1;
+            c
+        } else if let Some(cmd) = command.filter(|c| !matches!(c, b'Z' | b'z'))
+            && parser.at_number()
+        {
+            // コマンド文字を省略した座標は直前のコマンドの繰り返し
+            cmd
+        } else {
+            return Err(ParseError::UnexpectedCharacter {
+                position,
+                character: c as char,
+            });
+        };
+        if command.is_none() && !matches!(cmd, b'M' | b'm') {
+            return Err(ParseError::MissingMoveTo { position });
+        }
+
+        let base = if cmd.is_ascii_lowercase() {
+            current
+        } else {
+            Point::<T>::ZERO
+        };
+        let (next_cubic_control, next_quad_control) = match cmd.to_ascii_uppercase() {
+            b'M' => {
+                current = base + parser.point::<T>()?;
+                start = current;
+                path.move_to(current.x(), current.y());
+                // moveto に続く座標は lineto として扱う
+                command = Some(if cmd == b'M' { b'L' } else { b'l' });
+                (None, None)
+            }
+            b'L' => {
+                current = base + parser.point::<T>()?;
+                path.line_to(current.x(), current.y());
+                (None, None)
+            }
+            b'H' => {
+                current = Point::<T>::new(base.x() + parser.number::<T>()?, current.y());
+                path.line_to(current.x(), current.y());
+                (None, None)
+            }
+            b'V' => {
+                current = Point::<T>::new(current.x(), base.y() + parser.number::<T>()?);
+                path.line_to(current.x(), current.y());
+                (None, None)
+            }
+            b'C' | b'S' => {
+                let c1 = if cmd.eq_ignore_ascii_case(&b'C') {
+                    base + parser.point::<T>()?
+                } else {
+                    cubic_control.map_or(current, |c| current * vf(2.0) - c)
+                };
+                let c2 = base + parser.point::<T>()?;
+                current = base + parser.point::<T>()?;
+                path.curve_to(c1.x(), c1.y(), c2.x(), c2.y(), current.x(), current.y());
+                (Some(c2), None)
+            }
+            b'Q' | b'T' => {
+                let c1 = if cmd.eq_ignore_ascii_case(&b'Q') {
+                    base + parser.point::<T>()?
+                } else {
+                    quad_control.map_or(current, |c| current * vf(2.0) - c)
+                };
+                current = base + parser.point::<T>()?;
+                path.quad_to(c1.x(), c1.y(), current.x(), current.y());
+                (None, Some(c1))
+            }
+            b'A' => {
+                let rx = parser.number::<T>()?;
+                let ry = parser.number::<T>()?;
+                let rotation = parser.number::<T>()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let end = base + parser.point::<T>()?;
+                arc_to(&mut path, current, rx, ry, rotation, large_arc, sweep, end);
+                current = end;
+                (None, None)
+            }
+            _ => {
+                path.close();
+                current = start;
+                (None, None)
+            }
+        };
+        cubic_control = next_cubic_control;
+        quad_control = next_quad_control;
+        if !matches!(cmd, b'M' | b'm') {
+            command = Some(cmd);
+        }
+    }
+    Ok(path)
+}
+
+/// SVG 仕様の付録 (F.6.5) に従って円弧を中心点形式に直し、90 度以下の 3 次ベジエに分割する
+#[allow(clippy::too_many_arguments)]
+fn arc_to<T: Scalar>(
+    path: &mut Path<T>,
+    from: Point<T>,
+    rx: T,
+    ry: T,
+    rotation: T,
+    large_arc: bool,
+    sweep: bool,
+    to: Point<T>,
+) {
+    if from == to {
+        return;
+    }
+    if rx.is_zero() || ry.is_zero() {
+        path.line_to(to.x(), to.y());
+        return;
+    }
+
+    // 途中の計算は f64 で行う
+    let to_dvec2 = |p: Point<T>| DVec2::new(p.x().to_f64().unwrap(), p.y().to_f64().unwrap());
+    let p0 = to_dvec2(from);
+    let p1 = to_dvec2(to);
+    let mut rx = rx.abs().to_f64().unwrap();
+    let mut ry = ry.abs().to_f64().unwrap();
+    let (sin_phi, cos_phi) = rotation.to_f64().unwrap().to_radians().sin_cos();
+
+    let half = (p0 - p1) / 2.0;
+    let x1 = cos_phi * half.x + sin_phi * half.y;
+    let y1 = -sin_phi * half.x + cos_phi * half.y;
+
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let center =
+        DVec2::new(cos_phi * cx1 - sin_phi * cy1, sin_phi * cx1 + cos_phi * cy1) + (p0 + p1) / 2.0;
+
+    let u = DVec2::new((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let v = DVec2::new((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let theta = DVec2::X.angle_to(u);
+    let mut delta = u.angle_to(v);
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    let map = |p: DVec2| {
+        DVec2::new(
+            center.x + rx * cos_phi * p.x - ry * sin_phi * p.y,
+            center.y + rx * sin_phi * p.x + ry * cos_phi * p.y,
+        )
+    };
+    let count = (delta.abs() / FRAC_PI_2 - 1e-9).ceil().max(1.0) as usize;
+    let step = delta / count as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    for i in 0..count {
+        let a = theta + step * i as f64;
+        let b = a + step;
+        let (sin_a, cos_a) = a.sin_cos();
+        let (sin_b, cos_b) = b.sin_cos();
+        let c1 = map(DVec2::new(cos_a - k * sin_a, sin_a + k * cos_a));
+        let c2 = map(DVec2::new(cos_b + k * sin_b, sin_b - k * cos_b));
+        let end = if i + 1 == count {
+            to
+        } else {
+            let end = map(DVec2::new(cos_b, sin_b));
+            Point::<T>::new(vf(end.x), vf(end.y))
+        };
+        path.curve_to(vf(c1.x), vf(c1.y), vf(c2.x), vf(c2.y), end.x(), end.y());
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// 空白とカンマ 1 つを読み飛ばす
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(b',') {
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    fn at_number(&self) -> bool {
+        self.peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.'))
+    }
+
+    fn point<T: Scalar>(&mut self) -> Result<Point<T>, ParseError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point::<T>::new(x, y))
+    }
+
+    fn number<T: Scalar>(&mut self) -> Result<T, ParseError> {
+        self.skip_separator();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let integer = self.digits();
+        let mut fraction = 0;
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            fraction = self.digits();
+        }
+        if integer == 0 && fraction == 0 {
+            self.pos = start;
+            return Err(ParseError::ExpectedNumber { position: start });
+        }
+        // 指数部は数字が続く場合だけ読む
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                self.pos = mark;
+            }
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+        text.parse()
+            .map_err(|_| ParseError::ExpectedNumber { position: start })
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// 円弧のフラグは区切りなしで続けて書ける
+    fn flag(&mut self) -> Result<bool, ParseError> {
+        self.skip_separator();
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(ParseError::ExpectedFlag { position: self.pos }),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+}
+
+/// パスを SVG の `d` 属性の文字列に変換する
+///
+/// 絶対座標のコマンドだけを使い、繰り返しのコマンド文字や不要な区切りを省いて短くする。
+pub fn to_path_data<T: Scalar>(path: &Path<T>) -> String {
+    let mut writer = Writer::default();
+    for contour in path.contours.iter() {
+        let Some(first) = contour.segments.first() else {
+            continue;
+        };
+        let start = first.start();
+        let mut current = start;
+        writer.command('M');
+        writer.point::<T>(start);
+
+        for (i, segment) in contour.segments.iter().enumerate() {
+            if segment.start() != current {
+                writer.command('M');
+                writer.point::<T>(segment.start());
+            }
+            current = segment.end();
+            match segment {
+                Segment::Line(l) => {
+                    let is_last = i + 1 == contour.segments.len();
+                    if contour.closed && is_last && current == start {
+                        // Z で閉じる直線は書かなくてよい
+                    } else if l.y0 == l.y1 {
+                        writer.command('H');
+                        writer.number(l.x1);
+                    } else if l.x0 == l.x1 {
+                        writer.command('V');
+                        writer.number(l.y1);
+                    } else {
+                        writer.command('L');
+                        writer.point::<T>(current);
+                    }
+                }
+                Segment::Quadratic(q) => {
+                    writer.command('Q');
+                    writer.point::<T>(Point::<T>::new(q.cx0, q.cy0));
+                    writer.point::<T>(current);
+                }
+                Segment::Cubic(c) => {
+                    writer.command('C');
+                    writer.point::<T>(Point::<T>::new(c.cx0, c.cy0));
+                    writer.point::<T>(Point::<T>::new(c.cx1, c.cy1));
+                    writer.point::<T>(current);
+                }
+            }
+        }
+        if contour.closed {
+            writer.command('Z');
+        }
+    }
+    writer.out
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+    last_command: Option<char>,
+    /// 直前の数値が小数点を含んでいれば、続く "." 始まりの数値は区切りなしで書ける
+    last_has_dot: bool,
+    needs_separator: bool,
+}
+
+impl Writer {
+    fn command(&mut self, command: char) {
+        if command != 'M' && self.last_command == Some(command) {
+            return;
+        }
+        self.out.push(command);
+        // moveto に続く座標は lineto として扱われる
+        self.last_command = Some(if command == 'M' { 'L' } else { command });
+        self.needs_separator = false;
+    }
+
+    fn point<T: Scalar>(&mut self, p: Point<T>) {
+        self.number(p.x());
+        self.number(p.y());
+    }
+
+    fn number<T: Scalar>(&mut self, value: T) {
+        let text = format_number(value);
+        let omit = text.starts_with('-') || (text.starts_with('.') && self.last_has_dot);
+        if self.needs_separator && !omit {
+            self.out.push(' ');
+        }
+        self.out.push_str(&text);
+        self.last_has_dot = text.contains('.');
+        self.needs_separator = true;
+    }
+}
+
+fn format_number<T: Scalar>(value: T) -> String {
+    if value.is_zero() {
+        return "0".to_string();
+    }
+    let text = value.to_string();
+    if let Some(rest) = text.strip_prefix("0.") {
+        format!(".{rest}")
+    } else if let Some(rest) = text.strip_prefix("-0.") {
+        format!("-.{rest}")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::path::Line;
+
+    #[test]
+    fn parse_absolute_and_relative() {
+        let path = parse_path::<f32>("M10 10 h 5 v5 H10 z m 20 0 l 5 5").unwrap();
+        assert_eq!(path.contours.len(), 2);
+        assert!(path.contours[0].closed);
+        assert_eq!(path.contours[0].segments.len(), 4);
+        assert_eq!(
+            path.contours[1].segments[0],
+            Segment::Line(Line {
+                x0: 30.0,
+                y0: 10.0,
+                x1: 35.0,
+                y1: 15.0
+            })
+        );
+    }
+
+    #[test]
+    fn parse_compact_numbers_and_implicit_commands() {
+        let path = parse_path::<f32>("M0,0L1-1.5.5.5e1 3 4").unwrap();
+        let ends: Vec<Vec2> = path.contours[0].segments.iter().map(|s| s.end()).collect();
+        assert_eq!(
+            ends,
+            vec![
+                Vec2::new(1.0, -1.5),
+                Vec2::new(0.5, 5.0),
+                Vec2::new(3.0, 4.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_smooth_curves_reflect_control_points() {
+        let path = parse_path::<f32>("M0 0C0 10 10 10 10 0S20-10 20 0Q25 10 30 0T40 0").unwrap();
+        let segments = &path.contours[0].segments;
+        let Segment::Cubic(s) = segments[1] else {
+            panic!("expected cubic");
+        };
+        assert_eq!((s.cx0, s.cy0), (10.0, -10.0));
+        let Segment::Quadratic(t) = segments[3] else {
+            panic!("expected quadratic");
+        };
+        assert_eq!((t.cx0, t.cy0), (35.0, -10.0));
+    }
+
+    #[test]
+    fn parse_arc_ends_at_target() {
+        let path = parse_path::<f32>("M0 0A10 10 0 1 1 20 0a5 5 0 0020 0").unwrap();
+        let segments = &path.contours[0].segments;
+        assert!(segments.iter().all(|s| matches!(s, Segment::Cubic(_))));
+        assert_eq!(segments.last().unwrap().end(), Vec2::new(40.0, 0.0));
+        // 半円の頂点は中心 (10, 0) から半径 10 だけ離れている
+        let Segment::Cubic(first) = segments[0] else {
+            unreachable!()
+        };
+        assert!((Vec2::new(first.x1, first.y1).distance(Vec2::new(10.0, 0.0)) - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_path::<f32>("L0 0"),
+            Err(ParseError::MissingMoveTo { position: 0 })
+        );
+        assert_eq!(
+            parse_path::<f32>("M0 0 L1"),
+            Err(ParseError::ExpectedNumber { position: 7 })
+        );
+        assert_eq!(
+            parse_path::<f32>("M0 0 X"),
+            Err(ParseError::UnexpectedCharacter {
+                position: 5,
+                character: 'X'
+            })
+        );
+        assert_eq!(
+            parse_path::<f32>("M0 0 A1 1 0 2 0 1 1"),
+            Err(ParseError::ExpectedFlag { position: 12 })
+        );
+    }
+
+    #[test]
+    fn serialize_compact() {
+        let path = parse_path::<f32>("M 0 0 L 10 0 L 10 10 L -0.5 0.5 Z").unwrap();
+        assert_eq!(to_path_data(&path), "M0 0H10V10L-.5.5Z");
+    }
+
+    #[test]
+    fn round_trip_through_to_quadratic() {
+        let d = "M10 80C40 10 65 10 95 80S150 150 180 80M20 20Q40 0 60 20T100 20Z";
+        let path = parse_path::<f32>(d).unwrap().to_quadratic();
+        let serialized = to_path_data(&path);
+        assert_eq!(
+            parse_path::<f32>(&serialized).unwrap().contours,
+            path.contours
+        );
+    }
+}
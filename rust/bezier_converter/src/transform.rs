@@ -0,0 +1,121 @@
+use std::ops::Mul;
+
+use glam::{Affine2, DAffine2};
+
+use crate::scalar::{Point, Scalar, Vector};
+
+/// 2 次元のアフィン変換
+///
+/// SVG の `matrix(a b c d e f)` と同じ並びで、(x, y) を (a x + c y + e, b x + d y + f) に写す。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine<T: Scalar = f32> {
+    pub a: T,
+    pub b: T,
+    pub c: T,
+    pub d: T,
+    pub e: T,
+    pub f: T,
+}
+
+impl<T: Scalar> Affine<T> {
+    pub fn identity() -> Self {
+        Self::scale(T::one(), T::one())
+    }
+
+    pub fn translate(x: T, y: T) -> Self {
+        Self {
+            e: x,
+            f: y,
+            ..Self::identity()
+        }
+    }
+
+    pub fn scale(x: T, y: T) -> Self {
+        Self {
+            a: x,
+            b: T::zero(),
+            c: T::zero(),
+            d: y,
+            e: T::zero(),
+            f: T::zero(),
+        }
+    }
+
+    /// 原点を中心に angle (ラジアン) だけ回転する
+    pub fn rotate(angle: T) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            e: T::zero(),
+            f: T::zero(),
+        }
+    }
+
+    /// self を適用した後に next を適用する変換
+    pub fn then(&self, next: &Affine<T>) -> Self {
+        Self {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    pub fn apply(&self, p: Point<T>) -> Point<T> {
+        let (x, y) = self.apply_xy(p.x(), p.y());
+        Point::<T>::new(x, y)
+    }
+
+    pub(crate) fn apply_xy(&self, x: T, y: T) -> (T, T) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+}
+
+impl<T: Scalar> Default for Affine<T> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// `lhs * rhs` は rhs を適用した後に lhs を適用する変換
+impl<T: Scalar> Mul for Affine<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        rhs.then(&self)
+    }
+}
+
+impl From<Affine2> for Affine<f32> {
+    fn from(m: Affine2) -> Self {
+        Self {
+            a: m.matrix2.x_axis.x,
+            b: m.matrix2.x_axis.y,
+            c: m.matrix2.y_axis.x,
+            d: m.matrix2.y_axis.y,
+            e: m.translation.x,
+            f: m.translation.y,
+        }
+    }
+}
+
+impl From<DAffine2> for Affine<f64> {
+    fn from(m: DAffine2) -> Self {
+        Self {
+            a: m.matrix2.x_axis.x,
+            b: m.matrix2.x_axis.y,
+            c: m.matrix2.y_axis.x,
+            d: m.matrix2.y_axis.y,
+            e: m.translation.x,
+            f: m.translation.y,
+        }
+    }
+}
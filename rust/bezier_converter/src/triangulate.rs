@@ -0,0 +1,607 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    path::{FillRule, Path, Segment},
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+/// 重ならない分解で、曲線三角形が他の区間と重なるときに曲線を半分に分ける回数の上限
+const MAX_SPLIT_DEPTH: usize = 8;
+/// 接しているだけの図形を重なりとみなさないための余裕
+const EPSILON: f64 = 1e-4;
+/// 曲線のどちら側が塗られるかを調べるときにずらす距離
+const PROBE: f64 = 1e-2;
+
+/// Loop–Blinn 法で描画するための頂点
+///
+/// 曲線三角形は始点・制御点・終点に (0, 0)・(1/2, 0)・(1, 1) を持ち、補間した値が
+/// `u² - v < 0` となる画素が曲線と弦に挟まれた部分になる。
+/// 扇形の頂点は常にこの条件を満たす (0, 1) を持つので、すべての三角形を同じシェーダで描ける。
+/// `triangulate_disjoint` では三角形の向きで塗る側を表し、時計回りの三角形は `u² - v > 0` の側を塗る。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex<T: Scalar = f32> {
+    pub x: T,
+    pub y: T,
+    pub u: T,
+    pub v: T,
+}
+
+/// 頂点バッファと 3 つ組のインデックスバッファ
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh<T: Scalar = f32> {
+    pub vertices: Vec<Vertex<T>>,
+    pub indices: Vec<u32>,
+}
+
+impl<T: Scalar> Mesh<T> {
+    fn push_vertex(&mut self, p: Point<T>, u: T, v: T) -> u32 {
+        self.vertices.push(Vertex {
+            x: p.x(),
+            y: p.y(),
+            u,
+            v,
+        });
+        (self.vertices.len() - 1) as u32
+    }
+
+    fn push_fan(&mut self, anchor: (u32, Point<T>), a: (u32, Point<T>), b: (u32, Point<T>)) {
+        if !(a.1 - anchor.1).perp_dot(b.1 - anchor.1).is_zero() {
+            self.indices.extend([anchor.0, a.0, b.0]);
+        }
+    }
+
+    fn push_curve(&mut self, p0: Point<T>, c: Point<T>, p1: Point<T>) {
+        if (c - p0).perp_dot(p1 - p0).is_zero() {
+            return;
+        }
+        let i0 = self.push_vertex(p0, T::zero(), T::zero());
+        let i1 = self.push_vertex(c, vf(0.5), T::zero());
+        let i2 = self.push_vertex(p1, T::one(), T::one());
+        self.indices.extend([i0, i1, i2]);
+    }
+}
+
+/// 閉じたパスを Loop–Blinn 法で描画する三角形に分解する
+///
+/// 輪郭ごとに最初の点を中心とした扇形と、2 次ベジエごとの曲線三角形を出力する。
+/// 三角形は互いに重なるので、頂点の並びが正の向きなら +1、負の向きなら -1 として
+/// ステンシルなどで巻き数を数え、nonzero または even-odd で塗る画素を決める。
+/// 3 次ベジエは `CubicBezier::to_quadratic` で 2 次ベジエに変換してから分解する。
+pub fn triangulate<T: Scalar>(path: &Path<T>) -> Mesh<T> {
+    let mut mesh = Mesh::default();
+    for contour in &path.contours {
+        let Some(first) = contour.segments.first() else {
+            continue;
+        };
+        let inside =
+            |mesh: &mut Mesh<T>, p: Point<T>| (mesh.push_vertex(p, T::zero(), T::one()), p);
+        let anchor = inside(&mut mesh, first.start());
+        let mut prev = anchor;
+        for segment in &contour.segments {
+            let quads = match segment {
+                Segment::Line(_) => vec![],
+                other => other.to_quadratic(),
+            };
+            for q in quads {
+                mesh.push_curve(
+                    Point::<T>::new(q.x0, q.y0),
+                    Point::<T>::new(q.cx0, q.cy0),
+                    Point::<T>::new(q.x1, q.y1),
+                );
+                let next = inside(&mut mesh, Point::<T>::new(q.x1, q.y1));
+                mesh.push_fan(anchor, prev, next);
+                prev = next;
+            }
+            if let Segment::Line(_) = segment {
+                let next = inside(&mut mesh, segment.end());
+                mesh.push_fan(anchor, prev, next);
+                prev = next;
+            }
+        }
+    }
+    mesh
+}
+
+/// 閉じたパスを、互いに重ならない三角形に分解する
+///
+/// 曲線は曲線三角形にし、残りの多角形は頂点と交点の y 座標で帯に切って台形に分ける。
+/// 曲線三角形が他の区間と重なる間は曲線を半分に分けていき、制御点が外側にある曲線は弦を、
+/// 内側にある曲線は制御点を通る折れ線を多角形の辺にする。三角形同士が重ならないので、
+/// 星形でない輪郭や穴、重なった輪郭でもステンシルを使わずにそのまま描ける。
+///
+/// 巻き数は数えない代わりに、三角形の向きで塗る側を表す。反時計回り (x 右、y 上) の三角形は
+/// `u² - v < 0`、時計回りの三角形 (制御点が内側にある曲線) は `u² - v > 0` の画素を塗る。
+/// 開いた輪郭は始点まで直線で閉じているものとして扱う。
+pub fn triangulate_disjoint<T: Scalar>(path: &Path<T>, fill_rule: FillRule) -> Mesh<T> {
+    let path = path.to_quadratic();
+    let mut lines: Vec<[Point<T>; 2]> = vec![];
+    let mut curves: Vec<[Point<T>; 3]> = vec![];
+    for contour in &path.contours {
+        let (Some(first), Some(last)) = (contour.segments.first(), contour.segments.last()) else {
+            continue;
+        };
+        for segment in &contour.segments {
+            match segment {
+                Segment::Line(l) => {
+                    lines.push([Point::<T>::new(l.x0, l.y0), Point::<T>::new(l.x1, l.y1)])
+                }
+                Segment::Quadratic(q) => {
+                    let curve = [
+                        Point::<T>::new(q.x0, q.y0),
+                        Point::<T>::new(q.cx0, q.cy0),
+                        Point::<T>::new(q.x1, q.y1),
+                    ];
+                    if is_flat::<T>(&curve) {
+                        lines.push([curve[0], curve[2]]);
+                    } else {
+                        curves.push(curve);
+                    }
+                }
+                Segment::Cubic(_) => unreachable!(),
+            }
+        }
+        if last.end() != first.start() {
+            lines.push([last.end(), first.start()]);
+        }
+    }
+
+    let mut mesh = DisjointBuilder::default();
+    let mut edges = lines;
+    let (quarter, half): (T, T) = (vf(0.25), vf(0.5));
+    for curve in split_overlapping::<T>(curves, &edges) {
+        let [p0, c, p1] = curve;
+        let mid = p0 * quarter + c * half + p1 * quarter;
+        let probe = (c - mid).normalize_or_zero() * vf(PROBE);
+        let inside = |p| path.contains(p, fill_rule);
+        match (inside(mid + probe), inside(mid - probe)) {
+            (false, true) => {
+                mesh.push_curve(curve, true);
+                edges.push([p0, p1]);
+            }
+            (true, false) => {
+                mesh.push_curve(curve, false);
+                edges.push([p0, c]);
+                edges.push([c, p1]);
+            }
+            // 両側とも塗られる (重なった輪郭の内側にある) 曲線は弦で置き換えてよい
+            _ => edges.push([p0, p1]),
+        }
+    }
+    fill_polygon(&edges, fill_rule, &mut mesh);
+    mesh.mesh
+}
+
+/// 3 点がほぼ一直線に並んでいるかどうか
+fn is_flat<T: Scalar>(curve: &[Point<T>; 3]) -> bool {
+    let [p0, c, p1] = *curve;
+    let epsilon: T = vf(EPSILON);
+    (c - p0).perp_dot(p1 - p0).abs() <= epsilon * (p1 - p0).length().max(epsilon)
+}
+
+/// 三角形が他の曲線三角形や直線と重なる曲線を、重ならなくなるまで半分に分ける
+fn split_overlapping<T: Scalar>(
+    mut curves: Vec<[Point<T>; 3]>,
+    lines: &[[Point<T>; 2]],
+) -> Vec<[Point<T>; 3]> {
+    let half: T = vf(0.5);
+    for _ in 0..MAX_SPLIT_DEPTH {
+        let split: Vec<bool> = curves
+            .iter()
+            .enumerate()
+            .map(|(i, curve)| {
+                curves
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| i != j && overlaps::<T>(curve, other))
+                    || lines.iter().any(|line| overlaps::<T>(curve, line))
+            })
+            .collect();
+        if !split.contains(&true) {
+            break;
+        }
+        curves = curves
+            .into_iter()
+            .zip(split)
+            .flat_map(|(curve, split)| {
+                if !split {
+                    return vec![curve];
+                }
+                let [p0, c, p1] = curve;
+                let (c0, c1) = ((p0 + c) * half, (c + p1) * half);
+                let mid = (c0 + c1) * half;
+                vec![[p0, c0, mid], [mid, c1, p1]]
+            })
+            .collect();
+    }
+    curves
+}
+
+/// 凸多角形 (2 点なら線分) 同士が、接するだけでなく内部で重なるかどうか
+fn overlaps<T: Scalar>(a: &[Point<T>], b: &[Point<T>]) -> bool {
+    let epsilon: T = vf(EPSILON);
+    let axes = [a, b].into_iter().flat_map(|polygon| {
+        (0..polygon.len()).map(|i| (polygon[(i + 1) % polygon.len()] - polygon[i]).perp())
+    });
+    let project = |polygon: &[Point<T>], axis: Point<T>| {
+        polygon
+            .iter()
+            .map(|p| p.dot(axis))
+            .fold((T::infinity(), T::neg_infinity()), |(lo, hi), d| {
+                (lo.min(d), hi.max(d))
+            })
+    };
+    for axis in axes {
+        let axis = axis.normalize_or_zero();
+        if axis == Point::<T>::ZERO {
+            continue;
+        }
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        if a_max <= b_min + epsilon || b_max <= a_min + epsilon {
+            return false;
+        }
+    }
+    true
+}
+
+/// 下端から上端へ向かう辺
+struct Edge<T: Scalar> {
+    bottom: Point<T>,
+    top: Point<T>,
+    /// 元の辺が上向きなら 1、下向きなら -1
+    winding: i32,
+}
+
+impl<T: Scalar> Edge<T> {
+    fn x_at(&self, y: T) -> T {
+        if y <= self.bottom.y() {
+            return self.bottom.x();
+        }
+        if y >= self.top.y() {
+            return self.top.x();
+        }
+        let t = (y - self.bottom.y()) / (self.top.y() - self.bottom.y());
+        self.bottom.x() + (self.top.x() - self.bottom.x()) * t
+    }
+}
+
+/// 折れ線で囲まれた領域を、頂点と交点の y 座標で切った帯ごとに台形に分ける
+fn fill_polygon<T: Scalar>(
+    edges: &[[Point<T>; 2]],
+    fill_rule: FillRule,
+    mesh: &mut DisjointBuilder<T>,
+) {
+    let edges: Vec<Edge<T>> = edges
+        .iter()
+        .filter(|[a, b]| a.y() != b.y())
+        .map(|&[a, b]| {
+            let (bottom, top, winding) = if a.y() < b.y() { (a, b, 1) } else { (b, a, -1) };
+            Edge {
+                bottom,
+                top,
+                winding,
+            }
+        })
+        .collect();
+
+    let mut ys: Vec<T> = edges
+        .iter()
+        .flat_map(|e| [e.bottom.y(), e.top.y()])
+        .collect();
+    for (i, a) in edges.iter().enumerate() {
+        for b in &edges[i + 1..] {
+            if let Some(y) = crossing_y(a, b) {
+                ys.push(y);
+            }
+        }
+    }
+    ys.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    ys.dedup();
+
+    let epsilon: T = vf(EPSILON);
+    for slab in ys.windows(2) {
+        let (y0, y1) = (slab[0], slab[1]);
+        if y1 - y0 <= epsilon * epsilon {
+            continue;
+        }
+        let ym = (y0 + y1) * vf(0.5);
+        let mut spans: Vec<_> = edges
+            .iter()
+            .filter(|e| e.bottom.y() <= y0 && e.top.y() >= y1)
+            .map(|e| (e.x_at(ym), e.x_at(y0), e.x_at(y1), e.winding))
+            .collect();
+        spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut winding = 0;
+        let mut left = (T::zero(), T::zero());
+        for (_, x0, x1, w) in spans {
+            let was_inside = fill_rule.is_inside(winding);
+            winding += w;
+            match (was_inside, fill_rule.is_inside(winding)) {
+                (false, true) => left = (x0, x1),
+                (true, false) => mesh.push_trapezoid(y0, y1, left, (x0, x1)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 2 辺が両方の内部で交わる点の y 座標
+fn crossing_y<T: Scalar>(a: &Edge<T>, b: &Edge<T>) -> Option<T> {
+    if a.top.y() <= b.bottom.y() || b.top.y() <= a.bottom.y() {
+        return None;
+    }
+    let (da, db) = (a.top - a.bottom, b.top - b.bottom);
+    let denominator = da.perp_dot(db);
+    if denominator.is_zero() {
+        return None;
+    }
+    let offset = b.bottom - a.bottom;
+    let t = offset.perp_dot(db) / denominator;
+    let s = offset.perp_dot(da) / denominator;
+    let unit = T::zero()..=T::one();
+    (unit.contains(&t) && unit.contains(&s)).then_some(a.bottom.y() + da.y() * t)
+}
+
+/// 同じ位置の多角形の頂点をまとめながら、重ならないメッシュを組み立てる
+struct DisjointBuilder<T: Scalar> {
+    mesh: Mesh<T>,
+    shared: HashMap<(u64, u64), u32>,
+}
+
+impl<T: Scalar> Default for DisjointBuilder<T> {
+    fn default() -> Self {
+        Self {
+            mesh: Mesh::default(),
+            shared: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Scalar> DisjointBuilder<T> {
+    fn push_corner(&mut self, x: T, y: T) -> u32 {
+        let key = (x.to_f64().unwrap().to_bits(), y.to_f64().unwrap().to_bits());
+        if let Some(&index) = self.shared.get(&key) {
+            return index;
+        }
+        let index = self
+            .mesh
+            .push_vertex(Point::<T>::new(x, y), T::zero(), T::one());
+        self.shared.insert(key, index);
+        index
+    }
+
+    /// 塗る側が弦の側なら反時計回り、制御点の側なら時計回りに並べた曲線三角形
+    fn push_curve(&mut self, [p0, c, p1]: [Point<T>; 3], chord_side: bool) {
+        let i0 = self.mesh.push_vertex(p0, T::zero(), T::zero());
+        let i1 = self.mesh.push_vertex(c, vf(0.5), T::zero());
+        let i2 = self.mesh.push_vertex(p1, T::one(), T::one());
+        let counter_clockwise = (c - p0).perp_dot(p1 - p0) > T::zero();
+        if counter_clockwise == chord_side {
+            self.mesh.indices.extend([i0, i1, i2]);
+        } else {
+            self.mesh.indices.extend([i0, i2, i1]);
+        }
+    }
+
+    /// y0 での左右 `(left.0, right.0)` と y1 での左右 `(left.1, right.1)` を結ぶ台形
+    fn push_trapezoid(&mut self, y0: T, y1: T, left: (T, T), right: (T, T)) {
+        let l0 = self.push_corner(left.0, y0);
+        let r0 = self.push_corner(right.0, y0);
+        let r1 = self.push_corner(right.1, y1);
+        let l1 = self.push_corner(left.1, y1);
+        if right.0 > left.0 {
+            self.mesh.indices.extend([l0, r0, r1]);
+        }
+        if right.1 > left.1 {
+            self.mesh.indices.extend([l0, r1, l1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::*;
+    use crate::svg::parse_path;
+
+    /// 三角形の重心座標
+    fn barycentric(t: [DVec2; 3], p: DVec2) -> [f64; 3] {
+        let area = (t[1] - t[0]).perp_dot(t[2] - t[0]);
+        let w1 = (p - t[0]).perp_dot(t[2] - t[0]) / area;
+        let w2 = (t[1] - t[0]).perp_dot(p - t[0]) / area;
+        [1.0 - w1 - w2, w1, w2]
+    }
+
+    /// メッシュをステンシルと同じ要領で評価した巻き数
+    fn mesh_winding(mesh: &Mesh<f64>, p: DVec2) -> i32 {
+        mesh.indices
+            .chunks(3)
+            .map(|t| {
+                let v = [0, 1, 2].map(|i| mesh.vertices[t[i] as usize]);
+                let points = v.map(|v| DVec2::new(v.x, v.y));
+                let w = barycentric(points, p);
+                if w.iter().any(|w| *w < 0.0) {
+                    return 0;
+                }
+                let u = w[0] * v[0].u + w[1] * v[1].u + w[2] * v[2].u;
+                let tv = w[0] * v[0].v + w[1] * v[1].v + w[2] * v[2].v;
+                if u * u - tv >= 0.0 {
+                    return 0;
+                }
+                (points[1] - points[0])
+                    .perp_dot(points[2] - points[0])
+                    .signum() as i32
+            })
+            .sum()
+    }
+
+    /// 折れ線にしたパスで求めた巻き数と境界までの距離
+    fn reference_winding(path: &Path<f64>, p: DVec2) -> (i32, f64) {
+        let mut winding = 0;
+        let mut distance = f64::MAX;
+        for contour in &path.to_quadratic().contours {
+            for q in contour.segments.iter().flat_map(|s| s.to_quadratic()) {
+                for i in 0..64 {
+                    let a = q.calc_point(i as f64 / 64.0).unwrap();
+                    let b = q.calc_point((i + 1) as f64 / 64.0).unwrap();
+                    let d = b - a;
+                    let t = ((p - a).dot(d) / d.length_squared().max(1e-12)).clamp(0.0, 1.0);
+                    distance = distance.min(p.distance(a + d * t));
+                    if (a.y <= p.y) != (b.y <= p.y) {
+                        let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                        if x > p.x {
+                            winding += if b.y > a.y { 1 } else { -1 };
+                        }
+                    }
+                }
+            }
+        }
+        (winding, distance)
+    }
+
+    #[test]
+    fn curve_points_satisfy_implicit_form() {
+        let path = parse_path::<f64>("M0 0 Q50 80 100 0 Z").unwrap();
+        let mesh = triangulate(&path);
+        let curve = mesh
+            .indices
+            .chunks(3)
+            .find(|t| mesh.vertices[t[1] as usize].u == 0.5)
+            .unwrap();
+        let v = [0, 1, 2].map(|i| mesh.vertices[curve[i] as usize]);
+        let q = path.to_quadratic().contours[0].segments[0].to_quadratic()[0];
+        for t in [0.1, 0.3, 0.5, 0.9] {
+            let p = q.calc_point(t).unwrap();
+            let w = barycentric(v.map(|v| DVec2::new(v.x, v.y)), p);
+            let u = w[0] * v[0].u + w[1] * v[1].u + w[2] * v[2].u;
+            let tv = w[0] * v[0].v + w[1] * v[1].v + w[2] * v[2].v;
+            assert!((u - t).abs() < 1e-9);
+            assert!((u * u - tv).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn mesh_winding_matches_path() {
+        let path = parse_path::<f64>(
+            "M10 10 C40 -10 80 30 90 10 L90 90 Q50 50 10 90 Q30 50 10 10 Z \
+             M30 30 L30 60 L70 60 L70 30 Z \
+             M20 20 Q50 110 80 20 Z",
+        )
+        .unwrap();
+        let mesh = triangulate(&path);
+        assert_eq!(mesh.indices.len() % 3, 0);
+        for y in 0..100 {
+            for x in 0..100 {
+                // 扇形の辺にちょうど乗らないよう画素中心からずらす
+                let p = DVec2::new(x as f64 + 0.37, y as f64 + 0.61);
+                let (expected, distance) = reference_winding(&path, p);
+                if distance > 0.1 {
+                    assert_eq!(mesh_winding(&mesh, p), expected, "{p}");
+                }
+            }
+        }
+    }
+
+    /// 重ならないメッシュで点を塗る三角形の数。三角形の向きで塗る側を決める
+    fn disjoint_hits(mesh: &Mesh<f64>, p: DVec2) -> usize {
+        mesh.indices
+            .chunks(3)
+            .filter(|t| {
+                let v = [0, 1, 2].map(|i| mesh.vertices[t[i] as usize]);
+                let points = v.map(|v| DVec2::new(v.x, v.y));
+                let w = barycentric(points, p);
+                if w.iter().any(|w| *w < 0.0) {
+                    return false;
+                }
+                let u = w[0] * v[0].u + w[1] * v[1].u + w[2] * v[2].u;
+                let tv = w[0] * v[0].v + w[1] * v[1].v + w[2] * v[2].v;
+                let counter_clockwise =
+                    (points[1] - points[0]).perp_dot(points[2] - points[0]) > 0.0;
+                (u * u - tv < 0.0) == counter_clockwise
+            })
+            .count()
+    }
+
+    fn assert_disjoint_matches(path: &Path<f64>, fill_rule: FillRule, size: usize) {
+        let mesh = triangulate_disjoint(path, fill_rule);
+        assert_eq!(mesh.indices.len() % 3, 0);
+        for y in 0..size {
+            for x in 0..size {
+                let p = DVec2::new(x as f64 + 0.37, y as f64 + 0.61);
+                let hits = disjoint_hits(&mesh, p);
+                assert!(hits <= 1, "{hits} triangles at {p}");
+                let (winding, distance) = reference_winding(path, p);
+                if distance > 0.1 {
+                    assert_eq!(hits == 1, fill_rule.is_inside(winding), "{p}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn disjoint_non_star_shape() {
+        // 中心から見通せない U 字に、内側の曲線を付けたもの
+        let path = parse_path::<f64>(
+            "M4 36 L4 12 Q4 2 20 2 Q36 2 36 12 L36 36 L28 36 L28 14 \
+             Q28 9 20 9 Q12 9 12 14 L12 36 Z",
+        )
+        .unwrap();
+        assert_disjoint_matches(&path, FillRule::NonZero, 40);
+    }
+
+    #[test]
+    fn disjoint_ring_and_overlap() {
+        // 逆向きの円で穴を開けた輪に、長方形を重ねたもの
+        let path = parse_path::<f64>(
+            "M36 20 C36 28.8 28.8 36 20 36 C11.2 36 4 28.8 4 20 C4 11.2 11.2 4 20 4 \
+             C28.8 4 36 11.2 36 20 Z \
+             M33 20 C33 12.8 27.2 7 20 7 C12.8 7 7 12.8 7 20 C7 27.2 12.8 33 20 33 \
+             C27.2 33 33 27.2 33 20 Z \
+             M2 18 L38 18 L38 23 L2 23 Z",
+        )
+        .unwrap();
+        assert_disjoint_matches(&path, FillRule::NonZero, 40);
+        assert_disjoint_matches(&path, FillRule::EvenOdd, 40);
+    }
+
+    /// 曲線三角形の頂点
+    fn curve_triangles(mesh: &Mesh<f64>) -> Vec<[DVec2; 3]> {
+        mesh.indices
+            .chunks(3)
+            .map(|t| [0, 1, 2].map(|i| mesh.vertices[t[i] as usize]))
+            .filter(|v| v.iter().any(|v| v.u == 0.5))
+            .map(|v| v.map(|v| DVec2::new(v.x, v.y)))
+            .collect()
+    }
+
+    #[test]
+    fn disjoint_thin_arc() {
+        // 細い弧では制御点の三角形が反対側の輪郭まではみ出すので、曲線を分ける必要がある
+        let path = parse_path::<f64>("M0 0 Q20 40 40 0 L38 0 Q20 36 2 0 Z").unwrap();
+        let mesh = triangulate_disjoint(&path, FillRule::NonZero);
+        assert!(curve_triangles(&mesh).len() > 2);
+        assert_disjoint_matches(&path, FillRule::NonZero, 40);
+    }
+
+    #[test]
+    fn disjoint_concave_curve_is_clockwise() {
+        let path = parse_path::<f64>("M0 0 L40 0 L40 20 Q20 5 0 20 Z").unwrap();
+        let mesh = triangulate_disjoint(&path, FillRule::NonZero);
+        let curves = curve_triangles(&mesh);
+        assert_eq!(curves.len(), 1);
+        let p = curves[0];
+        assert!((p[1] - p[0]).perp_dot(p[2] - p[0]) < 0.0);
+        assert_disjoint_matches(&path, FillRule::NonZero, 40);
+    }
+
+    #[test]
+    fn lines_need_no_curve_triangles() {
+        let path = parse_path::<f32>("M0 0 H10 V10 H0 Z").unwrap();
+        let mesh = triangulate(&path);
+        assert_eq!(mesh.indices.len(), 6);
+        assert!(mesh.vertices.iter().all(|v| v.u == 0.0 && v.v == 1.0));
+    }
+}
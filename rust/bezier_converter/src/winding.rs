@@ -0,0 +1,341 @@
+use crate::{
+    CubicBezier, QuadraticBezier,
+    path::{FillRule, Line, Path, Segment},
+    scalar::{Point, Scalar, Vector, vf},
+};
+
+// 巻き数はどれも p から +x 方向に伸ばした半直線と交わる向きを数えて求める。
+// 曲線は y 方向に単調な範囲に分け、y 座標が p と一致する媒介変数を方程式の解として求めるので、
+// 折れ線に分割したときのような誤差は出ない。
+
+impl<T: Scalar> Line<T> {
+    /// 点 p に対する巻き数 (上向きに横切ると +1)
+    pub fn winding(&self, p: Point<T>) -> i32 {
+        if (self.y0 <= p.y()) == (self.y1 <= p.y()) {
+            return 0;
+        }
+        let t = (p.y() - self.y0) / (self.y1 - self.y0);
+        let x = self.x0 + (self.x1 - self.x0) * t;
+        crossing(x > p.x(), self.y0, self.y1)
+    }
+}
+
+impl<T: Scalar> QuadraticBezier<T> {
+    /// 点 p に対する巻き数 (上向きに横切ると +1)
+    pub fn winding(&self, p: Point<T>) -> i32 {
+        let two: T = vf(2.0);
+        // y(t) = a t² + b t + c
+        let a = self.y0 - two * self.cy0 + self.y1;
+        let b = two * (self.cy0 - self.y0);
+        let c = self.y0;
+        let extrema = solve_quadratic(T::zero(), two * a, b);
+        monotone_winding(
+            &extrema,
+            p,
+            |t| self.calc_point(t).unwrap(),
+            |t0, t1| {
+                let roots = solve_quadratic(a, b, c - p.y());
+                polish(
+                    &roots,
+                    t0,
+                    t1,
+                    |t| (a * t + b) * t + c - p.y(),
+                    |t| two * a * t + b,
+                )
+            },
+        )
+    }
+}
+
+impl<T: Scalar> CubicBezier<T> {
+    /// 点 p に対する巻き数 (上向きに横切ると +1)
+    pub fn winding(&self, p: Point<T>) -> i32 {
+        let three: T = vf(3.0);
+        // y(t) = a t³ + b t² + c t + d
+        let a = self.y1 - self.y0 + three * (self.cy0 - self.cy1);
+        let b = three * (self.y0 - vf::<T>(2.0) * self.cy0 + self.cy1);
+        let c = three * (self.cy0 - self.y0);
+        let d = self.y0;
+        let extrema = solve_quadratic(three * a, vf::<T>(2.0) * b, c);
+        monotone_winding(
+            &extrema,
+            p,
+            |t| self.calc_point(t).unwrap(),
+            |t0, t1| {
+                let roots = solve_cubic(a, b, c, d - p.y());
+                polish(
+                    &roots,
+                    t0,
+                    t1,
+                    |t| ((a * t + b) * t + c) * t + d - p.y(),
+                    |t| (three * a * t + vf::<T>(2.0) * b) * t + c,
+                )
+            },
+        )
+    }
+}
+
+impl<T: Scalar> Segment<T> {
+    /// 点 p に対する巻き数 (上向きに横切ると +1)
+    pub fn winding(&self, p: Point<T>) -> i32 {
+        match self {
+            Segment::Line(l) => l.winding(p),
+            Segment::Quadratic(q) => q.winding(p),
+            Segment::Cubic(c) => c.winding(p),
+        }
+    }
+}
+
+impl<T: Scalar> Path<T> {
+    /// 点 p に対する巻き数
+    ///
+    /// 開いた輪郭は終点から始点へ直線で閉じているものとして数える。
+    pub fn winding(&self, p: Point<T>) -> i32 {
+        self.contours
+            .iter()
+            .map(|contour| {
+                let (Some(first), Some(last)) = (contour.segments.first(), contour.segments.last())
+                else {
+                    return 0;
+                };
+                let closing = Line {
+                    x0: last.end().x(),
+                    y0: last.end().y(),
+                    x1: first.start().x(),
+                    y1: first.start().y(),
+                };
+                contour.segments.iter().map(|s| s.winding(p)).sum::<i32>() + closing.winding(p)
+            })
+            .sum()
+    }
+
+    /// 点 p が塗りつぶされる側にあるかどうか
+    pub fn contains(&self, p: Point<T>, fill_rule: FillRule) -> bool {
+        fill_rule.is_inside(self.winding(p))
+    }
+}
+
+fn crossing<T: Scalar>(right: bool, y0: T, y1: T) -> i32 {
+    match (right, y1 > y0) {
+        (false, _) => 0,
+        (true, true) => 1,
+        (true, false) => -1,
+    }
+}
+
+/// y の極値で区切った範囲ごとに、p の高さを横切る位置を調べる
+fn monotone_winding<T: Scalar>(
+    extrema: &[T],
+    p: Point<T>,
+    point: impl Fn(T) -> Point<T>,
+    solve: impl Fn(T, T) -> T,
+) -> i32 {
+    let mut ts = vec![T::zero()];
+    ts.extend(
+        extrema
+            .iter()
+            .copied()
+            .filter(|t| T::zero() < *t && *t < T::one()),
+    );
+    ts.push(T::one());
+    ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut winding = 0;
+    for range in ts.windows(2) {
+        let (t0, t1) = (range[0], range[1]);
+        let (y0, y1) = (point(t0).y(), point(t1).y());
+        if (y0 <= p.y()) == (y1 <= p.y()) {
+            continue;
+        }
+        let t = solve(t0, t1);
+        winding += crossing(point(t).x() > p.x(), y0, y1);
+    }
+    winding
+}
+
+/// 単調な範囲 t0..t1 にある解を選び、Newton 法で丸め誤差を取り除く
+fn polish<T: Scalar>(roots: &[T], t0: T, t1: T, f: impl Fn(T) -> T, df: impl Fn(T) -> T) -> T {
+    let clamp = |t: T| t.max(t0).min(t1);
+    let mut t = roots
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let da = (clamp(*a) - *a).abs();
+            let db = (clamp(*b) - *b).abs();
+            da.partial_cmp(&db).unwrap()
+        })
+        .map(clamp)
+        .unwrap_or((t0 + t1) * vf(0.5));
+    for _ in 0..2 {
+        let slope = df(t);
+        if slope.is_zero() {
+            break;
+        }
+        t = clamp(t - f(t) / slope);
+    }
+    t
+}
+
+/// a t² + b t + c = 0 の実数解
+pub(crate) fn solve_quadratic<T: Scalar>(a: T, b: T, c: T) -> Vec<T> {
+    if a.abs() <= T::epsilon() * (b.abs() + c.abs()) {
+        if b.is_zero() {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - vf::<T>(4.0) * a * c;
+    if discriminant < T::zero() {
+        return vec![];
+    }
+    // 桁落ちしないように絶対値の大きい方から求める
+    let q = -(b + b.signum() * discriminant.sqrt()) / vf(2.0);
+    if q.is_zero() {
+        return vec![T::zero()];
+    }
+    vec![q / a, c / q]
+}
+
+/// a t³ + b t² + c t + d = 0 の実数解
+pub(crate) fn solve_cubic<T: Scalar>(a: T, b: T, c: T, d: T) -> Vec<T> {
+    if a.abs() <= T::epsilon() * (b.abs() + c.abs() + d.abs()) {
+        return solve_quadratic(b, c, d);
+    }
+    let three: T = vf(3.0);
+    let (b, c, d) = (b / a, c / a, d / a);
+    // t = s - b / 3 と置いて s³ + p s + q = 0 にする
+    let shift = b / three;
+    let p = c - b * shift;
+    let q = vf::<T>(2.0) * shift * shift * shift - shift * c + d;
+    let half_q = q / vf(2.0);
+    let discriminant = half_q * half_q + p * p * p / vf(27.0);
+
+    let roots = if discriminant > T::zero() {
+        let sqrt = discriminant.sqrt();
+        vec![(-half_q + sqrt).cbrt() + (-half_q - sqrt).cbrt()]
+    } else if p.is_zero() {
+        vec![(-q).cbrt()]
+    } else {
+        // 3 つの実数解は三角関数で求める
+        let r = (-p / three).sqrt();
+        let cos = (-half_q / (r * r * r)).max(-T::one()).min(T::one());
+        let phi = cos.acos() / three;
+        let step = T::TAU() / three;
+        (0..3)
+            .map(|k| vf::<T>(2.0) * r * (phi - step * vf(k as f64)).cos())
+            .collect()
+    };
+    roots.into_iter().map(|s| s - shift).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::*;
+    use crate::svg::parse_path;
+
+    /// 折れ線にしたパスで求めた巻き数と境界までの距離
+    fn flattened_winding(path: &Path<f64>, p: DVec2) -> (i32, f64) {
+        let mut winding = 0;
+        let mut distance = f64::MAX;
+        for contour in &path.contours {
+            let mut points = vec![];
+            for segment in &contour.segments {
+                let curve = match segment {
+                    Segment::Line(l) => l.to_quadratic().elevate(),
+                    Segment::Quadratic(q) => q.elevate(),
+                    Segment::Cubic(c) => *c,
+                };
+                points.extend((0..256).map(|i| curve.calc_point(i as f64 / 256.0).unwrap()));
+            }
+            points.push(points[0]);
+            for pair in points.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let d = b - a;
+                let t = ((p - a).dot(d) / d.length_squared().max(1e-12)).clamp(0.0, 1.0);
+                distance = distance.min(p.distance(a + d * t));
+                if (a.y <= p.y) != (b.y <= p.y) {
+                    let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                    if x > p.x {
+                        winding += if b.y > a.y { 1 } else { -1 };
+                    }
+                }
+            }
+        }
+        (winding, distance)
+    }
+
+    #[test]
+    fn matches_flattened_path() {
+        let path = parse_path::<f64>(
+            "M10 60 A50 50 0 0 1 110 60 A50 50 0 0 1 10 60 Z \
+             M30 40 C130 120 -20 120 90 40 Z \
+             M20 20 Q60 100 100 20 L100 10 Z",
+        )
+        .unwrap();
+        for y in 0..60 {
+            for x in 0..60 {
+                let p = DVec2::new(x as f64 * 2.0 + 0.37, y as f64 * 2.0 + 0.61);
+                let (expected, distance) = flattened_winding(&path, p);
+                if distance > 0.05 {
+                    assert_eq!(path.winding(p), expected, "{p}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_near_curve() {
+        let path = parse_path::<f64>("M0 0 Q50 100 100 0 Z").unwrap();
+        // 頂点は (50, 50)
+        assert_eq!(path.winding(DVec2::new(50.0, 50.0 - 1e-9)).abs(), 1);
+        assert_eq!(path.winding(DVec2::new(50.0, 50.0 + 1e-9)), 0);
+
+        let path = parse_path::<f64>("M0 0 C0 100 100 100 100 0 Z").unwrap();
+        // 頂点は (50, 75)
+        assert_eq!(path.winding(DVec2::new(50.0, 75.0 - 1e-9)).abs(), 1);
+        assert_eq!(path.winding(DVec2::new(50.0, 75.0 + 1e-9)), 0);
+    }
+
+    #[test]
+    fn ray_through_vertices() {
+        let diamond = parse_path::<f32>("M0 -10 L10 0 L0 10 L-10 0 Z").unwrap();
+        assert_eq!(diamond.winding(glam::Vec2::new(-20.0, 0.0)), 0);
+        assert_eq!(diamond.winding(glam::Vec2::new(0.0, 0.0)).abs(), 1);
+        assert_eq!(diamond.winding(glam::Vec2::new(9.0, 0.0)).abs(), 1);
+        // 極値がちょうど半直線上にある曲線
+        let bump = parse_path::<f32>("M0 0 Q10 20 20 0 Q10 -20 0 0 Z").unwrap();
+        assert_eq!(bump.winding(glam::Vec2::new(-5.0, 10.0)), 0);
+        assert_eq!(bump.winding(glam::Vec2::new(-5.0, 0.0)), 0);
+        assert_eq!(bump.winding(glam::Vec2::new(10.0, 0.0)).abs(), 1);
+    }
+
+    #[test]
+    fn fill_rules() {
+        let path =
+            parse_path::<f64>("M0 0 H60 V60 H0 Z M30 30 H90 V90 H30 Z M10 10 L20 10").unwrap();
+        let overlap = DVec2::new(45.0, 45.0);
+        assert_eq!(path.winding(overlap).abs(), 2);
+        assert!(path.contains(overlap, FillRule::NonZero));
+        assert!(!path.contains(overlap, FillRule::EvenOdd));
+        assert!(path.contains(DVec2::new(5.0, 5.0), FillRule::EvenOdd));
+        // 開いた輪郭は閉じて数える
+        let open = parse_path::<f64>("M0 0 H10 V10").unwrap();
+        assert!(open.contains(DVec2::new(8.0, 2.0), FillRule::NonZero));
+        assert!(!open.contains(DVec2::new(2.0, 8.0), FillRule::NonZero));
+    }
+
+    #[test]
+    fn cubic_roots() {
+        let mut roots = solve_cubic(1.0f64, -6.0, 11.0, -6.0);
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        assert_eq!(solve_cubic(0.0f64, 1.0, -3.0, 2.0).len(), 2);
+        let roots = solve_cubic(1.0f64, 0.0, 0.0, -8.0);
+        assert_eq!(roots.len(), 1);
+        assert!((roots[0] - 2.0).abs() < 1e-9);
+    }
+}
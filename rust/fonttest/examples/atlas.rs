@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use fonttest::atlas::{AtlasOptions, build_atlas};
+use fonttest::font_file::FontFile;
+use fonttest::variation::Variation;
+use image::ImageFormat;
+
+/// フォントのパスは必須の引数
+const USAGE: &str = "usage: cargo run --example atlas -- <FONT> [AXIS=VALUE ...]";
+
+/// 2 つ目以降の引数で可変フォントの軸を指定できる (`wght=700` など)
+fn main() -> Result<()> {
+    let mut font = FontFile::open(std::env::args().nth(1).context(USAGE)?)?;
+    let variations = std::env::args()
+        .skip(2)
+        .map(|arg| arg.parse::<Variation>())
+        .collect::<Result<Vec<_>, _>>()?;
+    font.set_variations(&variations)?;
+    let face = font.face();
+    let chars = (' '..='~').chain("あいうえお".chars());
+    let atlas = build_atlas(&face, chars, &AtlasOptions::default())?;
+
+    atlas.image.save_with_format(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/atlas.png"),
+        ImageFormat::Png,
+    )?;
+    std::fs::write(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/atlas.json"),
+        atlas.manifest.to_json()?,
+    )?;
+    std::fs::write(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/atlas.bin"),
+        atlas.manifest.to_bytes(),
+    )?;
+    Ok(())
+}
@@ -1,12 +1,14 @@
 use anyhow::{Context, Result};
+use fonttest::font_file::FontFile;
 use fonttest::{Point, Triangle};
 use image::{ImageBuffer, ImageFormat, Rgba, RgbaImage};
-use ttf_parser::{Face, OutlineBuilder, Rect};
+use ttf_parser::{OutlineBuilder, Rect};
 
 const IMAGE_SIZE_WIDTH: u32 = 256;
 const IMAGE_SIZE_HEIGHT: u32 = 256;
 
-const FONT_DATA: &[u8] = include_bytes!("../src/font/HackGenConsole-Regular.ttf");
+/// フォントのパスは必須の引数
+const USAGE: &str = "usage: cargo run --example font -- <FONT>";
 
 struct ImageBuilder {
     rect: Rect,
@@ -84,20 +86,20 @@ impl ImageBuilder {
                 let p = self.image.get_pixel(x, y);
 
                 let mut color: u8 = 255;
-                if p.0[0] % 2 == 0 {
+                if p.0[0].is_multiple_of(2) {
                     color -= 63
                 }
-                if p.0[1] % 2 == 0 {
+                if p.0[1].is_multiple_of(2) {
                     color -= 63
                 }
-                if p.0[2] % 2 == 0 {
+                if p.0[2].is_multiple_of(2) {
                     color -= 63
                 }
-                if p.0[3] % 2 == 0 {
+                if p.0[3].is_multiple_of(2) {
                     color -= 63
                 }
 
-                if p.0[0] % 2 == 0 {
+                if p.0[0].is_multiple_of(2) {
                     self.image.put_pixel(x, y, Rgba([color, color, color, 255]))
                 } else {
                     self.image.put_pixel(x, y, Rgba([255, 255, 255, 255]))
@@ -107,7 +109,10 @@ impl ImageBuilder {
 
         self.image
             .save_with_format(
-                format!("fonttest/examples/images/{}.png", "write-font"),
+                format!(
+                    concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+                    "write-font"
+                ),
                 ImageFormat::Png,
             )
             .unwrap();
@@ -154,7 +159,8 @@ fn main() -> Result<()> {
 
 fn write_font() -> Result<()> {
     let c = 'あ';
-    let face = Face::parse(FONT_DATA, 0)?;
+    let font = FontFile::open(std::env::args().nth(1).context(USAGE)?)?;
+    let face = font.face();
     let glyph_id = face.glyph_index(c).with_context(|| "hello")?;
 
     let bounding_box = face.global_bounding_box();
@@ -65,10 +65,10 @@ impl ImageBuilder {
                 let p = self.image.get_pixel(x, y);
 
                 let mut color: u8 = 255;
-                if p.0[0] % 2 == 0 {
+                if p.0[0].is_multiple_of(2) {
                     color -= 250
                 }
-                if p.0[0] % 2 == 0 {
+                if p.0[0].is_multiple_of(2) {
                     self.image.put_pixel(x, y, Rgba([color, color, color, 255]))
                 } else {
                     self.image.put_pixel(x, y, Rgba([255, 255, 255, 255]))
@@ -78,7 +78,10 @@ impl ImageBuilder {
 
         self.image
             .save_with_format(
-                format!("fonttest/examples/images/{}.png", "write-font2-debug"),
+                format!(
+                    concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+                    "write-font2-debug"
+                ),
                 ImageFormat::Png,
             )
             .unwrap();
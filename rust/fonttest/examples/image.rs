@@ -1,12 +1,14 @@
 use anyhow::{Context, Result};
+use fonttest::font_file::FontFile;
 use image::{ImageBuffer, ImageFormat, Rgb, RgbImage};
 use std::fmt::Write;
-use ttf_parser::{Face, OutlineBuilder, Rect};
+use ttf_parser::{OutlineBuilder, Rect};
 
 const IMAGE_SIZE_WIDTH: u32 = 256;
 const IMAGE_SIZE_HEIGHT: u32 = 256;
 
-const FONT_DATA: &[u8] = include_bytes!("../src/font/HackGenConsole-Regular.ttf");
+/// フォントのパスは必須の引数
+const USAGE: &str = "usage: cargo run --example image -- <FONT>";
 
 struct ImageBuilder {
     rect: Rect,
@@ -56,7 +58,10 @@ impl OutlineBuilder for ImageBuilder {
     fn close(&mut self) {
         self.data
             .save_with_format(
-                format!("fonttest/examples/images/{}.png", "font"),
+                format!(
+                    concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+                    "font"
+                ),
                 ImageFormat::Png,
             )
             .unwrap();
@@ -94,7 +99,8 @@ fn main() -> Result<()> {
 
 fn write_font() -> Result<()> {
     let c = 'あ';
-    let face = Face::parse(FONT_DATA, 0)?;
+    let font = FontFile::open(std::env::args().nth(1).context(USAGE)?)?;
+    let face = font.face();
     let glyph_id = face.glyph_index(c).with_context(|| "hello")?;
     let mut builder = Builder(String::new());
 
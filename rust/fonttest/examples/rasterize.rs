@@ -0,0 +1,23 @@
+use anyhow::{Context, Result};
+use fonttest::font_file::FontFile;
+use fonttest::rasterize_glyph;
+use image::ImageFormat;
+
+/// フォントのパスは必須の引数
+const USAGE: &str = "usage: cargo run --example rasterize -- <FONT>";
+
+fn main() -> Result<()> {
+    let c = 'あ';
+    let font = FontFile::open(std::env::args().nth(1).context(USAGE)?)?;
+    let face = font.face();
+    let glyph_id = face.glyph_index(c).context("glyph_index")?;
+    let coverage = rasterize_glyph(&face, glyph_id, 64.0).context("rasterize_glyph")?;
+    coverage.to_image().save_with_format(
+        format!(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+            "rasterize"
+        ),
+        ImageFormat::Png,
+    )?;
+    Ok(())
+}
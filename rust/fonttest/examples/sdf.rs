@@ -0,0 +1,33 @@
+use anyhow::{Context, Result};
+use fonttest::font_file::FontFile;
+use fonttest::sdf::{SdfOptions, msdf_glyph, sdf_glyph};
+use image::ImageFormat;
+
+/// フォントのパスは必須の引数
+const USAGE: &str = "usage: cargo run --example sdf -- <FONT>";
+
+fn main() -> Result<()> {
+    let c = 'あ';
+    let font = FontFile::open(std::env::args().nth(1).context(USAGE)?)?;
+    let face = font.face();
+    let glyph_id = face.glyph_index(c).context("glyph_index")?;
+    let options = SdfOptions::default();
+
+    let sdf = sdf_glyph(&face, glyph_id, 32.0, &options).context("sdf_glyph")?;
+    sdf.image.save_with_format(
+        format!(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+            "sdf"
+        ),
+        ImageFormat::Png,
+    )?;
+    let msdf = msdf_glyph(&face, glyph_id, 32.0, &options).context("msdf_glyph")?;
+    msdf.image.save_with_format(
+        format!(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+            "msdf"
+        ),
+        ImageFormat::Png,
+    )?;
+    Ok(())
+}
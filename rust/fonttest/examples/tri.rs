@@ -37,7 +37,10 @@ fn main() -> Result<()> {
     }
 
     image.save_with_format(
-        format!("fonttest/examples/images/{}.png", "tri"),
+        format!(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/examples/images/{}.png"),
+            "tri"
+        ),
         ImageFormat::Png,
     )?;
     Ok(())
@@ -0,0 +1,443 @@
+//! 文字集合のグリフを 1 枚のテクスチャに詰め込み、位置とメトリクスを書き出す
+
+use anyhow::{Result, anyhow, ensure};
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use ttf_parser::Face;
+
+use crate::raster::rasterize_glyph;
+
+const MAGIC: &[u8; 4] = b"FATL";
+const VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasOptions {
+    /// em の大きさ (ピクセル)
+    pub size_px: f32,
+    /// テクスチャの幅。高さは詰め込んだ結果で決まる
+    pub width: u32,
+    /// グリフどうしの間隔 (ピクセル)
+    pub padding: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            size_px: 32.0,
+            width: 512,
+            padding: 1,
+        }
+    }
+}
+
+/// アトラス内の 1 グリフの情報。長さはすべてピクセル単位
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GlyphEntry {
+    pub character: char,
+    pub glyph_id: u16,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// テクスチャ座標 (左上と右下、0 から 1)
+    pub uv: [f32; 4],
+    /// 原点からビットマップ左端まで
+    pub bearing_x: i32,
+    /// ベースラインからビットマップ上端まで (上向きが正)
+    pub bearing_y: i32,
+    pub advance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub size_px: f32,
+    pub width: u32,
+    pub height: u32,
+    pub ascender: f32,
+    pub descender: f32,
+    pub line_gap: f32,
+    pub glyphs: Vec<GlyphEntry>,
+}
+
+pub struct Atlas {
+    pub image: GrayImage,
+    pub manifest: Manifest,
+}
+
+/// 文字集合をラスタライズしてアトラスにする
+///
+/// 文字集合は `('a'..='z').chain('0'..='9')` のような範囲や `text.chars()` で渡す。
+/// 重複は除き、フォントにない文字は含めない。輪郭のない文字 (空白など) は
+/// 大きさ 0 のエントリとして送り幅だけを持つ。
+pub fn build_atlas(
+    face: &Face,
+    chars: impl IntoIterator<Item = char>,
+    options: &AtlasOptions,
+) -> Result<Atlas> {
+    let scale = options.size_px / face.units_per_em() as f32;
+    let mut chars: Vec<char> = chars.into_iter().collect();
+    chars.sort_unstable();
+    chars.dedup();
+
+    let mut glyphs = Vec::new();
+    for c in chars {
+        let Some(glyph_id) = face.glyph_index(c) else {
+            continue;
+        };
+        let coverage = rasterize_glyph(face, glyph_id, options.size_px);
+        let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale;
+        glyphs.push((c, glyph_id, coverage, advance));
+    }
+
+    // 高いグリフから詰めると隙間が少なくなる
+    let mut order: Vec<usize> = (0..glyphs.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(glyphs[i].2.as_ref().map_or(0, |c| c.height)));
+
+    let mut packer = SkylinePacker::new(options.width);
+    let mut positions = vec![(0, 0); glyphs.len()];
+    for i in order {
+        let Some(coverage) = &glyphs[i].2 else {
+            continue;
+        };
+        let (w, h) = (
+            coverage.width + options.padding,
+            coverage.height + options.padding,
+        );
+        positions[i] = packer.pack(w, h).ok_or_else(|| {
+            anyhow!(
+                "glyph {:?} ({w}x{h}) does not fit in atlas width {}",
+                glyphs[i].0,
+                options.width
+            )
+        })?;
+    }
+
+    let height = packer.height().max(1);
+    let mut image = GrayImage::new(options.width, height);
+    let mut entries = Vec::with_capacity(glyphs.len());
+    for ((c, glyph_id, coverage, advance), (x, y)) in glyphs.into_iter().zip(positions) {
+        let (width, h, bearing_x, bearing_y) = match &coverage {
+            Some(coverage) => {
+                for cy in 0..coverage.height {
+                    for cx in 0..coverage.width {
+                        image.put_pixel(x + cx, y + cy, [coverage.get(cx, cy)].into());
+                    }
+                }
+                (coverage.width, coverage.height, coverage.left, coverage.top)
+            }
+            None => (0, 0, 0, 0),
+        };
+        let uv = [
+            x as f32 / options.width as f32,
+            y as f32 / height as f32,
+            (x + width) as f32 / options.width as f32,
+            (y + h) as f32 / height as f32,
+        ];
+        entries.push(GlyphEntry {
+            character: c,
+            glyph_id: glyph_id.0,
+            x,
+            y,
+            width,
+            height: h,
+            uv,
+            bearing_x,
+            bearing_y,
+            advance,
+        });
+    }
+
+    Ok(Atlas {
+        image,
+        manifest: Manifest {
+            size_px: options.size_px,
+            width: options.width,
+            height,
+            ascender: face.ascender() as f32 * scale,
+            descender: face.descender() as f32 * scale,
+            line_gap: face.line_gap() as f32 * scale,
+            glyphs: entries,
+        },
+    })
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// リトルエンディアンのバイナリ形式
+    ///
+    /// ヘッダ `FATL`, version, size_px, width, height, ascender, descender, line_gap,
+    /// グリフ数に続けて、グリフごとに codepoint, glyph_id, x, y, width, height,
+    /// uv[4], bearing_x, bearing_y, advance を 4 バイトずつ並べる。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(36 + self.glyphs.len() * 52);
+        bytes.extend(MAGIC);
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(self.size_px.to_le_bytes());
+        bytes.extend(self.width.to_le_bytes());
+        bytes.extend(self.height.to_le_bytes());
+        bytes.extend(self.ascender.to_le_bytes());
+        bytes.extend(self.descender.to_le_bytes());
+        bytes.extend(self.line_gap.to_le_bytes());
+        bytes.extend((self.glyphs.len() as u32).to_le_bytes());
+        for glyph in &self.glyphs {
+            bytes.extend((glyph.character as u32).to_le_bytes());
+            bytes.extend((glyph.glyph_id as u32).to_le_bytes());
+            for v in [glyph.x, glyph.y, glyph.width, glyph.height] {
+                bytes.extend(v.to_le_bytes());
+            }
+            for v in glyph.uv {
+                bytes.extend(v.to_le_bytes());
+            }
+            bytes.extend(glyph.bearing_x.to_le_bytes());
+            bytes.extend(glyph.bearing_y.to_le_bytes());
+            bytes.extend(glyph.advance.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.get(..4) == Some(MAGIC), "not an atlas manifest");
+        let mut words = bytes[4..]
+            .chunks_exact(4)
+            .map(|c| <[u8; 4]>::try_from(c).unwrap());
+        let mut next = || {
+            words
+                .next()
+                .ok_or_else(|| anyhow!("truncated atlas manifest"))
+        };
+        let version = u32::from_le_bytes(next()?);
+        ensure!(version == VERSION, "unsupported manifest version {version}");
+
+        let size_px = f32::from_le_bytes(next()?);
+        let width = u32::from_le_bytes(next()?);
+        let height = u32::from_le_bytes(next()?);
+        let ascender = f32::from_le_bytes(next()?);
+        let descender = f32::from_le_bytes(next()?);
+        let line_gap = f32::from_le_bytes(next()?);
+        let count = u32::from_le_bytes(next()?);
+        let mut glyphs = Vec::new();
+        for _ in 0..count {
+            let codepoint = u32::from_le_bytes(next()?);
+            let character = char::from_u32(codepoint)
+                .ok_or_else(|| anyhow!("invalid codepoint {codepoint:#x}"))?;
+            let glyph_id = u32::from_le_bytes(next()?) as u16;
+            let x = u32::from_le_bytes(next()?);
+            let y = u32::from_le_bytes(next()?);
+            let width = u32::from_le_bytes(next()?);
+            let height = u32::from_le_bytes(next()?);
+            let mut uv = [0.0; 4];
+            for v in &mut uv {
+                *v = f32::from_le_bytes(next()?);
+            }
+            glyphs.push(GlyphEntry {
+                character,
+                glyph_id,
+                x,
+                y,
+                width,
+                height,
+                uv,
+                bearing_x: i32::from_le_bytes(next()?),
+                bearing_y: i32::from_le_bytes(next()?),
+                advance: f32::from_le_bytes(next()?),
+            });
+        }
+        Ok(Manifest {
+            size_px,
+            width,
+            height,
+            ascender,
+            descender,
+            line_gap,
+            glyphs,
+        })
+    }
+}
+
+/// 幅を固定して下に伸ばしていくスカイライン法の矩形詰め込み
+pub struct SkylinePacker {
+    width: u32,
+    /// 左から順に並べた (x, 高さ, 幅) の水平な区間
+    skyline: Vec<(u32, u32, u32)>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32) -> Self {
+        Self {
+            width,
+            skyline: vec![(0, 0, width)],
+        }
+    }
+
+    /// これまでに使った高さ
+    pub fn height(&self) -> u32 {
+        self.skyline.iter().map(|s| s.1).max().unwrap_or(0)
+    }
+
+    /// w x h の矩形を置く左上の位置を返す。幅に収まらなければ `None`
+    ///
+    /// 置いたときの上端が最も低くなる位置を選び、同じなら左側を選ぶ。
+    pub fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w > self.width {
+            return None;
+        }
+        if w == 0 || h == 0 {
+            return Some((0, 0));
+        }
+
+        let mut best: Option<(u32, usize)> = None;
+        for i in 0..self.skyline.len() {
+            let x = self.skyline[i].0;
+            if x + w > self.width {
+                break;
+            }
+            // x から w の範囲にかかる区間のうち最も高いところに置く
+            let y = self.skyline[i..]
+                .iter()
+                .take_while(|s| s.0 < x + w)
+                .map(|s| s.1)
+                .max()
+                .unwrap();
+            if best.is_none_or(|(best_y, _)| y < best_y) {
+                best = Some((y, i));
+            }
+        }
+        let (y, i) = best?;
+        let x = self.skyline[i].0;
+
+        // 置いた矩形の上端で区間を置き換える
+        let end = x + w;
+        let mut rest = Vec::new();
+        for &(sx, sy, sw) in &self.skyline[i..] {
+            if sx + sw > end {
+                let start = sx.max(end);
+                rest.push((start, sy, sx + sw - start));
+            }
+        }
+        self.skyline.truncate(i);
+        self.skyline.push((x, y + h, w));
+        self.skyline.extend(rest);
+        Some((x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{FontBuilder, rect};
+
+    fn overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    #[test]
+    fn skyline_does_not_overlap() {
+        let mut packer = SkylinePacker::new(64);
+        let mut placed = Vec::new();
+        let mut seed = 12345u32;
+        for _ in 0..100 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let (w, h) = (seed % 20 + 1, (seed >> 8) % 20 + 1);
+            let (x, y) = packer.pack(w, h).unwrap();
+            assert!(x + w <= 64);
+            let r = (x, y, w, h);
+            assert!(placed.iter().all(|&p| !overlaps(p, r)), "{r:?}");
+            placed.push(r);
+        }
+        let area: u32 = placed.iter().map(|r| r.2 * r.3).sum();
+        // 詰め込み率が極端に悪くないこと
+        assert!(area as f32 / (64 * packer.height()) as f32 > 0.6);
+        assert_eq!(packer.pack(65, 1), None);
+    }
+
+    #[test]
+    fn skyline_fills_gaps() {
+        let mut packer = SkylinePacker::new(10);
+        assert_eq!(packer.pack(6, 5), Some((0, 0)));
+        assert_eq!(packer.pack(4, 2), Some((6, 0)));
+        assert_eq!(packer.pack(4, 2), Some((6, 2)));
+        // 低い右側に置ける
+        assert_eq!(packer.pack(4, 1), Some((6, 4)));
+        assert_eq!(packer.pack(10, 1), Some((0, 5)));
+        assert_eq!(packer.height(), 6);
+    }
+
+    #[test]
+    fn atlas_manifest() {
+        let mut font = FontBuilder::new(1000);
+        font.glyph(Some('a'), 600, vec![rect(0, 0, 500, 500)]);
+        font.glyph(Some('b'), 700, vec![rect(100, -200, 600, 800)]);
+        font.glyph(Some(' '), 250, vec![]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let options = AtlasOptions {
+            size_px: 10.0,
+            width: 32,
+            padding: 1,
+        };
+        let atlas = build_atlas(&face, "abba c".chars(), &options).unwrap();
+        let manifest = &atlas.manifest;
+        // 'c' はフォントにないので含まれない
+        let chars: Vec<char> = manifest.glyphs.iter().map(|g| g.character).collect();
+        assert_eq!(chars, vec![' ', 'a', 'b']);
+        assert_eq!((manifest.width, manifest.height), (32, 11));
+        assert_eq!((manifest.ascender, manifest.descender), (8.0, -2.0));
+
+        let space = &manifest.glyphs[0];
+        assert_eq!((space.width, space.height, space.advance), (0, 0, 2.5));
+        let b = &manifest.glyphs[2];
+        assert_eq!((b.x, b.y, b.width, b.height), (0, 0, 5, 10));
+        assert_eq!((b.bearing_x, b.bearing_y, b.advance), (1, 8, 7.0));
+        assert_eq!(b.uv, [0.0, 0.0, 5.0 / 32.0, 10.0 / 11.0]);
+        let a = &manifest.glyphs[1];
+        assert_eq!((a.x, a.y, a.width, a.height), (6, 0, 5, 5));
+
+        // 塗りつぶした矩形がそのまま書き込まれている
+        assert_eq!(atlas.image.get_pixel(b.x + 2, b.y + 5).0, [255]);
+        assert_eq!(atlas.image.get_pixel(a.x + 2, a.y + 2).0, [255]);
+        assert_eq!(atlas.image.get_pixel(a.x + 2, a.y + 5).0, [0]);
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let manifest = Manifest {
+            size_px: 16.0,
+            width: 64,
+            height: 32,
+            ascender: 12.8,
+            descender: -3.2,
+            line_gap: 0.0,
+            glyphs: vec![GlyphEntry {
+                character: 'あ',
+                glyph_id: 42,
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+                uv: [0.1, 0.2, 0.3, 0.4],
+                bearing_x: -1,
+                bearing_y: 10,
+                advance: 16.0,
+            }],
+        };
+        assert_eq!(
+            Manifest::from_json(&manifest.to_json().unwrap()).unwrap(),
+            manifest
+        );
+        let bytes = manifest.to_bytes();
+        assert_eq!(bytes.len(), 36 + 52);
+        assert_eq!(Manifest::from_bytes(&bytes).unwrap(), manifest);
+        assert!(Manifest::from_bytes(&bytes[..40]).is_err());
+        assert!(Manifest::from_bytes(b"nope").is_err());
+    }
+}
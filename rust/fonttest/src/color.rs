@@ -0,0 +1,650 @@
+//! カラーグリフ (COLR/CPAL のレイヤー、sbix / CBDT の埋め込みビットマップ) を描く
+
+use bezier_converter::path::Path;
+use image::{ImageFormat, Rgba, RgbaImage, imageops};
+use ttf_parser::{
+    Face, GlyphId, OutlineBuilder, RasterGlyphImage, RasterImageFormat, RgbaColor, Transform,
+    colr::{ClipBox, CompositeMode, Paint, Painter},
+};
+
+use crate::{
+    Coverage,
+    layout::Layout,
+    raster::{Bounds, fill_path},
+    rasterize_glyph,
+};
+
+/// 描画したカラーグリフ。`left` と `top` の意味は `Coverage` と同じ
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorGlyph {
+    pub left: i32,
+    pub top: i32,
+    /// 乗算済みでないアルファを持つ画像
+    pub image: RgbaImage,
+}
+
+/// グリフを色付きで描画する
+///
+/// COLR のレイヤー、埋め込みビットマップ、輪郭の順に探し、輪郭は `foreground` で塗る。
+pub fn rasterize_color_glyph(
+    face: &Face,
+    glyph_id: GlyphId,
+    size_px: f32,
+    foreground: Rgba<u8>,
+) -> Option<ColorGlyph> {
+    // 対応していないペイントや、描いた結果が完全に透明な COLR グリフはビットマップと輪郭で描く
+    if face.is_color_glyph(glyph_id)
+        && let Some(glyph) = paint_colr(face, glyph_id, size_px, foreground)
+    {
+        return Some(glyph);
+    }
+    let pixels_per_em = size_px.round().clamp(1.0, u16::MAX as f32) as u16;
+    if let Some(glyph) = face
+        .glyph_raster_image(glyph_id, pixels_per_em)
+        .and_then(|image| bitmap_glyph(&image, size_px, foreground))
+    {
+        return Some(glyph);
+    }
+    let coverage = rasterize_glyph(face, glyph_id, size_px)?;
+    Some(tint(&coverage, foreground))
+}
+
+/// レイアウトした文字列を透明な背景に色付きで描く。`faces` はレイアウトしたときと同じ順に渡す
+pub fn render_layout_color(faces: &[Face], layout: &Layout, foreground: Rgba<u8>) -> RgbaImage {
+    let mut image = RgbaImage::new(
+        layout.width.ceil().max(1.0) as u32,
+        layout.height.ceil().max(1.0) as u32,
+    );
+    for line in &layout.lines {
+        for glyph in &line.glyphs {
+            let face = &faces[glyph.face];
+            let Some(color) =
+                rasterize_color_glyph(face, glyph.glyph_id, layout.size_px, foreground)
+            else {
+                continue;
+            };
+            let left = glyph.x.round() as i32 + color.left;
+            let top = line.baseline.round() as i32 - color.top;
+            for (x, y, source) in color.image.enumerate_pixels() {
+                let (px, py) = (left + x as i32, top + y as i32);
+                if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                    continue;
+                }
+                let target = image.get_pixel_mut(px as u32, py as u32);
+                *target = over(*source, *target);
+            }
+        }
+    }
+    image
+}
+
+/// 乗算済みでないアルファどうしの source-over 合成
+fn over(source: Rgba<u8>, target: Rgba<u8>) -> Rgba<u8> {
+    let sa = source.0[3] as f32 / 255.0;
+    let ta = target.0[3] as f32 / 255.0;
+    let alpha = sa + ta * (1.0 - sa);
+    if alpha == 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    Rgba(std::array::from_fn(|i| {
+        if i == 3 {
+            return (alpha * 255.0 + 0.5) as u8;
+        }
+        let c = (source.0[i] as f32 * sa + target.0[i] as f32 * ta * (1.0 - sa)) / alpha;
+        (c + 0.5) as u8
+    }))
+}
+
+/// カバレッジをアルファにして一色で塗る
+fn tint(coverage: &Coverage, color: Rgba<u8>) -> ColorGlyph {
+    let image = RgbaImage::from_fn(coverage.width, coverage.height, |x, y| {
+        let alpha = coverage.get(x, y) as u32 * color.0[3] as u32;
+        Rgba([
+            color.0[0],
+            color.0[1],
+            color.0[2],
+            ((alpha + 127) / 255) as u8,
+        ])
+    });
+    ColorGlyph {
+        left: coverage.left,
+        top: coverage.top,
+        image,
+    }
+}
+
+/// 埋め込みビットマップを復号し、ストライクの大きさから `size_px` に拡大縮小する
+fn bitmap_glyph(
+    raster: &RasterGlyphImage,
+    size_px: f32,
+    foreground: Rgba<u8>,
+) -> Option<ColorGlyph> {
+    let (width, height) = (raster.width as u32, raster.height as u32);
+    let image = match raster.format {
+        RasterImageFormat::PNG => {
+            image::load_from_memory_with_format(raster.data, ImageFormat::Png)
+                .ok()?
+                .to_rgba8()
+        }
+        RasterImageFormat::BitmapPremulBgra32 => {
+            let data = raster.data.get(..(width * height * 4) as usize)?;
+            RgbaImage::from_fn(width, height, |x, y| {
+                let i = ((y * width + x) * 4) as usize;
+                unpremultiply(data[i + 2], data[i + 1], data[i], data[i + 3])
+            })
+        }
+        format => {
+            let (depth, padded) = match format {
+                RasterImageFormat::BitmapMono => (1, true),
+                RasterImageFormat::BitmapMonoPacked => (1, false),
+                RasterImageFormat::BitmapGray2 => (2, true),
+                RasterImageFormat::BitmapGray2Packed => (2, false),
+                RasterImageFormat::BitmapGray4 => (4, true),
+                RasterImageFormat::BitmapGray4Packed => (4, false),
+                _ => (8, true),
+            };
+            let coverage = Coverage {
+                width,
+                height,
+                left: 0,
+                top: 0,
+                data: unpack_gray(raster.data, width, height, depth, padded)?,
+            };
+            tint(&coverage, foreground).image
+        }
+    };
+
+    let scale = size_px / raster.pixels_per_em as f32;
+    let scaled_width = ((image.width() as f32 * scale).round() as u32).max(1);
+    let scaled_height = ((image.height() as f32 * scale).round() as u32).max(1);
+    let top = ((raster.y as f32 + image.height() as f32) * scale).round() as i32;
+    let image = if (scaled_width, scaled_height) == image.dimensions() {
+        image
+    } else {
+        imageops::resize(
+            &image,
+            scaled_width,
+            scaled_height,
+            imageops::FilterType::Triangle,
+        )
+    };
+    Some(ColorGlyph {
+        left: (raster.x as f32 * scale).round() as i32,
+        top,
+        image,
+    })
+}
+
+fn unpremultiply(r: u8, g: u8, b: u8, a: u8) -> Rgba<u8> {
+    if a == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    Rgba([channel(r), channel(g), channel(b), a])
+}
+
+/// 1 ピクセル `depth` ビットのグレースケール (1 がインク) を 8 bit のカバレッジにする
+///
+/// `padded` なら各行がバイト境界から始まる。
+fn unpack_gray(data: &[u8], width: u32, height: u32, depth: u32, padded: bool) -> Option<Vec<u8>> {
+    let row_bits = width * depth;
+    let stride = if padded {
+        row_bits.div_ceil(8) * 8
+    } else {
+        row_bits
+    };
+    let max = (1u32 << depth) - 1;
+    let mut coverage = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let bit = y * stride + x * depth;
+            let byte = *data.get((bit / 8) as usize)? as u32;
+            let value = (byte >> (8 - depth - bit % 8)) & max;
+            coverage.push((value * 255 / max) as u8);
+        }
+    }
+    Some(coverage)
+}
+
+/// COLR のレイヤーを順に重ねて描く
+fn paint_colr(
+    face: &Face,
+    glyph_id: GlyphId,
+    size_px: f32,
+    foreground: Rgba<u8>,
+) -> Option<ColorGlyph> {
+    let scale = size_px / face.units_per_em() as f32;
+    // 基底グリフの外形と em の枠を合わせた範囲に描く
+    let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32;
+    let (mut x_min, mut y_min) = (0.0f32, face.descender() as f32);
+    let (mut x_max, mut y_max) = (advance, face.ascender() as f32);
+    if let Some(rect) = face.glyph_bounding_box(glyph_id) {
+        x_min = x_min.min(rect.x_min as f32);
+        y_min = y_min.min(rect.y_min as f32);
+        x_max = x_max.max(rect.x_max as f32);
+        y_max = y_max.max(rect.y_max as f32);
+    }
+    let left = (x_min * scale).floor() as i32;
+    let top = (y_max * scale).ceil() as i32;
+    let bounds = Bounds {
+        left,
+        top,
+        width: ((x_max * scale).ceil() as i32 - left).max(1) as u32,
+        height: (top - (y_min * scale).floor() as i32).max(1) as u32,
+    };
+
+    let mut painter = ColrPainter::new(face, scale, bounds);
+    let [r, g, b, a] = foreground.0;
+    face.paint_color_glyph(glyph_id, 0, RgbaColor::new(r, g, b, a), &mut painter)?;
+    painter.finish()
+}
+
+/// 乗算済みアルファの RGBA (0..1)
+type Color = [f32; 4];
+
+struct ColrPainter<'f, 'a> {
+    face: &'f Face<'a>,
+    scale: f32,
+    bounds: Bounds,
+    /// 次の `paint` か `push_clip` で使う輪郭
+    outline: Option<Path>,
+    transforms: Vec<Transform>,
+    /// 0..1 のマスク。重なったクリップは掛け合わせてから積む
+    clips: Vec<Vec<f32>>,
+    layers: Vec<(Vec<Color>, CompositeMode)>,
+}
+
+impl<'f, 'a> ColrPainter<'f, 'a> {
+    fn new(face: &'f Face<'a>, scale: f32, bounds: Bounds) -> Self {
+        let len = (bounds.width * bounds.height) as usize;
+        Self {
+            face,
+            scale,
+            bounds,
+            outline: None,
+            transforms: vec![Transform::default()],
+            clips: vec![],
+            layers: vec![(vec![[0.0; 4]; len], CompositeMode::SourceOver)],
+        }
+    }
+
+    fn transform(&self) -> Transform {
+        *self.transforms.last().unwrap()
+    }
+
+    /// 輪郭のカバレッジと現在のクリップを掛け合わせたマスク。輪郭がなければ全面
+    fn mask(&self, path: Option<&Path>) -> Vec<f32> {
+        let len = (self.bounds.width * self.bounds.height) as usize;
+        let mut mask = match path {
+            Some(path) => fill_path(path, self.bounds)
+                .data
+                .iter()
+                .map(|&v| v as f32 / 255.0)
+                .collect(),
+            None => vec![1.0; len],
+        };
+        if let Some(clip) = self.clips.last() {
+            for (m, c) in mask.iter_mut().zip(clip) {
+                *m *= c;
+            }
+        }
+        mask
+    }
+
+    fn finish(mut self) -> Option<ColorGlyph> {
+        while self.layers.len() > 1 {
+            self.pop_layer();
+        }
+        let (pixels, _) = self.layers.pop().unwrap();
+        let width = self.bounds.width;
+        let image = RgbaImage::from_fn(width, self.bounds.height, |x, y| {
+            let [r, g, b, a] = pixels[(y * width + x) as usize];
+            let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            unpremultiply(channel(r), channel(g), channel(b), channel(a))
+        });
+
+        // 透明な縁を切り落とす
+        let opaque: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p.0[3] > 0)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let x0 = opaque.iter().map(|p| p.0).min()?;
+        let x1 = opaque.iter().map(|p| p.0).max()?;
+        let y0 = opaque.iter().map(|p| p.1).min()?;
+        let y1 = opaque.iter().map(|p| p.1).max()?;
+        Some(ColorGlyph {
+            left: self.bounds.left + x0 as i32,
+            top: self.bounds.top - y0 as i32,
+            image: imageops::crop_imm(&image, x0, y0, x1 - x0 + 1, y1 - y0 + 1).to_image(),
+        })
+    }
+}
+
+impl<'a> Painter<'a> for ColrPainter<'_, 'a> {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        let mut builder = TransformedOutline {
+            transform: self.transform(),
+            scale: self.scale,
+            path: Path::new(),
+        };
+        self.outline = self
+            .face
+            .outline_glyph(glyph_id, &mut builder)
+            .map(|_| builder.path);
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        // グラデーションには対応せず、色の停止点の平均で塗る
+        let stops: Vec<RgbaColor> = match &paint {
+            Paint::Solid(color) => vec![*color],
+            Paint::LinearGradient(g) => g.stops(0, &[]).map(|s| s.color).collect(),
+            Paint::RadialGradient(g) => g.stops(0, &[]).map(|s| s.color).collect(),
+            Paint::SweepGradient(g) => g.stops(0, &[]).map(|s| s.color).collect(),
+        };
+        if stops.is_empty() {
+            return;
+        }
+        let mut color = [0.0; 4];
+        for stop in &stops {
+            let alpha = stop.alpha as f32 / 255.0;
+            color[0] += stop.red as f32 / 255.0 * alpha;
+            color[1] += stop.green as f32 / 255.0 * alpha;
+            color[2] += stop.blue as f32 / 255.0 * alpha;
+            color[3] += alpha;
+        }
+        let color = color.map(|c| c / stops.len() as f32);
+
+        let outline = self.outline.take();
+        let mask = self.mask(outline.as_ref());
+        let (layer, _) = self.layers.last_mut().unwrap();
+        for (pixel, m) in layer.iter_mut().zip(mask) {
+            let source = color.map(|c| c * m);
+            *pixel = std::array::from_fn(|i| source[i] + pixel[i] * (1.0 - source[3]));
+        }
+    }
+
+    fn push_clip(&mut self) {
+        let outline = self.outline.take();
+        let mask = self.mask(outline.as_ref());
+        self.clips.push(mask);
+    }
+
+    fn push_clip_box(&mut self, clipbox: ClipBox) {
+        let t = self.transform();
+        let mut path = Path::new();
+        let corners = [
+            (clipbox.x_min, clipbox.y_min),
+            (clipbox.x_max, clipbox.y_min),
+            (clipbox.x_max, clipbox.y_max),
+            (clipbox.x_min, clipbox.y_max),
+        ];
+        for (i, (x, y)) in corners.into_iter().enumerate() {
+            let (x, y) = apply(&t, x, y);
+            if i == 0 {
+                path.move_to(x * self.scale, y * self.scale);
+            } else {
+                path.line_to(x * self.scale, y * self.scale);
+            }
+        }
+        path.close();
+        let mask = self.mask(Some(&path));
+        self.clips.push(mask);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn push_layer(&mut self, mode: CompositeMode) {
+        let len = (self.bounds.width * self.bounds.height) as usize;
+        self.layers.push((vec![[0.0; 4]; len], mode));
+    }
+
+    fn pop_layer(&mut self) {
+        let Some((source, mode)) = self.layers.pop() else {
+            return;
+        };
+        let Some((target, _)) = self.layers.last_mut() else {
+            return;
+        };
+        for (d, s) in target.iter_mut().zip(source) {
+            let (sa, da) = (s[3], d[3]);
+            // Porter-Duff の係数。ブレンドモードは source-over で代用する
+            let (fa, fb) = match mode {
+                CompositeMode::Clear => (0.0, 0.0),
+                CompositeMode::Source => (1.0, 0.0),
+                CompositeMode::Destination => (0.0, 1.0),
+                CompositeMode::DestinationOver => (1.0 - da, 1.0),
+                CompositeMode::SourceIn => (da, 0.0),
+                CompositeMode::DestinationIn => (0.0, sa),
+                CompositeMode::SourceOut => (1.0 - da, 0.0),
+                CompositeMode::DestinationOut => (0.0, 1.0 - sa),
+                CompositeMode::SourceAtop => (da, 1.0 - sa),
+                CompositeMode::DestinationAtop => (1.0 - da, sa),
+                CompositeMode::Xor => (1.0 - da, 1.0 - sa),
+                CompositeMode::Plus => (1.0, 1.0),
+                _ => (1.0, 1.0 - sa),
+            };
+            *d = std::array::from_fn(|i| (s[i] * fa + d[i] * fb).min(1.0));
+        }
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        let combined = Transform::combine(self.transform(), transform);
+        self.transforms.push(combined);
+    }
+
+    fn pop_transform(&mut self) {
+        if self.transforms.len() > 1 {
+            self.transforms.pop();
+        }
+    }
+}
+
+fn apply(t: &Transform, x: f32, y: f32) -> (f32, f32) {
+    (t.a * x + t.c * y + t.e, t.b * x + t.d * y + t.f)
+}
+
+/// 変換行列を掛けてからピクセル単位に拡大した輪郭
+struct TransformedOutline {
+    transform: Transform,
+    scale: f32,
+    path: Path,
+}
+
+impl TransformedOutline {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = apply(&self.transform, x, y);
+        (x * self.scale, y * self.scale)
+    }
+}
+
+impl OutlineBuilder for TransformedOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x, y) = self.point(x, y);
+        self.path.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let (x, y) = self.point(x, y);
+        self.path.curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layout::{LayoutOptions, layout_with_fallback},
+        test_font::{FontBuilder, colr, cpal, rect, sbix},
+    };
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn png(width: u32, height: u32, color: Rgba<u8>) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        RgbaImage::from_pixel(width, height, color)
+            .write_to(&mut data, ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn colr_layers() {
+        let mut font = FontBuilder::new(1000);
+        let base = font.glyph(Some('A'), 1000, vec![rect(0, 0, 1000, 800)]);
+        let bottom = font.glyph(None, 1000, vec![rect(0, 0, 1000, 400)]);
+        let upper = font.glyph(None, 1000, vec![rect(0, 400, 1000, 800)]);
+        let bar = font.glyph(None, 1000, vec![rect(400, 0, 600, 800)]);
+        font.table(
+            b"COLR",
+            colr(&[(base, vec![(bottom, 0), (upper, 1), (bar, 0xFFFF)])]),
+        );
+        font.table(b"CPAL", cpal(&[[255, 0, 0, 255], [0, 0, 255, 255]]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let glyph = rasterize_color_glyph(&face, GlyphId(base), 10.0, GREEN).unwrap();
+        assert_eq!((glyph.left, glyph.top), (0, 8));
+        assert_eq!(glyph.image.dimensions(), (10, 8));
+        assert_eq!(*glyph.image.get_pixel(0, 7), RED);
+        assert_eq!(*glyph.image.get_pixel(0, 0), BLUE);
+        // 0xFFFF のレイヤーは前景色で塗る
+        assert_eq!(*glyph.image.get_pixel(5, 0), GREEN);
+        assert_eq!(*glyph.image.get_pixel(5, 7), GREEN);
+    }
+
+    #[test]
+    fn colr_falls_back_to_outline() {
+        let mut font = FontBuilder::new(1000);
+        let base = font.glyph(Some('A'), 1000, vec![rect(0, 0, 1000, 800)]);
+        let layer = font.glyph(None, 1000, vec![rect(0, 0, 1000, 400)]);
+        font.table(b"COLR", colr(&[(base, vec![(layer, 0)])]));
+        // パレットの色が完全に透明
+        font.table(b"CPAL", cpal(&[[255, 0, 0, 0]]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+        assert!(face.is_color_glyph(GlyphId(base)));
+
+        let glyph = rasterize_color_glyph(&face, GlyphId(base), 10.0, GREEN).unwrap();
+        assert_eq!((glyph.left, glyph.top), (0, 8));
+        assert_eq!(glyph.image.dimensions(), (10, 8));
+        assert!(glyph.image.pixels().all(|p| *p == GREEN));
+
+        // 参照するパレットがない
+        let mut font = FontBuilder::new(1000);
+        let base = font.glyph(Some('A'), 1000, vec![rect(0, 0, 1000, 800)]);
+        let layer = font.glyph(None, 1000, vec![rect(0, 0, 1000, 400)]);
+        font.table(b"COLR", colr(&[(base, vec![(layer, 0)])]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+        let glyph = rasterize_color_glyph(&face, GlyphId(base), 10.0, GREEN).unwrap();
+        assert_eq!(glyph.image.dimensions(), (10, 8));
+        assert!(glyph.image.pixels().all(|p| *p == GREEN));
+    }
+
+    #[test]
+    fn sbix_bitmap() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('A'), 1000, vec![]);
+        font.table(b"sbix", sbix(2, 20, &[(id, 2, -2, png(4, 4, RED))]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let glyph = rasterize_color_glyph(&face, GlyphId(id), 20.0, GREEN).unwrap();
+        assert_eq!((glyph.left, glyph.top), (2, 2));
+        assert_eq!(glyph.image.dimensions(), (4, 4));
+        assert!(glyph.image.pixels().all(|p| *p == RED));
+
+        // ストライクより小さく描くときは縮小する
+        let glyph = rasterize_color_glyph(&face, GlyphId(id), 10.0, GREEN).unwrap();
+        assert_eq!((glyph.left, glyph.top), (1, 1));
+        assert_eq!(glyph.image.dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn outline_is_tinted() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('a'), 1000, vec![rect(0, 0, 1000, 500)]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let glyph = rasterize_color_glyph(&face, GlyphId(id), 16.0, BLUE).unwrap();
+        assert_eq!(glyph.image.dimensions(), (16, 8));
+        assert!(glyph.image.pixels().all(|p| *p == BLUE));
+        assert_eq!(rasterize_color_glyph(&face, GlyphId(0), 16.0, BLUE), None);
+    }
+
+    #[test]
+    fn raw_bitmaps() {
+        // 半透明の緑は (0, 128, 0, 128) と乗算済みで格納される
+        assert_eq!(unpremultiply(0, 128, 0, 128), Rgba([0, 255, 0, 128]));
+        assert_eq!(unpremultiply(10, 10, 10, 0), Rgba([0, 0, 0, 0]));
+
+        // 3 ピクセル幅の 1 bit 画像。各行はバイト境界から始まる
+        let data = [0b1010_0000, 0b0100_0000];
+        assert_eq!(
+            unpack_gray(&data, 3, 2, 1, true).unwrap(),
+            [255, 0, 255, 0, 255, 0]
+        );
+        // 詰めて並べた 2 bit 画像
+        let data = [0b1100_0110];
+        assert_eq!(
+            unpack_gray(&data, 2, 2, 2, false).unwrap(),
+            [255, 0, 85, 170]
+        );
+        assert_eq!(unpack_gray(&data, 4, 4, 2, false), None);
+    }
+
+    #[test]
+    fn fallback_faces() {
+        let mut primary = FontBuilder::new(1000);
+        primary.glyph(Some('a'), 500, vec![rect(0, 0, 500, 500)]);
+        let mut emoji = FontBuilder::new(2000);
+        emoji.glyph(Some('a'), 2000, vec![]);
+        let smile = emoji.glyph(Some('😀'), 2000, vec![rect(0, 0, 2000, 1600)]);
+        emoji.table(b"COLR", colr(&[(smile, vec![(smile, 0)])]));
+        emoji.table(b"CPAL", cpal(&[[255, 0, 0, 255]]));
+        let (primary, emoji) = (primary.build(), emoji.build());
+        let faces = [
+            Face::parse(&primary, 0).unwrap(),
+            Face::parse(&emoji, 0).unwrap(),
+        ];
+
+        let options = LayoutOptions {
+            size_px: 10.0,
+            ..LayoutOptions::default()
+        };
+        let layout = layout_with_fallback(&faces, "a😀\u{FE0F}?", &options);
+        let glyphs = &layout.lines[0].glyphs;
+        // 異体字セレクタはどのフェイスにもないので飛ばす
+        let placed: Vec<(usize, u16, f32)> =
+            glyphs.iter().map(|g| (g.face, g.glyph_id.0, g.x)).collect();
+        assert_eq!(placed, [(0, 1, 0.0), (1, smile, 5.0), (0, 0, 15.0)]);
+
+        let image = render_layout_color(&faces, &layout, BLUE);
+        assert_eq!(*image.get_pixel(2, 6), BLUE);
+        assert_eq!(*image.get_pixel(10, 6), RED);
+        assert_eq!(image.get_pixel(10, 9).0[3], 0);
+    }
+}
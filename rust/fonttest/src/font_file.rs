@@ -0,0 +1,241 @@
+//! フォントファイル (TTF / OTF / TTC) をディスクから読み込む
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+use ttf_parser::{Face, FaceParsingError};
+
+use crate::variation::{Variation, VariationError, set_variations};
+
+/// ファイルの形式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontFormat {
+    /// TrueType のアウトラインを持つ単体のフォント
+    TrueType,
+    /// CFF のアウトラインを持つ単体のフォント
+    OpenType,
+    /// 複数のフォントをまとめた TrueType Collection
+    Collection,
+}
+
+/// フォントの読み込みに失敗した理由
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// 形式は分かるが対応していない (WOFF など)
+    Unsupported(&'static str),
+    UnknownFormat([u8; 4]),
+    FaceIndexOutOfRange {
+        index: u32,
+        count: u32,
+    },
+    Malformed(FaceParsingError),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io { path, .. } => {
+                write!(f, "failed to read {}", path.display())
+            }
+            LoadError::Unsupported(format) => write!(f, "{format} fonts are not supported"),
+            LoadError::UnknownFormat(magic) => {
+                write!(f, "unknown font format (magic {magic:02x?})")
+            }
+            LoadError::FaceIndexOutOfRange { index, count } => {
+                write!(
+                    f,
+                    "face index {index} is out of range (font has {count} faces)"
+                )
+            }
+            LoadError::Malformed(e) => write!(f, "malformed font: {e}"),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 読み込んだフォントのデータと、使うフェイスの番号
+///
+/// `Face` はデータを借用するので、必要になるたびに `face()` で作る。
+#[derive(Clone, Debug)]
+pub struct FontFile {
+    data: Vec<u8>,
+    format: FontFormat,
+    index: u32,
+    /// `face()` で作るフェイスに設定する可変フォントの軸の値
+    variations: Vec<Variation>,
+}
+
+impl FontFile {
+    /// 先頭のフェイスを読み込む
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        Self::open_face(path, 0)
+    }
+
+    /// コレクションの `index` 番目のフェイスを読み込む
+    pub fn open_face(path: impl AsRef<Path>, index: u32) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|source| LoadError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_vec(data, index)
+    }
+
+    /// メモリ上のデータから読み込む。形式とフェイスの番号をここで検証する
+    pub fn from_vec(data: Vec<u8>, index: u32) -> Result<Self, LoadError> {
+        let format = detect_format(&data)?;
+        let count = match format {
+            FontFormat::Collection => ttf_parser::fonts_in_collection(&data)
+                .ok_or(LoadError::Malformed(FaceParsingError::MalformedFont))?,
+            _ => 1,
+        };
+        if index >= count {
+            return Err(LoadError::FaceIndexOutOfRange { index, count });
+        }
+        Face::parse(&data, index).map_err(LoadError::Malformed)?;
+        Ok(Self {
+            data,
+            format,
+            index,
+            variations: vec![],
+        })
+    }
+
+    pub fn face(&self) -> Face<'_> {
+        let mut face = Face::parse(&self.data, self.index).expect("face is validated in from_vec");
+        set_variations(&mut face, &self.variations).expect("variations are validated");
+        face
+    }
+
+    /// 以後の `face()` で使うインスタンスを選ぶ。失敗したときは前の設定のまま
+    pub fn set_variations(&mut self, variations: &[Variation]) -> Result<(), VariationError> {
+        let mut face = Face::parse(&self.data, self.index).expect("face is validated in from_vec");
+        set_variations(&mut face, variations)?;
+        self.variations = variations.to_vec();
+        Ok(())
+    }
+
+    pub fn variations(&self) -> &[Variation] {
+        &self.variations
+    }
+
+    pub fn format(&self) -> FontFormat {
+        self.format
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// ファイルに含まれるフェイスの数
+    pub fn face_count(&self) -> u32 {
+        ttf_parser::fonts_in_collection(&self.data).unwrap_or(1)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+fn detect_format(data: &[u8]) -> Result<FontFormat, LoadError> {
+    let Some(magic) = data.get(..4) else {
+        return Err(LoadError::Malformed(FaceParsingError::MalformedFont));
+    };
+    match magic {
+        [0x00, 0x01, 0x00, 0x00] | b"true" => Ok(FontFormat::TrueType),
+        b"OTTO" => Ok(FontFormat::OpenType),
+        b"ttcf" => Ok(FontFormat::Collection),
+        b"wOFF" => Err(LoadError::Unsupported("WOFF")),
+        b"wOF2" => Err(LoadError::Unsupported("WOFF2")),
+        _ => Err(LoadError::UnknownFormat(magic.try_into().unwrap())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{FontBuilder, collection, rect};
+
+    fn font(units_per_em: u16) -> FontBuilder {
+        let mut font = FontBuilder::new(units_per_em);
+        font.glyph(Some('a'), 500, vec![rect(0, 0, 100, 100)]);
+        font
+    }
+
+    #[test]
+    fn open_from_disk() {
+        let path = std::env::temp_dir().join(format!("fonttest-{}.ttf", std::process::id()));
+        std::fs::write(&path, font(1000).build()).unwrap();
+        let file = FontFile::open(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let file = file.unwrap();
+        assert_eq!(file.format(), FontFormat::TrueType);
+        assert_eq!(file.face_count(), 1);
+        assert_eq!(file.face().units_per_em(), 1000);
+
+        let error = FontFile::open(&path).unwrap_err();
+        assert!(matches!(error, LoadError::Io { .. }));
+        assert!(error.to_string().contains(path.to_str().unwrap()));
+    }
+
+    #[test]
+    fn collection_faces() {
+        let data = collection(&[&font(1000), &font(2048)]);
+        let file = FontFile::from_vec(data.clone(), 1).unwrap();
+        assert_eq!(file.format(), FontFormat::Collection);
+        assert_eq!(file.face_count(), 2);
+        assert_eq!(file.index(), 1);
+        assert_eq!(file.face().units_per_em(), 2048);
+        let face = file.face();
+        assert_eq!(face.glyph_index('a').map(|g| g.0), Some(1));
+
+        let error = FontFile::from_vec(data, 2).unwrap_err();
+        assert!(matches!(
+            error,
+            LoadError::FaceIndexOutOfRange { index: 2, count: 2 }
+        ));
+        assert!(matches!(
+            FontFile::from_vec(font(1000).build(), 1),
+            Err(LoadError::FaceIndexOutOfRange { index: 1, count: 1 })
+        ));
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(matches!(
+            FontFile::from_vec(b"wOFF\0\0\0\0".to_vec(), 0),
+            Err(LoadError::Unsupported("WOFF"))
+        ));
+        let error = FontFile::from_vec(b"GIF89a".to_vec(), 0).unwrap_err();
+        assert!(matches!(error, LoadError::UnknownFormat(magic) if &magic == b"GIF8"));
+        assert!(matches!(
+            FontFile::from_vec(vec![0, 1], 0),
+            Err(LoadError::Malformed(_))
+        ));
+
+        // テーブルディレクトリの途中で切れたファイル
+        let mut data = font(1000).build();
+        data.truncate(40);
+        let error = FontFile::from_vec(data, 0).unwrap_err();
+        assert!(matches!(error, LoadError::Malformed(_)));
+        assert!(error.source().is_some());
+    }
+}
@@ -0,0 +1,110 @@
+//! sRGB の色を線形の輝度に戻してからカバレッジで合成する
+//!
+//! sRGB の値のまま線形補間すると、細い線や小さい文字が細く暗く見える。
+
+use std::sync::LazyLock;
+
+use image::{Rgb, RgbImage};
+
+use crate::Coverage;
+
+static SRGB_TO_LINEAR: LazyLock<[f32; 256]> = LazyLock::new(|| {
+    std::array::from_fn(|i| {
+        let c = i as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+});
+
+/// sRGB の 8 bit 値を 0..1 の線形の輝度にする
+pub fn srgb_to_linear(value: u8) -> f32 {
+    SRGB_TO_LINEAR[value as usize]
+}
+
+/// 0..1 の線形の輝度を sRGB の 8 bit 値にする
+pub fn linear_to_srgb(value: f32) -> u8 {
+    let c = value.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0 + 0.5) as u8
+}
+
+/// `background` の上に `foreground` を `coverage` の割合だけ線形空間で重ねる
+pub fn blend(background: Rgb<u8>, foreground: Rgb<u8>, coverage: u8) -> Rgb<u8> {
+    match coverage {
+        0 => background,
+        255 => foreground,
+        _ => {
+            let alpha = coverage as f32 / 255.0;
+            Rgb(std::array::from_fn(|i| {
+                let b = srgb_to_linear(background.0[i]);
+                let f = srgb_to_linear(foreground.0[i]);
+                linear_to_srgb(b + (f - b) * alpha)
+            }))
+        }
+    }
+}
+
+/// 原点が `(x, y)` (ベースライン上、下向き) に来るようにカバレッジを `color` で重ねる
+pub fn blend_coverage(image: &mut RgbImage, coverage: &Coverage, x: i32, y: i32, color: Rgb<u8>) {
+    let left = x + coverage.left;
+    let top = y - coverage.top;
+    for cy in 0..coverage.height {
+        for cx in 0..coverage.width {
+            let (px, py) = (left + cx as i32, top + cy as i32);
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                continue;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            *pixel = blend(*pixel, color, coverage.get(cx, cy));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for value in 0..=255 {
+            assert_eq!(linear_to_srgb(srgb_to_linear(value)), value);
+        }
+        assert_eq!(srgb_to_linear(0), 0.0);
+        assert_eq!(srgb_to_linear(255), 1.0);
+    }
+
+    #[test]
+    fn half_coverage_is_brighter_than_average() {
+        let black = Rgb([0, 0, 0]);
+        let white = Rgb([255, 255, 255]);
+        // 線形で 0.5 の明るさは sRGB では 188
+        assert_eq!(blend(black, white, 128), Rgb([188, 188, 188]));
+        assert_eq!(blend(white, black, 128), Rgb([187, 187, 187]));
+        assert_eq!(blend(black, white, 0), black);
+        assert_eq!(blend(black, white, 255), white);
+    }
+
+    #[test]
+    fn blend_at_origin() {
+        let coverage = Coverage {
+            width: 2,
+            height: 1,
+            left: -1,
+            top: 1,
+            data: vec![255, 128],
+        };
+        let mut image = RgbImage::from_pixel(3, 2, Rgb([255, 255, 255]));
+        blend_coverage(&mut image, &coverage, 1, 2, Rgb([0, 0, 0]));
+        assert_eq!(image.get_pixel(0, 1), &Rgb([0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 1), &Rgb([187, 187, 187]));
+        assert_eq!(image.get_pixel(2, 1), &Rgb([255, 255, 255]));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([255, 255, 255]));
+    }
+}
@@ -0,0 +1,376 @@
+//! 描画結果を保存済みの正解画像 (golden) と比べる
+//!
+//! 環境変数 `UPDATE_GOLDEN` を設定して実行すると、比べずに正解画像を書き換える。
+//! 許容範囲を超えたときは、正解画像の隣に実際の画像 (`*.actual.png`) と差分画像
+//! (`*.diff.png`) を書き出すので、ラスタライザを変えたときに見比べられる。
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use image::{GrayImage, ImageError, Rgb, RgbImage};
+
+/// 設定されていれば正解画像を書き換える環境変数
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// 許容する差
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// 1 画素あたりの差の最大値
+    pub max_diff: u8,
+    /// PSNR (dB) の下限
+    pub min_psnr: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            max_diff: 8,
+            min_psnr: 40.0,
+        }
+    }
+}
+
+/// 2 枚の画像の差
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    pub max_diff: u8,
+    /// 一致すれば無限大
+    pub psnr: f64,
+    /// 値の異なる画素の数
+    pub changed: usize,
+}
+
+impl Comparison {
+    pub fn is_within(&self, tolerance: &Tolerance) -> bool {
+        self.max_diff <= tolerance.max_diff && self.psnr >= tolerance.min_psnr
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "max diff {}, PSNR {:.2} dB, {} pixels changed",
+            self.max_diff, self.psnr, self.changed
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    /// 正解画像がまだない
+    Missing(PathBuf),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// 許容範囲を超えた。差分画像の場所も持つ
+    Mismatch {
+        comparison: Comparison,
+        diff: PathBuf,
+    },
+    Image(ImageError),
+}
+
+impl Display for GoldenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoldenError::Missing(path) => write!(
+                f,
+                "golden image {} does not exist; run with {UPDATE_ENV}=1 to create it",
+                path.display()
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} image, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            GoldenError::Mismatch { comparison, diff } => {
+                write!(f, "{comparison} (see {})", diff.display())
+            }
+            GoldenError::Image(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for GoldenError {}
+
+impl From<ImageError> for GoldenError {
+    fn from(e: ImageError) -> Self {
+        GoldenError::Image(e)
+    }
+}
+
+/// 同じ大きさの 2 枚の画像を比べる
+pub fn compare(expected: &GrayImage, actual: &GrayImage) -> Result<Comparison, GoldenError> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(GoldenError::SizeMismatch {
+            expected: expected.dimensions(),
+            actual: actual.dimensions(),
+        });
+    }
+    let mut max_diff = 0;
+    let mut changed = 0;
+    let mut squared = 0.0;
+    for (a, b) in expected.as_raw().iter().zip(actual.as_raw()) {
+        let diff = a.abs_diff(*b);
+        max_diff = max_diff.max(diff);
+        changed += (diff != 0) as usize;
+        squared += (diff as f64).powi(2);
+    }
+    let mse = squared / expected.as_raw().len().max(1) as f64;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+    Ok(Comparison {
+        max_diff,
+        psnr,
+        changed,
+    })
+}
+
+/// 正解画像を薄く敷き、実際の方が濃い画素を赤、薄い画素を青で示す
+pub fn diff_image(expected: &GrayImage, actual: &GrayImage) -> RgbImage {
+    RgbImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let a = expected.get_pixel(x, y).0[0];
+        let b = actual.get_pixel(x, y).0[0];
+        let base = 255 - a / 4;
+        let diff = a.abs_diff(b);
+        match a.cmp(&b) {
+            std::cmp::Ordering::Equal => Rgb([base, base, base]),
+            std::cmp::Ordering::Less => Rgb([255, 255 - diff, 255 - diff]),
+            std::cmp::Ordering::Greater => Rgb([255 - diff, 255 - diff, 255]),
+        }
+    })
+}
+
+/// ディレクトリにある名前付きの正解画像
+#[derive(Clone, Debug)]
+pub struct Golden {
+    pub dir: PathBuf,
+    pub tolerance: Tolerance,
+    /// 比べずに正解画像を書き換える
+    pub update: bool,
+}
+
+impl Golden {
+    /// `UPDATE_GOLDEN` が設定されていれば書き換えるモードになる
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tolerance: Tolerance::default(),
+            update: std::env::var_os(UPDATE_ENV).is_some(),
+        }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.png"))
+    }
+
+    /// `name.png` と比べる。通れば前回の失敗で書き出した画像を消す
+    pub fn check(&self, name: &str, actual: &GrayImage) -> Result<Comparison, GoldenError> {
+        let path = self.path(name);
+        let actual_path = self.dir.join(format!("{name}.actual.png"));
+        let diff_path = self.dir.join(format!("{name}.diff.png"));
+        if self.update {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::IoError)?;
+            actual.save(&path)?;
+        }
+        if !path.exists() {
+            return Err(GoldenError::Missing(path));
+        }
+        let expected = image::open(&path)?.into_luma8();
+        let comparison = compare(&expected, actual)?;
+        if comparison.is_within(&self.tolerance) {
+            remove_if_exists(&actual_path);
+            remove_if_exists(&diff_path);
+            return Ok(comparison);
+        }
+        actual.save(&actual_path)?;
+        diff_image(&expected, actual).save(&diff_path)?;
+        Err(GoldenError::Mismatch {
+            comparison,
+            diff: diff_path,
+        })
+    }
+}
+
+fn remove_if_exists(path: &Path) {
+    if path.exists() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+    use ttf_parser::{Face, GlyphId};
+
+    use super::*;
+    use crate::{
+        rasterize_glyph, rasterize_glyph_at,
+        test_font::{FontBuilder, circle, rect},
+    };
+
+    /// 正解画像を置くディレクトリ
+    fn golden_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+    }
+
+    /// テストごとに別の一時ディレクトリ
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("fonttest-golden-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// 直線・斜めの辺・曲線・穴・細い線をひと通り含むグリフ
+    fn glyph_set() -> (Vec<u8>, Vec<(&'static str, u16)>) {
+        let mut font = FontBuilder::new(1000);
+        let mut hole = rect(300, 200, 700, 500);
+        hole.reverse();
+        let glyphs = vec![
+            (
+                "box",
+                font.glyph(None, 1000, vec![rect(100, 0, 900, 700), hole]),
+            ),
+            (
+                "ring",
+                font.glyph(
+                    None,
+                    1000,
+                    vec![circle(500, 350, 350, false), circle(500, 350, 220, true)],
+                ),
+            ),
+            (
+                "wedge",
+                font.glyph(
+                    None,
+                    1000,
+                    vec![vec![(50, 700, true), (500, 0, true), (950, 700, true)]],
+                ),
+            ),
+            (
+                "hairline",
+                font.glyph(None, 1000, vec![rect(470, 0, 510, 700)]),
+            ),
+            (
+                "swash",
+                font.glyph(
+                    None,
+                    1000,
+                    vec![vec![
+                        (100, 0, true),
+                        (900, 0, false),
+                        (900, 700, true),
+                        (750, 700, true),
+                        (750, 150, false),
+                        (100, 150, true),
+                    ]],
+                ),
+            ),
+        ];
+        (font.build(), glyphs)
+    }
+
+    #[test]
+    fn glyphs_match_golden() {
+        let (data, glyphs) = glyph_set();
+        let face = Face::parse(&data, 0).unwrap();
+        let golden = Golden::new(golden_dir());
+        let mut failures = vec![];
+        for size in [9.0, 16.0, 31.0] {
+            for &(name, id) in &glyphs {
+                let name = format!("{name}-{size}");
+                let coverage = rasterize_glyph(&face, GlyphId(id), size).unwrap();
+                if let Err(e) = golden.check(&name, &coverage.to_image()) {
+                    failures.push(format!("{name}: {e}"));
+                }
+            }
+        }
+        let shifted = rasterize_glyph_at(&face, GlyphId(glyphs[2].1), 16.0, 0.25).unwrap();
+        if let Err(e) = golden.check("wedge-16-shifted", &shifted.to_image()) {
+            failures.push(format!("wedge-16-shifted: {e}"));
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn metrics() {
+        let a = GrayImage::from_raw(2, 2, vec![0, 64, 128, 255]).unwrap();
+        let same = compare(&a, &a).unwrap();
+        assert_eq!(same.max_diff, 0);
+        assert_eq!(same.changed, 0);
+        assert!(same.psnr.is_infinite());
+
+        let b = GrayImage::from_raw(2, 2, vec![0, 64, 138, 255]).unwrap();
+        let c = compare(&a, &b).unwrap();
+        assert_eq!((c.max_diff, c.changed), (10, 1));
+        // MSE = 100 / 4 = 25
+        assert!((c.psnr - 10.0 * (65025.0f64 / 25.0).log10()).abs() < 1e-9);
+        assert!(c.is_within(&Tolerance {
+            max_diff: 10,
+            min_psnr: 30.0
+        }));
+        assert!(!c.is_within(&Tolerance::default()));
+
+        let small = GrayImage::new(1, 2);
+        assert!(matches!(
+            compare(&a, &small),
+            Err(GoldenError::SizeMismatch {
+                expected: (2, 2),
+                actual: (1, 2)
+            })
+        ));
+    }
+
+    #[test]
+    fn diff_colors() {
+        let a = GrayImage::from_raw(3, 1, vec![0, 100, 100]).unwrap();
+        let b = GrayImage::from_raw(3, 1, vec![0, 150, 40]).unwrap();
+        let diff = diff_image(&a, &b);
+        assert_eq!(diff.get_pixel(0, 0), &Rgb([255, 255, 255]));
+        assert_eq!(diff.get_pixel(1, 0), &Rgb([255, 205, 205]));
+        assert_eq!(diff.get_pixel(2, 0), &Rgb([195, 195, 255]));
+    }
+
+    #[test]
+    fn update_then_fail_writes_diff() {
+        let dir = temp_dir("update");
+        let image = GrayImage::from_pixel(4, 4, Luma([100]));
+        let mut golden = Golden {
+            update: false,
+            ..Golden::new(&dir)
+        };
+        assert!(matches!(
+            golden.check("square", &image),
+            Err(GoldenError::Missing(_))
+        ));
+
+        golden.update = true;
+        golden.check("square", &image).unwrap();
+        golden.update = false;
+        assert!(golden.path("square").exists());
+
+        let mut changed = image.clone();
+        changed.put_pixel(1, 1, Luma([200]));
+        let Err(GoldenError::Mismatch { comparison, diff }) = golden.check("square", &changed)
+        else {
+            panic!("expected a mismatch");
+        };
+        assert_eq!(comparison.max_diff, 100);
+        assert!(diff.exists());
+        assert!(dir.join("square.actual.png").exists());
+
+        // 直れば失敗の画像は消える
+        assert!(golden.check("square", &image).is_ok());
+        assert!(!diff.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
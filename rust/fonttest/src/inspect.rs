@@ -0,0 +1,355 @@
+//! フォントの名前、メトリクス、テーブルの一覧と、文字集合をどこまで含むかを調べる
+//!
+//! 範囲は `U+0020-U+007E` のように書くか、テキストファイルに含まれる文字で指定する。
+
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use serde::Serialize;
+use ttf_parser::{Face, Language, name_id};
+
+/// name テーブルの 1 項目
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NameEntry {
+    pub id: u16,
+    pub label: &'static str,
+    pub value: String,
+}
+
+/// テーブルのタグと大きさ (バイト)
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TableInfo {
+    pub tag: String,
+    pub length: u32,
+}
+
+/// フォントの概要。メトリクスはフォント単位
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FontInfo {
+    pub names: Vec<NameEntry>,
+    pub units_per_em: u16,
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+    pub x_height: Option<i16>,
+    pub capital_height: Option<i16>,
+    pub glyph_count: u16,
+    pub variable: bool,
+    pub tables: Vec<TableInfo>,
+}
+
+/// `face` の概要。名前は ID ごとに 1 つで、米国英語のものを優先する
+pub fn font_info(face: &Face) -> FontInfo {
+    let mut names: Vec<_> = face
+        .names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = name.to_string()?;
+            let english = name.language() == Language::English_UnitedStates;
+            Some((name.name_id, !english, value))
+        })
+        .collect();
+    names.sort_by_key(|(id, not_english, _)| (*id, *not_english));
+    names.dedup_by_key(|(id, _, _)| *id);
+
+    FontInfo {
+        names: names
+            .into_iter()
+            .map(|(id, _, value)| NameEntry {
+                id,
+                label: name_label(id),
+                value,
+            })
+            .collect(),
+        units_per_em: face.units_per_em(),
+        ascender: face.ascender(),
+        descender: face.descender(),
+        line_gap: face.line_gap(),
+        x_height: face.x_height(),
+        capital_height: face.capital_height(),
+        glyph_count: face.number_of_glyphs(),
+        variable: face.is_variable(),
+        tables: face
+            .raw_face()
+            .table_records
+            .into_iter()
+            .map(|record| TableInfo {
+                tag: record.tag.to_string(),
+                length: record.length,
+            })
+            .collect(),
+    }
+}
+
+/// name ID の呼び名。定義のない ID は空文字列
+pub fn name_label(id: u16) -> &'static str {
+    match id {
+        name_id::COPYRIGHT_NOTICE => "Copyright",
+        name_id::FAMILY => "Family",
+        name_id::SUBFAMILY => "Subfamily",
+        name_id::UNIQUE_ID => "Unique ID",
+        name_id::FULL_NAME => "Full name",
+        name_id::VERSION => "Version",
+        name_id::POST_SCRIPT_NAME => "PostScript name",
+        name_id::TRADEMARK => "Trademark",
+        name_id::MANUFACTURER => "Manufacturer",
+        name_id::DESIGNER => "Designer",
+        name_id::DESCRIPTION => "Description",
+        name_id::VENDOR_URL => "Vendor URL",
+        name_id::DESIGNER_URL => "Designer URL",
+        name_id::LICENSE => "License",
+        name_id::LICENSE_URL => "License URL",
+        name_id::TYPOGRAPHIC_FAMILY => "Typographic family",
+        name_id::TYPOGRAPHIC_SUBFAMILY => "Typographic subfamily",
+        name_id::COMPATIBLE_FULL => "Compatible full name",
+        name_id::SAMPLE_TEXT => "Sample text",
+        name_id::POST_SCRIPT_CID => "PostScript CID name",
+        name_id::WWS_FAMILY => "WWS family",
+        name_id::WWS_SUBFAMILY => "WWS subfamily",
+        name_id::LIGHT_BACKGROUND_PALETTE => "Light background palette",
+        name_id::DARK_BACKGROUND_PALETTE => "Dark background palette",
+        name_id::VARIATIONS_POST_SCRIPT_NAME_PREFIX => "Variations PostScript name prefix",
+        _ => "",
+    }
+}
+
+/// 両端を含むコードポイントの範囲 (`U+0020-U+007E`、`0020..007E`、`U+00E9` など)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CharRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl CharRange {
+    /// 範囲内の文字。サロゲートは飛ばす
+    pub fn chars(&self) -> impl Iterator<Item = char> {
+        (self.start..=self.end).filter_map(char::from_u32)
+    }
+}
+
+impl Display for CharRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "U+{:04X}", self.start)
+        } else {
+            write!(f, "U+{:04X}-U+{:04X}", self.start, self.end)
+        }
+    }
+}
+
+impl FromStr for CharRange {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRangeError(s.to_string());
+        let code_point = |s: &str| {
+            let s = s.trim();
+            let hex = s
+                .strip_prefix("U+")
+                .or_else(|| s.strip_prefix("u+"))
+                .unwrap_or(s);
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|c| !hex.is_empty() && hex.len() <= 6 && *c <= char::MAX as u32)
+                .ok_or_else(error)
+        };
+        let (start, end) = match s.split_once("..").or_else(|| s.split_once('-')) {
+            Some((start, end)) => (code_point(start)?, code_point(end)?),
+            None => {
+                let c = code_point(s)?;
+                (c, c)
+            }
+        };
+        if start > end {
+            return Err(error());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// 範囲の書き方が正しくない
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseRangeError(pub String);
+
+impl Display for ParseRangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected a range like U+0020-U+007E, got {:?}", self.0)
+    }
+}
+
+impl Error for ParseRangeError {}
+
+/// 文字集合 1 つについての結果
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RangeCoverage {
+    pub label: String,
+    pub total: usize,
+    pub covered: usize,
+    /// グリフのない文字 (コードポイント順)
+    pub missing: Vec<char>,
+}
+
+impl RangeCoverage {
+    /// 含まれる文字の割合 (%)。文字がなければ 100
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// `chars` のうち `face` の cmap で .notdef 以外のグリフに対応するものを数える
+pub fn coverage(
+    face: &Face,
+    label: impl Into<String>,
+    chars: impl IntoIterator<Item = char>,
+) -> RangeCoverage {
+    let mut total = 0;
+    let mut missing = vec![];
+    for c in chars {
+        total += 1;
+        if face.glyph_index(c).is_none_or(|id| id.0 == 0) {
+            missing.push(c);
+        }
+    }
+    RangeCoverage {
+        label: label.into(),
+        total,
+        covered: total - missing.len(),
+        missing,
+    }
+}
+
+/// テキストに現れる文字を重複なくコードポイント順に並べる。改行などの制御文字は除く
+pub fn text_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{FontBuilder, name, rect};
+
+    fn sample() -> Vec<u8> {
+        let mut font = FontBuilder::new(1000);
+        for c in ['A', 'B', 'C', 'a', 'é'] {
+            font.glyph(Some(c), 500, vec![rect(50, 0, 450, 700)]);
+        }
+        font.table(
+            b"name",
+            name(&[
+                (name_id::FAMILY, "Sample"),
+                (name_id::FULL_NAME, "Sample Regular"),
+            ]),
+        );
+        font.build()
+    }
+
+    #[test]
+    fn info() {
+        let data = sample();
+        let face = Face::parse(&data, 0).unwrap();
+        let info = font_info(&face);
+        assert_eq!(info.units_per_em, 1000);
+        assert_eq!(
+            (info.ascender, info.descender, info.line_gap),
+            (800, -200, 0)
+        );
+        assert_eq!(info.glyph_count, face.number_of_glyphs());
+        assert!(!info.variable);
+        assert_eq!(
+            info.names,
+            vec![
+                NameEntry {
+                    id: name_id::FAMILY,
+                    label: "Family",
+                    value: "Sample".into(),
+                },
+                NameEntry {
+                    id: name_id::FULL_NAME,
+                    label: "Full name",
+                    value: "Sample Regular".into(),
+                },
+            ]
+        );
+        let tags: Vec<&str> = info.tables.iter().map(|t| t.tag.as_str()).collect();
+        for tag in [
+            "cmap", "glyf", "head", "hhea", "hmtx", "loca", "maxp", "name",
+        ] {
+            assert!(tags.contains(&tag), "{tag} in {tags:?}");
+        }
+        assert_eq!(
+            info.tables.iter().find(|t| t.tag == "head").unwrap().length,
+            54
+        );
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["units_per_em"], 1000);
+        assert_eq!(json["x_height"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let range = |start, end| Ok(CharRange { start, end });
+        assert_eq!("U+0020-U+007E".parse(), range(0x20, 0x7e));
+        assert_eq!("0020-007e".parse(), range(0x20, 0x7e));
+        assert_eq!("u+3040..u+309F".parse(), range(0x3040, 0x309f));
+        assert_eq!("U+00E9".parse(), range(0xe9, 0xe9));
+        assert_eq!("U+1F600".parse(), range(0x1f600, 0x1f600));
+        for bad in ["", "U+", "007E-0020", "xyz", "U+110000", "0020-"] {
+            assert_eq!(
+                bad.parse::<CharRange>(),
+                Err(ParseRangeError(bad.into())),
+                "{bad}"
+            );
+        }
+        assert_eq!(
+            ParseRangeError("x".into()).to_string(),
+            "expected a range like U+0020-U+007E, got \"x\""
+        );
+        assert_eq!(
+            CharRange {
+                start: 0x20,
+                end: 0x7e
+            }
+            .to_string(),
+            "U+0020-U+007E"
+        );
+        // サロゲートは数えない
+        let surrogates = CharRange {
+            start: 0xd7ff,
+            end: 0xe000,
+        };
+        assert_eq!(surrogates.chars().count(), 2);
+    }
+
+    #[test]
+    fn coverage_counts() {
+        let data = sample();
+        let face = Face::parse(&data, 0).unwrap();
+        let range: CharRange = "U+0041-U+0045".parse().unwrap();
+        let report = coverage(&face, range.to_string(), range.chars());
+        assert_eq!(report.label, "U+0041-U+0045");
+        assert_eq!((report.total, report.covered), (5, 3));
+        assert_eq!(report.missing, ['D', 'E']);
+        assert_eq!(report.percent(), 60.0);
+
+        let chars = text_chars("café\nCafé Bé\t");
+        assert_eq!(chars, [' ', 'B', 'C', 'a', 'c', 'f', 'é']);
+        let report = coverage(&face, "text", chars);
+        assert_eq!(report.missing, [' ', 'c', 'f']);
+
+        let empty = coverage(&face, "empty", []);
+        assert_eq!(empty.percent(), 100.0);
+    }
+}
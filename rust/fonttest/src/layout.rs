@@ -0,0 +1,491 @@
+//! 文字列をグリフに変換し、カーニングと改行を適用して並べる
+
+use image::GrayImage;
+use ttf_parser::{
+    Face, GlyphId, Tag,
+    gpos::{PairAdjustment, PositioningSubtable},
+    opentype_layout::LayoutTable,
+};
+
+use crate::rasterize_glyph;
+
+/// 行頭に置かない文字 (閉じ括弧や句読点など)
+const NO_LINE_START: &str = "、。，．,.:;!?)]}）］｝〕〉》」』】〙〗〟ゝゞ々ーぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ・：；！？";
+/// 行末に置かない文字 (開き括弧)
+const NO_LINE_END: &str = "([{（［｛〔〈《「『【〘〖〝";
+
+#[derive(Clone, Debug)]
+pub struct LayoutOptions {
+    /// em の大きさ (ピクセル)
+    pub size_px: f32,
+    /// 行の最大幅。`None` なら改行文字でだけ改行する
+    pub max_width: Option<f32>,
+    pub kerning: bool,
+    /// 行送り。`None` ならフォントの ascender - descender + line_gap
+    pub line_height: Option<f32>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            size_px: 32.0,
+            max_width: None,
+            kerning: true,
+            line_height: None,
+        }
+    }
+}
+
+/// 配置済みのグリフ
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    /// グリフを取り出したフェイスの番号 (フォールバックの順)
+    pub face: usize,
+    pub glyph_id: GlyphId,
+    pub character: char,
+    /// 元の文字列でのバイト位置
+    pub cluster: usize,
+    /// 原点の x 座標 (行頭が 0)
+    pub x: f32,
+    /// カーニングを含まない送り幅
+    pub advance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// 行末の空白を除いた幅
+    pub width: f32,
+    /// レイアウトの上端からベースラインまでの距離 (下向き)
+    pub baseline: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub size_px: f32,
+    pub lines: Vec<Line>,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// 全角で組む文字かどうか
+pub fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{303F}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{3100}'..='\u{31FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF01}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{20000}'..='\u{3FFFD}')
+}
+
+/// 2 つのグリフの間のカーニング (フォント単位)
+///
+/// GPOS に `kern` フィーチャがあればそれを使い、なければ `kern` テーブルを見る。
+pub fn kerning(face: &Face, left: GlyphId, right: GlyphId) -> i16 {
+    if let Some(value) = face
+        .tables()
+        .gpos
+        .and_then(|gpos| gpos_kerning(&gpos, left, right))
+    {
+        return value;
+    }
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|s| s.horizontal && !s.variable && !s.has_cross_stream)
+                .find_map(|s| s.glyphs_kerning(left, right))
+        })
+        .unwrap_or(0)
+}
+
+fn gpos_kerning(table: &LayoutTable, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let feature = table.features.find(Tag::from_bytes(b"kern"))?;
+    let mut value = 0i16;
+    for index in feature.lookup_indices {
+        let Some(lookup) = table.lookups.get(index) else {
+            continue;
+        };
+        // 1 つの lookup では最初に当てはまったサブテーブルだけを使う
+        let adjustment = lookup
+            .subtables
+            .into_iter::<PositioningSubtable>()
+            .find_map(|subtable| match subtable {
+                PositioningSubtable::Pair(pair) => pair_adjustment(&pair, left, right),
+                _ => None,
+            });
+        value = value.saturating_add(adjustment.unwrap_or(0));
+    }
+    Some(value)
+}
+
+fn pair_adjustment(pair: &PairAdjustment, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let coverage = pair.coverage().get(left)?;
+    let (first, _) = match pair {
+        PairAdjustment::Format1 { sets, .. } => sets.get(coverage)?.get(right)?,
+        PairAdjustment::Format2 {
+            classes, matrix, ..
+        } => matrix.get((classes.0.get(left), classes.1.get(right)))?,
+    };
+    Some(first.x_advance)
+}
+
+/// 改行位置を決める前のグリフ
+struct Item {
+    face: usize,
+    glyph_id: GlyphId,
+    character: char,
+    cluster: usize,
+    advance: f32,
+    /// 直前のグリフとのカーニング
+    kerning: f32,
+}
+
+/// `text` をグリフに変換して行に分ける
+pub fn layout(face: &Face, text: &str, options: &LayoutOptions) -> Layout {
+    layout_with_fallback(std::slice::from_ref(face), text, options)
+}
+
+/// 文字ごとに `faces` を先頭から探し、最初に見つかったフェイスのグリフを使う
+///
+/// 行の高さは先頭のフェイスで決める。どのフェイスにもない文字は先頭のフェイスの .notdef になる。
+/// `faces` が空なら、行のない大きさ 0 のレイアウトを返す。
+pub fn layout_with_fallback(faces: &[Face], text: &str, options: &LayoutOptions) -> Layout {
+    let Some(primary) = faces.first() else {
+        return Layout {
+            size_px: options.size_px,
+            lines: vec![],
+            width: 0.0,
+            height: 0.0,
+        };
+    };
+    let scale = options.size_px / primary.units_per_em() as f32;
+    let ascender = primary.ascender() as f32 * scale;
+    let descender = primary.descender() as f32 * scale;
+    let line_height = options
+        .line_height
+        .unwrap_or(ascender - descender + primary.line_gap() as f32 * scale);
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for paragraph in text.split('\n') {
+        let items = shape(faces, paragraph, offset, options);
+        offset += paragraph.len() + 1;
+
+        let mut start = 0;
+        loop {
+            let end = match options.max_width {
+                Some(max_width) => line_end(&items, start, max_width),
+                None => items.len(),
+            };
+            lines.push(place(&items[start..end]));
+            if end == items.len() {
+                break;
+            }
+            start = end;
+        }
+    }
+
+    for (i, line) in lines.iter_mut().enumerate() {
+        line.baseline = ascender + i as f32 * line_height;
+    }
+    Layout {
+        size_px: options.size_px,
+        width: lines.iter().map(|l| l.width).fold(0.0, f32::max),
+        height: ascender - descender + (lines.len() - 1) as f32 * line_height,
+        lines,
+    }
+}
+
+/// 異体字セレクタと ZWJ。どのフェイスにもなければ描かずに飛ばす
+fn is_ignorable(c: char) -> bool {
+    matches!(c, '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn shape(faces: &[Face], text: &str, offset: usize, options: &LayoutOptions) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    for (cluster, character) in text.char_indices() {
+        let found = faces
+            .iter()
+            .enumerate()
+            .find_map(|(i, face)| Some((i, face.glyph_index(character)?)));
+        if found.is_none() && is_ignorable(character) {
+            continue;
+        }
+        let (index, glyph_id) = found.unwrap_or((0, GlyphId(0)));
+        let face = &faces[index];
+        let scale = options.size_px / face.units_per_em() as f32;
+        let advance = match found {
+            Some(_) => face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale,
+            // フォントにない全角文字も 1 em 分の幅を取る
+            None if is_wide(character) => options.size_px,
+            None => face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale,
+        };
+        // カーニングは同じフェイスのグリフの間にだけかける
+        let kerning = match items.last() {
+            Some(previous) if options.kerning && previous.face == index => {
+                kerning(face, previous.glyph_id, glyph_id) as f32 * scale
+            }
+            _ => 0.0,
+        };
+        items.push(Item {
+            face: index,
+            glyph_id,
+            character,
+            cluster: offset + cluster,
+            advance,
+            kerning,
+        });
+    }
+    items
+}
+
+/// `previous` と `next` の間で改行できるか
+fn can_break(previous: char, next: char) -> bool {
+    if next.is_whitespace() {
+        // 空白は前の行の末尾にぶら下げる
+        return false;
+    }
+    if previous.is_whitespace() {
+        return true;
+    }
+    if NO_LINE_START.contains(next) || NO_LINE_END.contains(previous) {
+        return false;
+    }
+    is_wide(previous) || is_wide(next)
+}
+
+/// `start` から始まる行が `max_width` に収まる範囲の終わり
+fn line_end(items: &[Item], start: usize, max_width: f32) -> usize {
+    let mut x = 0.0;
+    let mut last_break = None;
+    for i in start..items.len() {
+        let item = &items[i];
+        if i > start {
+            x += item.kerning;
+            if can_break(items[i - 1].character, item.character) {
+                last_break = Some(i);
+            }
+        }
+        if !item.character.is_whitespace() && x + item.advance > max_width && i > start {
+            // 区切れる場所がなければ単語の途中で折り返す
+            return last_break.unwrap_or(i);
+        }
+        x += item.advance;
+    }
+    items.len()
+}
+
+fn place(items: &[Item]) -> Line {
+    let mut x = 0.0;
+    let mut width = 0.0;
+    let mut glyphs = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            x += item.kerning;
+        }
+        glyphs.push(PositionedGlyph {
+            face: item.face,
+            glyph_id: item.glyph_id,
+            character: item.character,
+            cluster: item.cluster,
+            x,
+            advance: item.advance,
+        });
+        x += item.advance;
+        if !item.character.is_whitespace() {
+            width = x;
+        }
+    }
+    Line {
+        glyphs,
+        width,
+        baseline: 0.0,
+    }
+}
+
+/// レイアウトした文字列をグレースケール画像に描く。`faces` はレイアウトしたときと同じ順に渡す
+pub fn render_layout(faces: &[Face], layout: &Layout) -> GrayImage {
+    let mut image = GrayImage::new(
+        layout.width.ceil().max(1.0) as u32,
+        layout.height.ceil().max(1.0) as u32,
+    );
+    for line in &layout.lines {
+        for glyph in &line.glyphs {
+            let face = &faces[glyph.face];
+            let Some(coverage) = rasterize_glyph(face, glyph.glyph_id, layout.size_px) else {
+                continue;
+            };
+            let left = glyph.x.round() as i32 + coverage.left;
+            let top = line.baseline.round() as i32 - coverage.top;
+            for y in 0..coverage.height {
+                for x in 0..coverage.width {
+                    let (px, py) = (left + x as i32, top + y as i32);
+                    if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32
+                    {
+                        continue;
+                    }
+                    let pixel = image.get_pixel_mut(px as u32, py as u32);
+                    pixel.0[0] = pixel.0[0].saturating_add(coverage.get(x, y));
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{FontBuilder, gpos_kern, kern, rect};
+
+    /// em 1000、各グリフが 500 幅の欧文と 1000 幅の「あ」を持つフォント
+    fn font() -> FontBuilder {
+        let mut font = FontBuilder::new(1000);
+        for c in ['A', 'V', 'a', 'b'] {
+            font.glyph(Some(c), 500, vec![rect(50, 0, 450, 700)]);
+        }
+        font.glyph(Some(' '), 250, vec![]);
+        font.glyph(Some('あ'), 1000, vec![rect(100, -100, 900, 700)]);
+        font.glyph(Some('。'), 1000, vec![rect(100, 0, 300, 200)]);
+        font.glyph(Some('「'), 1000, vec![rect(700, 0, 900, 700)]);
+        font
+    }
+
+    fn options(max_width: Option<f32>) -> LayoutOptions {
+        LayoutOptions {
+            size_px: 10.0,
+            max_width,
+            ..LayoutOptions::default()
+        }
+    }
+
+    fn text(line: &Line) -> String {
+        line.glyphs.iter().map(|g| g.character).collect()
+    }
+
+    #[test]
+    fn advances_and_clusters() {
+        let data = font().build();
+        let face = Face::parse(&data, 0).unwrap();
+        let layout = layout(&face, "Aあ b", &options(None));
+        assert_eq!(layout.lines.len(), 1);
+        let line = &layout.lines[0];
+        let xs: Vec<f32> = line.glyphs.iter().map(|g| g.x).collect();
+        assert_eq!(xs, [0.0, 5.0, 15.0, 17.5]);
+        let clusters: Vec<usize> = line.glyphs.iter().map(|g| g.cluster).collect();
+        assert_eq!(clusters, [0, 1, 4, 5]);
+        assert_eq!(line.width, 22.5);
+        assert_eq!(line.baseline, 8.0);
+        assert_eq!(layout.height, 10.0);
+    }
+
+    #[test]
+    fn kern_table() {
+        let mut font = font();
+        font.table(b"kern", kern(&[(1, 2, -100)]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+        assert_eq!(kerning(&face, GlyphId(1), GlyphId(2)), -100);
+        assert_eq!(kerning(&face, GlyphId(2), GlyphId(1)), 0);
+
+        let line = &layout(&face, "AVA", &options(None)).lines[0];
+        assert_eq!(line.glyphs[1].x, 4.0);
+        assert_eq!(line.glyphs[2].x, 9.0);
+        let line = &layout(
+            &face,
+            "AVA",
+            &LayoutOptions {
+                kerning: false,
+                ..options(None)
+            },
+        )
+        .lines[0];
+        assert_eq!(line.glyphs[1].x, 5.0);
+    }
+
+    #[test]
+    fn gpos_takes_precedence() {
+        let mut font = font();
+        font.table(b"kern", kern(&[(1, 2, -100)]));
+        font.table(b"GPOS", gpos_kern(&[(1, 2, -200), (1, 3, 50), (3, 4, -30)]));
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+        assert_eq!(kerning(&face, GlyphId(1), GlyphId(2)), -200);
+        assert_eq!(kerning(&face, GlyphId(1), GlyphId(3)), 50);
+        assert_eq!(kerning(&face, GlyphId(3), GlyphId(4)), -30);
+        assert_eq!(kerning(&face, GlyphId(2), GlyphId(1)), 0);
+    }
+
+    #[test]
+    fn break_at_spaces() {
+        let data = font().build();
+        let face = Face::parse(&data, 0).unwrap();
+        let layout = layout(&face, "ab ab aabb\nA", &options(Some(30.0)));
+        let lines: Vec<String> = layout.lines.iter().map(text).collect();
+        assert_eq!(lines, ["ab ab ", "aabb", "A"]);
+        // 行末の空白は幅に含めない
+        assert_eq!(layout.lines[0].width, 22.5);
+        assert_eq!(layout.lines[1].glyphs[0].x, 0.0);
+        assert_eq!(layout.lines[1].glyphs[0].cluster, 6);
+        assert_eq!(layout.lines[2].glyphs[0].cluster, 11);
+        assert_eq!(layout.lines[2].baseline, 28.0);
+        assert_eq!(layout.height, 30.0);
+
+        // 収まらない単語は途中で折り返す
+        let layout = super::layout(&face, "aaaaaaa", &options(Some(16.0)));
+        let lines: Vec<String> = layout.lines.iter().map(text).collect();
+        assert_eq!(lines, ["aaa", "aaa", "a"]);
+    }
+
+    #[test]
+    fn break_between_wide_characters() {
+        let data = font().build();
+        let face = Face::parse(&data, 0).unwrap();
+        let lines = |text_: &str, width| -> Vec<String> {
+            layout(&face, text_, &options(Some(width)))
+                .lines
+                .iter()
+                .map(text)
+                .collect()
+        };
+        assert_eq!(lines("あああ", 25.0), ["ああ", "あ"]);
+        // 句点は行頭に置かず、前の文字ごと次の行に送る
+        assert_eq!(lines("ああ。", 25.0), ["あ", "あ。"]);
+        // 開き括弧は行末に残さない
+        assert_eq!(lines("あ「あ", 25.0), ["あ", "「あ"]);
+        // フォントにない全角文字も 1 em の幅を取る
+        let layout = layout(&face, "い", &options(None));
+        assert_eq!(layout.lines[0].glyphs[0].glyph_id, GlyphId(0));
+        assert_eq!(layout.width, 10.0);
+    }
+
+    #[test]
+    fn render_lines() {
+        let data = font().build();
+        let face = Face::parse(&data, 0).unwrap();
+        let layout = layout(&face, "a\nb", &options(None));
+        let image = render_layout(std::slice::from_ref(&face), &layout);
+        assert_eq!(image.dimensions(), (5, 20));
+        // 1 行目のグリフは y = 1..8、2 行目は y = 11..18 を塗る
+        assert_eq!(image.get_pixel(2, 4).0[0], 255);
+        assert_eq!(image.get_pixel(2, 9).0[0], 0);
+        assert_eq!(image.get_pixel(2, 14).0[0], 255);
+        assert_eq!(image.get_pixel(0, 4).0[0], 128);
+    }
+
+    #[test]
+    fn no_faces() {
+        let layout = layout_with_fallback(&[], "ab", &options(None));
+        assert!(layout.lines.is_empty());
+        assert_eq!((layout.width, layout.height), (0.0, 0.0));
+        assert_eq!(render_layout(&[], &layout).dimensions(), (1, 1));
+    }
+}
@@ -1,4 +1,27 @@
-use std::ops::{Add, Div, Mul, Sub};
+use std::{
+    cmp::Ordering,
+    ops::{Add, Div, Mul, Sub},
+};
+
+pub mod atlas;
+pub mod color;
+pub mod font_file;
+pub mod gamma;
+pub mod golden;
+pub mod inspect;
+pub mod layout;
+pub mod mesh;
+pub mod predicates;
+pub mod raster;
+pub mod rasterizer;
+pub mod sdf;
+pub mod subpixel;
+#[cfg(test)]
+mod test_font;
+pub mod variation;
+pub mod vector;
+
+pub use raster::{Coverage, rasterize_glyph, rasterize_glyph_at};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Point {
@@ -56,17 +79,25 @@ impl Div<f32> for Point {
     }
 }
 
+/// 辺の上の点の判定に使う外積の計算方法
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Predicate {
+    /// f32 の外積。辺のごく近くでは丸め誤差で符号を誤ることがある
+    #[default]
+    Fast,
+    /// `predicates::orient2d_exact` による誤差のない符号
+    Exact,
+}
+
 #[derive(Clone, Copy)]
 pub struct Triangle {
     a: Point,
     b: Point,
     c: Point,
+    predicate: Predicate,
+    /// a → b → c の向き。一直線なら `Equal`
+    orientation: Ordering,
 
-    ab: Point,
-    bc: Point,
-    ca: Point,
-
-    total_area: f32,
     min_x: f32,
     min_y: f32,
     max_x: f32,
@@ -75,6 +106,10 @@ pub struct Triangle {
 
 impl Triangle {
     pub fn new(a: Point, b: Point, c: Point) -> Self {
+        Self::with_predicate(a, b, c, Predicate::default())
+    }
+
+    pub fn with_predicate(a: Point, b: Point, c: Point, predicate: Predicate) -> Self {
         let xs = [a.x, b.x, c.x];
         let min_x = xs.into_iter().reduce(f32::min).unwrap();
         let max_x = xs.into_iter().reduce(f32::max).unwrap();
@@ -82,36 +117,68 @@ impl Triangle {
         let min_y = xy.into_iter().reduce(f32::min).unwrap();
         let max_y = xy.into_iter().reduce(f32::max).unwrap();
 
-        Self {
+        let mut triangle = Self {
             a,
             b,
             c,
-            ab: a - b,
-            bc: b - c,
-            ca: c - a,
-            total_area: Self::outer_prod(a - b, a - c).abs(),
+            predicate,
+            orientation: Ordering::Equal,
             min_x,
             min_y,
             max_x,
             max_y,
-        }
+        };
+        triangle.orientation = triangle.orient(a, b, c);
+        triangle
     }
 
     /// 点が三角形の内部にあるかどうかを判定する
+    ///
+    /// 辺の上の点は、y 下向きの画像座標で上辺か左辺にあるときだけ内部とみなす (top-left rule)。
+    /// 辺を共有する三角形のどちらか一方だけが辺上の点を含むので、隙間も二重塗りも起きない。
+    /// 面積のない三角形は何も含まない。
     pub fn in_triangle(&self, p: &Point) -> bool {
-        if !self.in_rect(p) {
+        if self.orientation == Ordering::Equal || !self.in_rect(p) {
             return false;
         }
+        [(self.a, self.b), (self.b, self.c), (self.c, self.a)]
+            .into_iter()
+            .all(|(from, to)| self.owns(from, to, p))
+    }
 
-        let ap = self.a - *p;
-        let bp = self.b - *p;
-        let cp = self.c - *p;
+    /// 重心座標 (a, b, c の重み)。重みの和は 1 で、外側の点では負の重みを含む
+    ///
+    /// 面積のない三角形では `None`。
+    pub fn barycentric(&self, p: &Point) -> Option<[f32; 3]> {
+        let area = predicates::orient2d(self.a, self.b, self.c);
+        if area == 0.0 {
+            return None;
+        }
+        let wa = predicates::orient2d(self.b, self.c, *p) / area;
+        let wb = predicates::orient2d(self.c, self.a, *p) / area;
+        Some([wa as f32, wb as f32, (1.0 - wa - wb) as f32])
+    }
 
-        let o1 = self.ab.x * bp.y - self.ab.y * bp.x;
-        let o2 = self.bc.x * cp.y - self.bc.y * cp.x;
-        let o3 = self.ca.x * ap.y - self.ca.y * ap.x;
+    /// 辺 from → to が p を内側に含むかどうか。辺の上なら持ち主の辺のときだけ含む
+    fn owns(&self, from: Point, to: Point, p: &Point) -> bool {
+        match self.orient(from, to, *p) {
+            Ordering::Equal => {
+                // 三角形が時計回りなら辺を逆向きにたどって向きをそろえる
+                let d = match self.orientation {
+                    Ordering::Greater => to - from,
+                    _ => from - to,
+                };
+                d.y < 0.0 || (d.y == 0.0 && d.x > 0.0)
+            }
+            side => side == self.orientation,
+        }
+    }
 
-        (o1 > 0.0 && o2 > 0.0 && o3 > 0.0) || (o1 < 0.0 && o2 < 0.0 && o3 < 0.0)
+    fn orient(&self, a: Point, b: Point, c: Point) -> Ordering {
+        match self.predicate {
+            Predicate::Fast => Self::outer_prod(b - a, c - a).total_cmp(&0.0),
+            Predicate::Exact => predicates::orient2d_exact(a, b, c),
+        }
     }
 
     /// 外積を計算する
@@ -126,33 +193,108 @@ impl Triangle {
         (self.min_x..=self.max_x).contains(&p.x) && (self.min_y..=self.max_y).contains(&p.y)
     }
 
+    /// a を始点、b を終点、c を制御点とする 2 次ベジエと弦の間にあるかどうかを判定する
+    ///
+    /// 三角形の内外は `in_triangle` と同じ規則で決め、内側なら重心座標から
+    /// Loop–Blinn 法の (u, v) を求めて `u² < v` を調べる。
     pub fn in_besie(&self, p: &Point) -> bool {
-        // 矩形内になければそもそもすべてベジエ曲線のの範囲の外
-        if !self.in_rect(p) {
+        if !self.in_triangle(p) {
             return false;
         }
+        let Some([wa, _, wc]) = self.barycentric(p) else {
+            return false;
+        };
+        let (u, v) = (wc / 2.0 + wa, wa);
+        u * u < v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 中心で 4 つに分けた [0, 4]² の正方形。向きは交互に変える
+    fn square_fan(predicate: Predicate) -> Vec<Triangle> {
+        let center = Point::new(2.0, 2.0);
+        let corners =
+            [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)].map(|(x, y)| Point::new(x, y));
+        (0..4)
+            .map(|i| {
+                let (p, q) = (corners[i], corners[(i + 1) % 4]);
+                if i % 2 == 0 {
+                    Triangle::with_predicate(center, p, q, predicate)
+                } else {
+                    Triangle::with_predicate(q, p, center, predicate)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shared_edges_are_owned_once() {
+        for predicate in [Predicate::Fast, Predicate::Exact] {
+            let triangles = square_fan(predicate);
+            for y in 0..=8 {
+                for x in 0..=8 {
+                    let p = Point::new(x as f32 / 2.0, y as f32 / 2.0);
+                    let count = triangles.iter().filter(|t| t.in_triangle(&p)).count();
+                    // 右端と下端は外側の三角形の持ち分になる
+                    let expected = (x < 8 && y < 8) as usize;
+                    assert_eq!(count, expected, "{predicate:?} {p:?}");
+                }
+            }
+        }
+    }
 
-        let ap = self.a - *p;
-        let bp = self.b - *p;
-        let cp = self.c - *p;
-
-        let s = Self::outer_prod(self.ab, ap).abs() / self.total_area;
-        let t = Self::outer_prod(self.bc, bp).abs() / self.total_area;
-        let r = Self::outer_prod(self.ca, cp).abs() / self.total_area;
-
-        // 丸め誤差
-        let zero = -0.00001;
-        let one = 1.00001;
-        let zero_one_range = zero..=one;
-
-        if zero <= (s + t + r)
-            && (s + t + r) <= one
-            && zero_one_range.contains(&s)
-            && zero_one_range.contains(&t)
-            && zero_one_range.contains(&r)
-        {
-            return (s / 2.0 + t).powi(2) < t;
+    #[test]
+    fn exact_predicate_on_awkward_edge() {
+        let (from, to) = (Point::new(0.1, 0.3), Point::new(7.7, 9.1));
+        let left = Triangle::with_predicate(from, to, Point::new(-3.0, 8.0), Predicate::Exact);
+        let right = Triangle::with_predicate(to, from, Point::new(9.0, 1.0), Predicate::Exact);
+        // 端点は周りの他の三角形の持ち分になりうるので、辺の途中だけを調べる
+        for i in 1..1000 {
+            let t = i as f32 / 1000.0;
+            let p = from + (to - from) * t;
+            let count = [left, right].iter().filter(|tr| tr.in_triangle(&p)).count();
+            assert_eq!(count, 1, "{p:?}");
         }
-        false
+    }
+
+    #[test]
+    fn barycentric_weights() {
+        let t = Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(4.0, 0.0),
+            Point::new(0.0, 4.0),
+        );
+        assert_eq!(t.barycentric(&Point::new(0.0, 0.0)), Some([1.0, 0.0, 0.0]));
+        assert_eq!(
+            t.barycentric(&Point::new(1.0, 2.0)),
+            Some([0.25, 0.25, 0.5])
+        );
+        assert_eq!(t.barycentric(&Point::new(4.0, 4.0)), Some([-1.0, 1.0, 1.0]));
+
+        let flat = Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(2.0, 2.0),
+        );
+        assert_eq!(flat.barycentric(&Point::new(1.0, 1.0)), None);
+        assert!(!flat.in_triangle(&Point::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn bezier_region() {
+        // (0, 0) から (2, 0) へ、制御点 (1, 2) の曲線。頂点は (1, 1) を通る
+        let t = Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(2.0, 0.0),
+            Point::new(1.0, 2.0),
+        );
+        assert!(t.in_besie(&Point::new(1.0, 0.5)));
+        assert!(t.in_besie(&Point::new(1.0, 0.99)));
+        assert!(!t.in_besie(&Point::new(1.0, 1.01)));
+        assert!(!t.in_besie(&Point::new(1.0, 1.5)));
+        assert!(!t.in_besie(&Point::new(3.0, 0.5)));
     }
 }
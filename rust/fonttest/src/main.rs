@@ -0,0 +1,485 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand};
+use fonttest::{
+    color::render_layout_color,
+    font_file::FontFile,
+    inspect::{CharRange, coverage, font_info, text_chars},
+    layout::{LayoutOptions, layout_with_fallback, render_layout},
+    mesh::glyph_mesh,
+    rasterize_glyph,
+    subpixel::{GlyphCache, render_layout_rgb},
+    variation::{self, Variation, parse_tag, sweep},
+    vector::{render_layout_pdf, render_layout_svg},
+};
+use image::{GrayImage, Rgb, Rgba, imageops};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Rasterize glyphs from font files.", long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the faces in a font file or collection
+    Faces {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+    },
+    /// Rasterize a single character to a grayscale PNG
+    Glyph {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+
+        character: char,
+
+        /// Face index in a font collection
+        #[arg(long, short, default_value_t = 0)]
+        index: u32,
+
+        /// Em size in pixels
+        #[arg(long, short, default_value_t = 64.0)]
+        size: f32,
+
+        /// Variable font axis value such as wght=700 (repeatable)
+        #[arg(long)]
+        variation: Vec<Variation>,
+
+        #[arg(long, short, default_value = "glyph.png")]
+        output: PathBuf,
+    },
+    /// Print names, metrics and tables of a face and report Unicode coverage
+    Inspect {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+
+        /// Face index in a font collection
+        #[arg(long, short, default_value_t = 0)]
+        index: u32,
+
+        /// Code point range to check, such as U+0020-U+007E or U+00E9 (repeatable)
+        #[arg(long)]
+        range: Vec<CharRange>,
+
+        /// Check the characters used in this UTF-8 text file (repeatable)
+        #[arg(long)]
+        text: Vec<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Triangulate a single character and write the mesh as OBJ or glTF
+    ///
+    /// The format follows the extension of OUTPUT (.obj or .gltf).
+    Mesh {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+
+        character: char,
+
+        /// Face index in a font collection
+        #[arg(long, short, default_value_t = 0)]
+        index: u32,
+
+        /// Em size in pixels
+        #[arg(long, short, default_value_t = 64.0)]
+        size: f32,
+
+        /// Variable font axis value such as wght=700 (repeatable)
+        #[arg(long)]
+        variation: Vec<Variation>,
+
+        #[arg(long, short, default_value = "glyph.obj")]
+        output: PathBuf,
+    },
+    /// Lay out a string and render it to a grayscale PNG
+    Render {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+
+        text: String,
+
+        /// Face index in a font collection
+        #[arg(long, short, default_value_t = 0)]
+        index: u32,
+
+        /// Em size in pixels
+        #[arg(long, short, default_value_t = 32.0)]
+        size: f32,
+
+        /// Wrap lines longer than this many pixels
+        #[arg(long)]
+        max_width: Option<f32>,
+
+        /// Variable font axis value such as wght=700 (repeatable)
+        #[arg(long)]
+        variation: Vec<Variation>,
+
+        /// Disable pair kerning
+        #[arg(long)]
+        no_kerning: bool,
+
+        /// Fonts to search, in order, for characters missing from FONT
+        #[arg(long)]
+        fallback: Vec<PathBuf>,
+
+        /// Render color glyphs to an RGBA PNG with black text on a transparent background
+        #[arg(long, conflicts_with_all = ["foreground", "background"])]
+        color: bool,
+
+        /// Text color as RRGGBB. Renders an RGB PNG with subpixel positioning
+        #[arg(long, value_parser = parse_color)]
+        foreground: Option<Rgb<u8>>,
+
+        /// Background color as RRGGBB. Renders an RGB PNG with subpixel positioning
+        #[arg(long, value_parser = parse_color)]
+        background: Option<Rgb<u8>>,
+
+        /// Output file. A .svg or .pdf extension writes glyph outlines instead of pixels
+        #[arg(long, short, default_value = "text.png")]
+        output: PathBuf,
+    },
+    /// Render a string at evenly spaced values of one or two variable font axes
+    ///
+    /// The first axis varies down the rows and the second across the columns.
+    Sweep {
+        /// TTF / OTF / TTC file
+        font: PathBuf,
+
+        text: String,
+
+        /// Axis tag such as wght; give it twice for a two-axis grid
+        #[arg(long, required = true)]
+        axis: Vec<String>,
+
+        /// Number of values per axis, from its minimum to its maximum
+        #[arg(long, default_value_t = 5)]
+        steps: usize,
+
+        /// Face index in a font collection
+        #[arg(long, short, default_value_t = 0)]
+        index: u32,
+
+        /// Em size in pixels
+        #[arg(long, short, default_value_t = 32.0)]
+        size: f32,
+
+        /// Fixed value for another axis, such as opsz=12 (repeatable)
+        #[arg(long)]
+        variation: Vec<Variation>,
+
+        #[arg(long, short, default_value = "sweep.png")]
+        output: PathBuf,
+    },
+}
+
+/// スイープの格子でセルの間に空けるピクセル数
+const GRID_GAP: u32 = 8;
+
+/// カバレッジの報告で書き出す足りない文字の数の上限
+const MISSING_LIMIT: usize = 32;
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match args.command {
+        Command::Faces { font } => {
+            let file = FontFile::open(&font)?;
+            for index in 0..file.face_count() {
+                let file = FontFile::open_face(&font, index)?;
+                let face = file.face();
+                let family = face
+                    .names()
+                    .into_iter()
+                    .find(|name| {
+                        name.name_id == ttf_parser::name_id::FULL_NAME && name.is_unicode()
+                    })
+                    .and_then(|name| name.to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{index}: {family} ({:?}, {} glyphs, {} units/em)",
+                    file.format(),
+                    face.number_of_glyphs(),
+                    face.units_per_em()
+                );
+                for axis in face.variation_axes() {
+                    println!(
+                        "    {} {}..{} (default {})",
+                        axis.tag, axis.min_value, axis.max_value, axis.def_value
+                    );
+                }
+            }
+        }
+        Command::Glyph {
+            font,
+            character,
+            index,
+            size,
+            variation,
+            output,
+        } => {
+            let mut file = FontFile::open_face(&font, index)?;
+            file.set_variations(&variation)?;
+            let face = file.face();
+            let glyph_id = face
+                .glyph_index(character)
+                .with_context(|| format!("{character:?} is not in {}", font.display()))?;
+            let coverage = rasterize_glyph(&face, glyph_id, size)
+                .with_context(|| format!("{character:?} has no outline"))?;
+            coverage.to_image().save(&output)?;
+        }
+        Command::Inspect {
+            font,
+            index,
+            range,
+            text,
+            json,
+        } => {
+            let file = FontFile::open_face(&font, index)?;
+            let face = file.face();
+            let info = font_info(&face);
+            let mut reports = vec![];
+            for range in &range {
+                reports.push(coverage(&face, range.to_string(), range.chars()));
+            }
+            for path in &text {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                reports.push(coverage(
+                    &face,
+                    path.display().to_string(),
+                    text_chars(&contents),
+                ));
+            }
+
+            if json {
+                let report = serde_json::json!({ "font": info, "coverage": reports });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            println!("{} ({:?}, face {index})", font.display(), file.format());
+            for name in &info.names {
+                let label = match name.label {
+                    "" => format!("Name {}", name.id),
+                    label => label.to_string(),
+                };
+                // 複数行の名前 (ライセンスなど) は 1 行目だけにする。全文は --json で見る
+                let mut lines = name.value.lines();
+                let first = lines.next().unwrap_or_default();
+                let more = if lines.next().is_some() { " ..." } else { "" };
+                println!("  {label}: {first}{more}");
+            }
+            println!("  Units per em: {}", info.units_per_em);
+            println!(
+                "  Ascender / descender / line gap: {} / {} / {}",
+                info.ascender, info.descender, info.line_gap
+            );
+            if let Some(x_height) = info.x_height {
+                println!("  x-height: {x_height}");
+            }
+            if let Some(capital_height) = info.capital_height {
+                println!("  Cap height: {capital_height}");
+            }
+            println!("  Glyphs: {}", info.glyph_count);
+            println!("  Variable: {}", if info.variable { "yes" } else { "no" });
+            println!("  Tables ({}):", info.tables.len());
+            for table in &info.tables {
+                println!("    {} {:>10} bytes", table.tag, table.length);
+            }
+            if !reports.is_empty() {
+                println!("Coverage:");
+            }
+            for report in &reports {
+                println!(
+                    "  {}: {}/{} ({:.1}%)",
+                    report.label,
+                    report.covered,
+                    report.total,
+                    report.percent()
+                );
+                if report.missing.is_empty() {
+                    continue;
+                }
+                let mut missing: Vec<_> = report
+                    .missing
+                    .iter()
+                    .take(MISSING_LIMIT)
+                    .map(|c| format!("U+{:04X}", *c as u32))
+                    .collect();
+                if report.missing.len() > MISSING_LIMIT {
+                    missing.push(format!("... {} more", report.missing.len() - MISSING_LIMIT));
+                }
+                println!("    missing: {}", missing.join(" "));
+            }
+        }
+        Command::Mesh {
+            font,
+            character,
+            index,
+            size,
+            variation,
+            output,
+        } => {
+            let mut file = FontFile::open_face(&font, index)?;
+            file.set_variations(&variation)?;
+            let face = file.face();
+            let glyph_id = face
+                .glyph_index(character)
+                .with_context(|| format!("{character:?} is not in {}", font.display()))?;
+            let mesh = glyph_mesh(&face, glyph_id, size)
+                .with_context(|| format!("{character:?} has no outline"))?;
+            let contents = match output.extension().and_then(|e| e.to_str()) {
+                Some("obj") => mesh.to_obj(),
+                Some("gltf") => mesh.to_gltf(),
+                _ => bail!("unsupported mesh format {}", output.display()),
+            };
+            std::fs::write(&output, contents)?;
+            println!(
+                "{} triangles, {} vertices",
+                mesh.triangle_count(),
+                mesh.vertices.len()
+            );
+        }
+        Command::Render {
+            font,
+            text,
+            index,
+            size,
+            max_width,
+            variation,
+            no_kerning,
+            fallback,
+            color,
+            foreground,
+            background,
+            output,
+        } => {
+            let mut primary = FontFile::open_face(&font, index)?;
+            primary.set_variations(&variation)?;
+            let mut files = vec![primary];
+            for path in &fallback {
+                files.push(FontFile::open(path)?);
+            }
+            let faces: Vec<_> = files.iter().map(FontFile::face).collect();
+            let options = LayoutOptions {
+                size_px: size,
+                max_width,
+                kerning: !no_kerning,
+                ..LayoutOptions::default()
+            };
+            let layout = layout_with_fallback(&faces, &text, &options);
+            let ink = foreground.unwrap_or(Rgb([0, 0, 0]));
+            match output.extension().and_then(|e| e.to_str()) {
+                Some("svg") => {
+                    let svg = render_layout_svg(&faces, &layout, ink, background);
+                    std::fs::write(&output, svg)?;
+                }
+                Some("pdf") => {
+                    let pdf = render_layout_pdf(&faces, &layout, ink, background);
+                    std::fs::write(&output, pdf)?;
+                }
+                _ if color => {
+                    render_layout_color(&faces, &layout, Rgba([0, 0, 0, 255])).save(&output)?;
+                }
+                _ if foreground.is_some() || background.is_some() => {
+                    let mut cache = GlyphCache::default();
+                    render_layout_rgb(
+                        &faces,
+                        &layout,
+                        &mut cache,
+                        ink,
+                        background.unwrap_or(Rgb([255, 255, 255])),
+                    )
+                    .save(&output)?;
+                }
+                _ => render_layout(&faces, &layout).save(&output)?,
+            }
+        }
+        Command::Sweep {
+            font,
+            text,
+            axis,
+            steps,
+            index,
+            size,
+            variation,
+            output,
+        } => {
+            if axis.len() > 2 {
+                bail!("at most two axes can be swept");
+            }
+            let mut file = FontFile::open_face(&font, index)?;
+            let mut axes = vec![];
+            for name in &axis {
+                let tag = parse_tag(name).with_context(|| format!("invalid axis tag {name:?}"))?;
+                let axis = variation::axis(&file.face(), tag)
+                    .with_context(|| format!("{} has no {tag} axis", font.display()))?;
+                axes.push(axis);
+            }
+
+            let rows = sweep(&axes[0], steps);
+            let columns = match axes.get(1) {
+                Some(axis) => sweep(axis, steps).into_iter().map(Some).collect(),
+                None => vec![None],
+            };
+            let options = LayoutOptions {
+                size_px: size,
+                ..LayoutOptions::default()
+            };
+            let mut cells = vec![];
+            for (row, &row_value) in rows.iter().enumerate() {
+                println!("row {row}: {}={row_value}", axes[0].tag);
+                for &column_value in &columns {
+                    let mut variations = variation.clone();
+                    variations.push(Variation {
+                        tag: axes[0].tag,
+                        value: row_value,
+                    });
+                    if let Some(value) = column_value {
+                        variations.push(Variation {
+                            tag: axes[1].tag,
+                            value,
+                        });
+                    }
+                    file.set_variations(&variations)?;
+                    let face = file.face();
+                    let layout = layout_with_fallback(std::slice::from_ref(&face), &text, &options);
+                    cells.push(render_layout(std::slice::from_ref(&face), &layout));
+                }
+            }
+            for (column, value) in columns.iter().enumerate() {
+                if let Some(value) = value {
+                    println!("column {column}: {}={value}", axes[1].tag);
+                }
+            }
+
+            let cell_width = cells.iter().map(|c| c.width()).max().unwrap_or(0) + GRID_GAP;
+            let cell_height = cells.iter().map(|c| c.height()).max().unwrap_or(0) + GRID_GAP;
+            let mut grid = GrayImage::new(
+                cell_width * columns.len() as u32 + GRID_GAP,
+                cell_height * rows.len() as u32 + GRID_GAP,
+            );
+            for (i, cell) in cells.iter().enumerate() {
+                let (row, column) = (i / columns.len(), i % columns.len());
+                let x = GRID_GAP + column as u32 * cell_width;
+                let y = GRID_GAP + row as u32 * cell_height;
+                imageops::replace(&mut grid, cell, x as i64, y as i64);
+            }
+            grid.save(&output)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_color(s: &str) -> Result<Rgb<u8>, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let value = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)
+        .ok_or_else(|| format!("expected a color like ff8000, got {s:?}"))?;
+    Ok(Rgb([(value >> 16) as u8, (value >> 8) as u8, value as u8]))
+}
@@ -0,0 +1,323 @@
+//! グリフの輪郭を、重なりのない三角形のメッシュにして書き出す
+//!
+//! 分解は `bezier_converter::triangulate::triangulate_disjoint` で行う。三角形同士が重ならないので、
+//! ステンシルを使わずにそのまま描ける。ここでは CPU での描画と OBJ / glTF への書き出しを受け持つ。
+
+use std::{fmt::Write, ops::Deref};
+
+use bezier_converter::{
+    path::FillRule,
+    scalar::Point,
+    triangulate::{Mesh, triangulate_disjoint},
+};
+use serde_json::json;
+use ttf_parser::{Face, GlyphId};
+
+use crate::{Coverage, raster::Outline};
+
+type Vec2 = Point<f32>;
+
+/// CPU で描くときの 1 ピクセルあたりの縦横のサンプル数
+const SAMPLES: u32 = 8;
+
+/// ピクセル単位 (y は上向き) の重ならないメッシュ
+///
+/// 反時計回りの三角形は補間した値が `u² - v < 0`、時計回りの三角形は `u² - v > 0` の画素を塗る。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlyphMesh(pub Mesh);
+
+impl Deref for GlyphMesh {
+    type Target = Mesh;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// グリフを `size_px` ピクセルの em でメッシュにする。輪郭のないグリフは `None`
+pub fn glyph_mesh(face: &Face, glyph_id: GlyphId, size_px: f32) -> Option<GlyphMesh> {
+    let (outline, _) = Outline::glyph(face, glyph_id, size_px)?;
+    Some(GlyphMesh(triangulate_disjoint(
+        &outline.path,
+        FillRule::NonZero,
+    )))
+}
+
+impl GlyphMesh {
+    /// 三角形の数
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// CPU で `SAMPLES`² 点ずつ標本化して塗る
+    ///
+    /// 範囲は `Coverage` と同じく、原点から左端までと、ベースラインから上端までのピクセル数で指定する。
+    pub fn rasterize(&self, left: i32, top: i32, width: u32, height: u32) -> Coverage {
+        let (columns, rows) = (width * SAMPLES, height * SAMPLES);
+        let mut hits = vec![false; (columns * rows) as usize];
+        let n = SAMPLES as f32;
+        for triangle in self.indices.chunks_exact(3) {
+            let v = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+            // サンプルの中心が整数になる座標 (y は下向き)
+            let p =
+                v.map(|v| Vec2::new((v.x - left as f32) * n - 0.5, (top as f32 - v.y) * n - 0.5));
+            let area = (p[1] - p[0]).perp_dot(p[2] - p[0]);
+            if area == 0.0 {
+                continue;
+            }
+            // y を反転しているので、面積が負なら元の座標で反時計回り
+            let counter_clockwise = area < 0.0;
+            let min = p[0].min(p[1]).min(p[2]).ceil().max(Vec2::ZERO);
+            let max = p[0].max(p[1]).max(p[2]).floor();
+            let max = max.min(Vec2::new(columns as f32 - 1.0, rows as f32 - 1.0));
+            for sy in min.y as i32..=max.y as i32 {
+                for sx in min.x as i32..=max.x as i32 {
+                    let s = Vec2::new(sx as f32, sy as f32);
+                    let w1 = (s - p[0]).perp_dot(p[2] - p[0]) / area;
+                    let w2 = (p[1] - p[0]).perp_dot(s - p[0]) / area;
+                    let w0 = 1.0 - w1 - w2;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+                    let u = w0 * v[0].u + w1 * v[1].u + w2 * v[2].u;
+                    let tv = w0 * v[0].v + w1 * v[1].v + w2 * v[2].v;
+                    if (u * u - tv < 0.0) == counter_clockwise {
+                        hits[(sy as u32 * columns + sx as u32) as usize] = true;
+                    }
+                }
+            }
+        }
+
+        let mut data = vec![0; (width * height) as usize];
+        for (i, value) in data.iter_mut().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let count: u32 = (0..SAMPLES)
+                .flat_map(|dy| (0..SAMPLES).map(move |dx| (dx, dy)))
+                .filter(|(dx, dy)| hits[((y * SAMPLES + dy) * columns + x * SAMPLES + dx) as usize])
+                .count() as u32;
+            *value = ((count * 255 + SAMPLES * SAMPLES / 2) / (SAMPLES * SAMPLES)) as u8;
+        }
+        Coverage {
+            width,
+            height,
+            left,
+            top,
+            data,
+        }
+    }
+
+    /// Wavefront OBJ。(u, v) はテクスチャ座標に書き、面の頂点の順序は三角形の向きを保つ
+    pub fn to_obj(&self) -> String {
+        let mut obj = String::from("# fonttest glyph mesh\n");
+        for v in &self.vertices {
+            writeln!(obj, "v {} {} 0", v.x, v.y).unwrap();
+        }
+        for v in &self.vertices {
+            writeln!(obj, "vt {} {}", v.u, v.v).unwrap();
+        }
+        for t in self.indices.chunks_exact(3) {
+            let [a, b, c] = [t[0] + 1, t[1] + 1, t[2] + 1];
+            writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}").unwrap();
+        }
+        obj
+    }
+
+    /// バッファを埋め込んだ glTF 2.0
+    ///
+    /// 位置は z = 0 の `POSITION`、(u, v) は `TEXCOORD_0` に入れる。
+    pub fn to_gltf(&self) -> String {
+        let mut buffer = vec![];
+        for v in &self.vertices {
+            for value in [v.x, v.y, 0.0] {
+                buffer.extend(value.to_le_bytes());
+            }
+        }
+        let texcoord_offset = buffer.len();
+        for v in &self.vertices {
+            buffer.extend(v.u.to_le_bytes());
+            buffer.extend(v.v.to_le_bytes());
+        }
+        let index_offset = buffer.len();
+        buffer.extend(self.index_bytes());
+
+        let (min, max) = self.vertices.iter().fold(
+            ([f32::MAX, f32::MAX, 0.0], [f32::MIN, f32::MIN, 0.0]),
+            |(min, max), v| {
+                (
+                    [min[0].min(v.x), min[1].min(v.y), 0.0],
+                    [max[0].max(v.x), max[1].max(v.y), 0.0],
+                )
+            },
+        );
+        let count = self.vertices.len();
+        let view = |offset: usize, length: usize, target: u32| json!({"buffer": 0, "byteOffset": offset, "byteLength": length, "target": target});
+        let gltf = json!({
+            "asset": {"version": "2.0", "generator": "fonttest"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"mesh": 0}],
+            "meshes": [{
+                "primitives": [{
+                    "attributes": {"POSITION": 0, "TEXCOORD_0": 1},
+                    "indices": 2,
+                    "mode": 4,
+                }],
+            }],
+            "buffers": [{
+                "byteLength": buffer.len(),
+                "uri": format!("data:application/octet-stream;base64,{}", base64(&buffer)),
+            }],
+            "bufferViews": [
+                view(0, texcoord_offset, 34962),
+                view(texcoord_offset, index_offset - texcoord_offset, 34962),
+                view(index_offset, buffer.len() - index_offset, 34963),
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": count, "type": "VEC3",
+                 "min": if count > 0 { min } else { [0.0; 3] },
+                 "max": if count > 0 { max } else { [0.0; 3] }},
+                {"bufferView": 1, "componentType": 5126, "count": count, "type": "VEC2"},
+                {"bufferView": 2, "componentType": 5125, "count": self.indices.len(), "type": "SCALAR"},
+            ],
+        });
+        serde_json::to_string_pretty(&gltf).unwrap()
+    }
+
+    /// `Vertex` をそのまま並べたリトルエンディアンの頂点バッファ
+    pub fn vertex_bytes(&self) -> Vec<u8> {
+        self.vertices
+            .iter()
+            .flat_map(|v| [v.x, v.y, v.u, v.v])
+            .flat_map(f32::to_le_bytes)
+            .collect()
+    }
+
+    /// u32 のリトルエンディアンのインデックスバッファ
+    pub fn index_bytes(&self) -> Vec<u8> {
+        self.indices.iter().flat_map(|i| i.to_le_bytes()).collect()
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [0, 1, 2].map(|i| chunk.get(i).copied().unwrap_or(0) as u32);
+        let n = bytes[0] << 16 | bytes[1] << 8 | bytes[2];
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use bezier_converter::path::Path;
+
+    use super::*;
+    use crate::{
+        rasterize_glyph,
+        test_font::{FontBuilder, circle, rect},
+    };
+
+    #[test]
+    fn ring_and_overlap() {
+        // 逆回りの円で穴を開けた輪に、長方形を重ねたもの
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(
+            None,
+            1000,
+            vec![
+                circle(500, 500, 400, false),
+                circle(500, 500, 325, true),
+                rect(50, 450, 950, 575),
+            ],
+        );
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let mesh = glyph_mesh(&face, GlyphId(id), 40.0).unwrap();
+        let expected = rasterize_glyph(&face, GlyphId(id), 40.0).unwrap();
+        let actual = mesh.rasterize(expected.left, expected.top, expected.width, expected.height);
+        let diffs: Vec<u8> = expected
+            .data
+            .iter()
+            .zip(&actual.data)
+            .map(|(a, b)| a.abs_diff(*b))
+            .collect();
+        let max = diffs.iter().copied().max().unwrap();
+        let mean = diffs.iter().map(|&d| d as f32).sum::<f32>() / diffs.len() as f32;
+        assert!(max <= 40 && mean < 1.0, "{max} {mean}");
+    }
+
+    #[test]
+    fn glyph_matches_rasterizer() {
+        let mut font = FontBuilder::new(1000);
+        let mut inner = rect(250, 125, 750, 375);
+        inner.reverse();
+        let id = font.glyph(None, 1000, vec![rect(0, 0, 1000, 500), inner]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let mesh = glyph_mesh(&face, GlyphId(id), 16.0).unwrap();
+        // 穴のある長方形は台形 4 つに分かれる
+        assert_eq!(mesh.triangle_count(), 8);
+        assert!(mesh.vertices.iter().all(|v| (v.u, v.v) == (0.0, 1.0)));
+        let expected = rasterize_glyph(&face, GlyphId(id), 16.0).unwrap();
+        let actual = mesh.rasterize(expected.left, expected.top, expected.width, expected.height);
+        assert_eq!(actual, expected);
+        assert_eq!(glyph_mesh(&face, GlyphId(0), 16.0), None);
+    }
+
+    #[test]
+    fn export_formats() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(10.0, 0.0);
+        path.quad_to(10.0, 10.0, 0.0, 10.0);
+        path.close();
+        let mesh = GlyphMesh(triangulate_disjoint(&path, FillRule::NonZero));
+        let (vertices, triangles) = (mesh.vertices.len(), mesh.triangle_count());
+
+        let obj = mesh.to_obj();
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("v ")).count(),
+            vertices
+        );
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("vt ")).count(),
+            vertices
+        );
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("f ")).count(),
+            triangles
+        );
+        assert!(obj.contains("vt 0.5 0\n"));
+
+        let gltf: serde_json::Value = serde_json::from_str(&mesh.to_gltf()).unwrap();
+        let length = gltf["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+        assert_eq!(length, vertices * 20 + triangles * 12);
+        let uri = gltf["buffers"][0]["uri"].as_str().unwrap();
+        let encoded = uri.split_once(',').unwrap().1;
+        assert_eq!(encoded.len(), length.div_ceil(3) * 4);
+        assert_eq!(gltf["accessors"][2]["count"], triangles * 3);
+        assert_eq!(gltf["accessors"][0]["max"][0], 10.0);
+
+        assert_eq!(mesh.vertex_bytes().len(), vertices * 16);
+        assert_eq!(mesh.index_bytes().len(), triangles * 12);
+        assert_eq!(&mesh.vertex_bytes()[..4], &mesh.vertices[0].x.to_le_bytes());
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b""), "");
+    }
+}
@@ -0,0 +1,107 @@
+//! 丸め誤差で符号を誤らない 2 次元の向きの判定
+//!
+//! 外積の各項を誤差のない掛け算と足し算で f64 の列 (expansion) のまま足し合わせ、
+//! 最も大きい成分の符号を答えにする (Shewchuk の方法)。
+
+use std::cmp::Ordering;
+
+use crate::Point;
+
+/// a → b → c が反時計回り (x 右、y 上) なら正、時計回りなら負、一直線なら 0 になる外積
+///
+/// f64 で計算した近似値なので、ほぼ一直線のときは符号を誤ることがある。
+pub fn orient2d(a: Point, b: Point, c: Point) -> f64 {
+    let (ax, ay) = (a.x as f64 - c.x as f64, a.y as f64 - c.y as f64);
+    let (bx, by) = (b.x as f64 - c.x as f64, b.y as f64 - c.y as f64);
+    ax * by - ay * bx
+}
+
+/// `orient2d` の正確な符号
+pub fn orient2d_exact(a: Point, b: Point, c: Point) -> Ordering {
+    let [ax, ay, bx, by, cx, cy] = [a.x, a.y, b.x, b.y, c.x, c.y].map(f64::from);
+    // (a - c) × (b - c) を展開すると c.x * c.y の項は打ち消し合う
+    let terms = [
+        (ax, by),
+        (-ax, cy),
+        (-cx, by),
+        (-ay, bx),
+        (ay, cx),
+        (cy, bx),
+    ];
+    let mut expansion = Vec::with_capacity(terms.len() * 2);
+    for (x, y) in terms {
+        let (product, error) = two_product(x, y);
+        grow(&mut expansion, error);
+        grow(&mut expansion, product);
+    }
+    expansion
+        .iter()
+        .rev()
+        .find(|v| **v != 0.0)
+        .map_or(Ordering::Equal, |v| v.total_cmp(&0.0))
+}
+
+/// 和と、その丸め誤差
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+/// 積と、その丸め誤差
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+/// 絶対値の小さい順に並んだ重なりのない列に `b` を足す
+fn grow(expansion: &mut Vec<f64>, b: f64) {
+    let mut q = b;
+    for component in expansion.iter_mut() {
+        let (sum, error) = two_sum(q, *component);
+        *component = error;
+        q = sum;
+    }
+    expansion.push(q);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orientation_signs() {
+        let (a, b) = (Point::new(0.0, 0.0), Point::new(4.0, 0.0));
+        assert_eq!(orient2d(a, b, Point::new(1.0, 3.0)), 12.0);
+        assert_eq!(
+            orient2d_exact(a, b, Point::new(1.0, 3.0)),
+            Ordering::Greater
+        );
+        assert_eq!(orient2d_exact(a, b, Point::new(1.0, -3.0)), Ordering::Less);
+        assert_eq!(orient2d_exact(a, b, Point::new(9.0, 0.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn nearly_collinear() {
+        // 傾き 1 の直線上の点と、y を f32 で 1 ulp だけ上にずらした点
+        let (a, b) = (Point::new(0.5, 0.5), Point::new(12.0, 12.0));
+        let on = Point::new(24.0, 24.0);
+        let above = Point::new(0.5, f32::from_bits(0.5f32.to_bits() + 1));
+        assert_eq!(orient2d_exact(a, b, on), Ordering::Equal);
+        assert_eq!(orient2d_exact(a, b, above), Ordering::Greater);
+        assert_eq!(orient2d_exact(b, a, above), Ordering::Less);
+
+        // 大きさの違う項が打ち消し合っても符号を保つ
+        let far = Point::new(1e30, 1e30);
+        let near = Point::new(1.0, f32::from_bits(1.0f32.to_bits() + 1));
+        assert_eq!(
+            orient2d_exact(Point::new(0.0, 0.0), far, near),
+            Ordering::Greater
+        );
+        assert_eq!(
+            orient2d_exact(Point::new(0.0, 0.0), far, Point::new(3.0, 3.0)),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! グリフの輪郭を塗りつぶしてカバレッジのビットマップを作る
+
+use bezier_converter::path::{Path, Segment};
+use image::GrayImage;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::{
+    Point,
+    rasterizer::{FLATTEN_TOLERANCE, Rasterizer},
+};
+
+/// グリフのカバレッジ (0 が空白、255 が塗りつぶし)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    pub width: u32,
+    pub height: u32,
+    /// 原点からビットマップ左端までのピクセル数
+    pub left: i32,
+    /// ベースラインからビットマップ上端までのピクセル数 (上向きが正)
+    pub top: i32,
+    /// 上の行から順に並べたカバレッジ
+    pub data: Vec<u8>,
+}
+
+impl Coverage {
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        self.data[(y * self.width + x) as usize]
+    }
+
+    /// カバレッジをそのまま輝度にしたグレースケール画像
+    pub fn to_image(&self) -> GrayImage {
+        GrayImage::from_raw(self.width, self.height, self.data.clone()).unwrap()
+    }
+}
+
+/// グリフを `size_px` ピクセルの em で描画する。輪郭のないグリフは `None`
+pub fn rasterize_glyph(face: &Face, glyph_id: GlyphId, size_px: f32) -> Option<Coverage> {
+    rasterize_glyph_at(face, glyph_id, size_px, 0.0)
+}
+
+/// 原点を右に `offset_x` ピクセルずらして描画する
+///
+/// `left` はずらす前の原点からの位置になるので、整数のペン位置にそのまま足せる。
+pub fn rasterize_glyph_at(
+    face: &Face,
+    glyph_id: GlyphId,
+    size_px: f32,
+    offset_x: f32,
+) -> Option<Coverage> {
+    let (outline, bounds) = Outline::glyph_at(face, glyph_id, size_px, offset_x)?;
+    Some(outline.rasterize(bounds))
+}
+
+/// ビットマップの位置と大きさ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Bounds {
+    pub(crate) left: i32,
+    pub(crate) top: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl Bounds {
+    /// 四方に `padding` ピクセル広げる
+    pub(crate) fn pad(&self, padding: u32) -> Bounds {
+        Bounds {
+            left: self.left - padding as i32,
+            top: self.top + padding as i32,
+            width: self.width + 2 * padding,
+            height: self.height + 2 * padding,
+        }
+    }
+}
+
+/// ピクセル単位に拡大したグリフの輪郭 (y は上向き)
+pub(crate) struct Outline {
+    scale: f32,
+    offset_x: f32,
+    pub(crate) path: Path,
+}
+
+impl Outline {
+    pub(crate) fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            offset_x: 0.0,
+            path: Path::new(),
+        }
+    }
+
+    /// グリフの輪郭と、それを覆うビットマップの範囲
+    pub(crate) fn glyph(face: &Face, glyph_id: GlyphId, size_px: f32) -> Option<(Self, Bounds)> {
+        Self::glyph_at(face, glyph_id, size_px, 0.0)
+    }
+
+    /// 原点を右に `offset_x` ピクセルずらしたグリフの輪郭と範囲
+    pub(crate) fn glyph_at(
+        face: &Face,
+        glyph_id: GlyphId,
+        size_px: f32,
+        offset_x: f32,
+    ) -> Option<(Self, Bounds)> {
+        let scale = size_px / face.units_per_em() as f32;
+        let mut outline = Outline {
+            offset_x,
+            ..Outline::new(scale)
+        };
+        let rect = face.outline_glyph(glyph_id, &mut outline)?;
+
+        let left = (rect.x_min as f32 * scale + offset_x).floor() as i32;
+        let right = (rect.x_max as f32 * scale + offset_x).ceil() as i32;
+        let top = (rect.y_max as f32 * scale).ceil() as i32;
+        let bottom = (rect.y_min as f32 * scale).floor() as i32;
+        let bounds = Bounds {
+            left,
+            top,
+            width: (right - left).max(1) as u32,
+            height: (top - bottom).max(1) as u32,
+        };
+        Some((outline, bounds))
+    }
+
+    /// 3 次ベジエを 2 次ベジエに変換して `Rasterizer` で塗りつぶす
+    pub(crate) fn rasterize(&self, bounds: Bounds) -> Coverage {
+        fill_path(&self.path, bounds)
+    }
+}
+
+/// ピクセル単位 (y は上向き) のパスを `bounds` の範囲で塗りつぶす
+///
+/// 3 次ベジエは直線に分割するときと同じ `FLATTEN_TOLERANCE` で 2 次ベジエに変換する。
+pub(crate) fn fill_path(path: &Path, bounds: Bounds) -> Coverage {
+    let to_pixel = |x: f32, y: f32| Point::new(x - bounds.left as f32, bounds.top as f32 - y);
+    let mut rasterizer = Rasterizer::new(bounds.width, bounds.height);
+    for contour in &path.to_quadratic_with_tolerance(FLATTEN_TOLERANCE).contours {
+        for segment in &contour.segments {
+            match segment {
+                Segment::Line(l) => {
+                    rasterizer.draw_line(to_pixel(l.x0, l.y0), to_pixel(l.x1, l.y1))
+                }
+                Segment::Quadratic(q) => rasterizer.draw_quad(
+                    to_pixel(q.x0, q.y0),
+                    to_pixel(q.cx0, q.cy0),
+                    to_pixel(q.x1, q.y1),
+                ),
+                Segment::Cubic(_) => unreachable!(),
+            }
+        }
+    }
+
+    Coverage {
+        width: bounds.width,
+        height: bounds.height,
+        left: bounds.left,
+        top: bounds.top,
+        data: rasterizer.accumulate(),
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (s, o) = (self.scale, self.offset_x);
+        self.path.move_to(x * s + o, y * s);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (s, o) = (self.scale, self.offset_x);
+        self.path.line_to(x * s + o, y * s);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (s, o) = (self.scale, self.offset_x);
+        self.path.quad_to(x1 * s + o, y1 * s, x * s + o, y * s);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (s, o) = (self.scale, self.offset_x);
+        self.path
+            .curve_to(x1 * s + o, y1 * s, x2 * s + o, y2 * s, x * s + o, y * s);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{FontBuilder, circle, rect};
+
+    fn sum(coverage: &Coverage) -> f32 {
+        coverage.data.iter().map(|&v| v as f32 / 255.0).sum()
+    }
+
+    #[test]
+    fn rectangle_is_filled() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('a'), 1000, vec![rect(0, 0, 1000, 500)]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let coverage = rasterize_glyph(&face, GlyphId(id), 16.0).unwrap();
+        assert_eq!((coverage.width, coverage.height), (16, 8));
+        assert_eq!((coverage.left, coverage.top), (0, 8));
+        assert!(coverage.data.iter().all(|&v| v == 255));
+    }
+
+    #[test]
+    fn hole_is_empty() {
+        let mut font = FontBuilder::new(1000);
+        let mut inner = rect(250, 125, 750, 375);
+        inner.reverse();
+        let id = font.glyph(None, 1000, vec![rect(0, 0, 1000, 500), inner]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let coverage = rasterize_glyph(&face, GlyphId(id), 16.0).unwrap();
+        assert_eq!(coverage.get(0, 0), 255);
+        assert_eq!(coverage.get(8, 4), 0);
+        assert!((sum(&coverage) - (128.0 - 32.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn circle_area() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('o'), 1000, vec![circle(500, 500, 500, false)]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let coverage = rasterize_glyph(&face, GlyphId(id), 32.0).unwrap();
+        assert_eq!((coverage.width, coverage.height), (32, 32));
+        // 菱形 2r² と、4 つの弓形 (三角形の 2/3) の和
+        let r = 16.0;
+        let expected = 2.0 * r * r + 4.0 * (2.0 / 3.0) * (r * r / 2.0);
+        assert!(
+            (sum(&coverage) / expected - 1.0).abs() < 0.01,
+            "{} {expected}",
+            sum(&coverage)
+        );
+    }
+
+    #[test]
+    fn cubic_outline() {
+        // 4 分円を 3 次ベジエで近似した円
+        let k = 0.552_284_8 * 10.0;
+        let mut outline = Outline::new(4.0);
+        outline.move_to(20.0, 10.0);
+        outline.curve_to(20.0, 10.0 + k, 10.0 + k, 20.0, 10.0, 20.0);
+        outline.curve_to(10.0 - k, 20.0, 0.0, 10.0 + k, 0.0, 10.0);
+        outline.curve_to(0.0, 10.0 - k, 10.0 - k, 0.0, 10.0, 0.0);
+        outline.curve_to(10.0 + k, 0.0, 20.0, 10.0 - k, 20.0, 10.0);
+        outline.close();
+
+        let coverage = outline.rasterize(Bounds {
+            left: 0,
+            top: 80,
+            width: 80,
+            height: 80,
+        });
+        let expected = std::f32::consts::PI * 1600.0;
+        assert!(
+            (sum(&coverage) / expected - 1.0).abs() < 0.01,
+            "{} {expected}",
+            sum(&coverage)
+        );
+    }
+
+    /// 小さい 3 次ベジエの円のカバレッジを、ピクセルを細かく区切って数えた正確な円と比べる
+    #[test]
+    fn small_cubic_circle() {
+        let r = 4.0;
+        let k = 0.552_284_8 * r;
+        let (cx, cy) = (5.0, 5.0);
+        let mut outline = Outline::new(1.0);
+        outline.move_to(cx + r, cy);
+        outline.curve_to(cx + r, cy + k, cx + k, cy + r, cx, cy + r);
+        outline.curve_to(cx - k, cy + r, cx - r, cy + k, cx - r, cy);
+        outline.curve_to(cx - r, cy - k, cx - k, cy - r, cx, cy - r);
+        outline.curve_to(cx + k, cy - r, cx + r, cy - k, cx + r, cy);
+        outline.close();
+
+        let coverage = outline.rasterize(Bounds {
+            left: 0,
+            top: 10,
+            width: 10,
+            height: 10,
+        });
+        let n = 64;
+        let mut max_diff = 0.0_f32;
+        for y in 0..10 {
+            for x in 0..10 {
+                let inside = (0..n * n)
+                    .filter(|i| {
+                        let px = x as f32 + ((i % n) as f32 + 0.5) / n as f32 - cx;
+                        let py = 10.0 - y as f32 - ((i / n) as f32 + 0.5) / n as f32 - cy;
+                        px * px + py * py < r * r
+                    })
+                    .count();
+                let exact = inside as f32 / (n * n) as f32;
+                let diff = (coverage.get(x, y) as f32 / 255.0 - exact).abs();
+                max_diff = max_diff.max(diff);
+            }
+        }
+        let expected = std::f32::consts::PI * r * r;
+        assert!(
+            (sum(&coverage) / expected - 1.0).abs() < 0.01,
+            "{} {expected}",
+            sum(&coverage)
+        );
+        assert!(max_diff < 0.03, "{max_diff}");
+    }
+
+    #[test]
+    fn subpixel_offset() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('a'), 1000, vec![rect(0, 0, 1000, 500)]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let coverage = rasterize_glyph_at(&face, GlyphId(id), 16.0, 0.5).unwrap();
+        assert_eq!((coverage.width, coverage.left), (17, 0));
+        assert_eq!(coverage.get(0, 0), 128);
+        assert_eq!(coverage.get(8, 0), 255);
+        assert_eq!(coverage.get(16, 0), 128);
+        assert!((sum(&coverage) - 128.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn empty_glyph() {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some(' '), 250, vec![]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        assert_eq!(rasterize_glyph(&face, GlyphId(id), 16.0), None);
+        assert_eq!(rasterize_glyph(&face, GlyphId(0), 16.0), None);
+    }
+}
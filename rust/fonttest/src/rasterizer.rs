@@ -0,0 +1,224 @@
+//! 符号付き面積を蓄積してアンチエイリアスされたカバレッジを求めるラスタライザ
+//!
+//! 各辺が横切るピクセルに、その辺より右側の面積の変化量を書き込み、
+//! 最後に行方向の累積和を取るとピクセルごとの巻き数 (の面積平均) になる。
+
+use crate::Point;
+
+/// 曲線を直線に分割するときの許容誤差 (ピクセル)
+pub(crate) const FLATTEN_TOLERANCE: f32 = 0.02;
+
+pub struct Rasterizer {
+    width: u32,
+    height: u32,
+    /// 累積前の面積の変化量。右端の辺が最終行の後ろにはみ出す分だけ余分に持つ
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            accumulation: vec![0.0; (width * height + 2) as usize],
+        }
+    }
+
+    /// 直線を描く。座標はピクセル単位で y は下向き
+    pub fn draw_line(&mut self, p0: Point, p1: Point) {
+        if p0.y == p1.y {
+            return;
+        }
+        let (direction, p0, p1) = if p0.y < p1.y {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let width = self.width as f32;
+
+        let y_start = p0.y.max(0.0);
+        let y_end = p1.y.min(self.height as f32);
+        let mut x = p0.x + (y_start - p0.y) * dxdy;
+        let mut y = y_start;
+        while y < y_end {
+            let row = y.floor();
+            let next_y = (row + 1.0).min(y_end);
+            let next_x = x + (next_y - y) * dxdy;
+            let line_start = row as usize * self.width as usize;
+            // 画像の左右にはみ出した部分は端に寄せる
+            self.accumulate_span(
+                line_start,
+                x.clamp(0.0, width),
+                next_x.clamp(0.0, width),
+                (next_y - y) * direction,
+            );
+            x = next_x;
+            y = next_y;
+        }
+    }
+
+    /// 2 次ベジエを、誤差が `FLATTEN_TOLERANCE` 以下になるように直線に分割して描く
+    pub fn draw_quad(&mut self, p0: Point, p1: Point, p2: Point) {
+        let deviation = p0 - p1 * 2.0 + p2;
+        let deviation = (deviation.x * deviation.x + deviation.y * deviation.y).sqrt();
+        // 分割数 n での最大誤差は deviation / (8 n²)
+        let n = ((deviation / (8.0 * FLATTEN_TOLERANCE)).sqrt().ceil() as u32).max(1);
+
+        let mut previous = p0;
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let next = p0 * ((1.0 - t) * (1.0 - t)) + p1 * (2.0 * t * (1.0 - t)) + p2 * (t * t);
+            self.draw_line(previous, next);
+            previous = next;
+        }
+    }
+
+    /// 1 行の中で x0 から x1 へ進み、高さ d だけ下がる辺の寄与を書き込む
+    fn accumulate_span(&mut self, line_start: usize, x: f32, next_x: f32, d: f32) {
+        let a = &mut self.accumulation[line_start..];
+        let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+        let x0_floor = x0.floor();
+        let x0i = x0_floor as usize;
+        let x1_ceil = x1.ceil();
+        let x1i = x1_ceil as usize;
+
+        if x1i <= x0i + 1 {
+            // 1 ピクセルに収まる場合は、辺の中点より右側の割合で分ける
+            let xm = 0.5 * (x + next_x) - x0_floor;
+            a[x0i] += d - d * xm;
+            a[x0i + 1] += d * xm;
+            return;
+        }
+
+        let s = (x1 - x0).recip();
+        let x0f = x0 - x0_floor;
+        let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+        let x1f = x1 - x1_ceil + 1.0;
+        let am = 0.5 * s * x1f * x1f;
+        a[x0i] += d * a0;
+        if x1i == x0i + 2 {
+            a[x0i + 1] += d * (1.0 - a0 - am);
+        } else {
+            let a1 = s * (1.5 - x0f);
+            a[x0i + 1] += d * (a1 - a0);
+            for value in &mut a[x0i + 2..x1i - 1] {
+                *value += d * s;
+            }
+            let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+            a[x1i - 1] += d * (1.0 - a2 - am);
+        }
+        a[x1i] += d * am;
+    }
+
+    /// 累積和を取り、巻き数の絶対値を 1 で打ち切って 8 bit のアルファにする
+    ///
+    /// 巻き数が 0 でなければ塗る (nonzero) 規則になる。
+    pub fn accumulate(&self) -> Vec<u8> {
+        let mut sum = 0.0;
+        self.accumulation[..(self.width * self.height) as usize]
+            .iter()
+            .map(|a| {
+                sum += a;
+                (sum.abs().min(1.0) * 255.0 + 0.5) as u8
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(rasterizer: &mut Rasterizer, points: &[(f32, f32)]) {
+        for (i, &(x, y)) in points.iter().enumerate() {
+            let (nx, ny) = points[(i + 1) % points.len()];
+            rasterizer.draw_line(Point::new(x, y), Point::new(nx, ny));
+        }
+    }
+
+    #[test]
+    fn partial_pixels() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        polygon(
+            &mut rasterizer,
+            &[(0.5, 0.5), (0.5, 2.5), (3.25, 2.5), (3.25, 0.5)],
+        );
+        let alpha = rasterizer.accumulate();
+        assert_eq!(&alpha[0..4], &[64, 128, 128, 32]);
+        assert_eq!(&alpha[4..8], &[128, 255, 255, 64]);
+        assert_eq!(&alpha[12..16], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn diagonal_halves_pixels() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        polygon(&mut rasterizer, &[(0.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        let alpha = rasterizer.accumulate();
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = match x.cmp(&y) {
+                    std::cmp::Ordering::Less => 255,
+                    std::cmp::Ordering::Equal => 128,
+                    std::cmp::Ordering::Greater => 0,
+                };
+                assert_eq!(alpha[y * 4 + x], expected, "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn nonzero_winding() {
+        let square = [(1.0, 1.0), (1.0, 7.0), (7.0, 7.0), (7.0, 1.0)];
+        let inner = [(3.0, 3.0), (3.0, 5.0), (5.0, 5.0), (5.0, 3.0)];
+
+        // 同じ向きに重なった輪郭は塗りつぶしたまま
+        let mut rasterizer = Rasterizer::new(8, 8);
+        polygon(&mut rasterizer, &square);
+        polygon(&mut rasterizer, &inner);
+        assert_eq!(rasterizer.accumulate()[4 * 8 + 4], 255);
+
+        // 逆向きの輪郭は穴になる
+        let mut reversed = inner;
+        reversed.reverse();
+        let mut rasterizer = Rasterizer::new(8, 8);
+        polygon(&mut rasterizer, &square);
+        polygon(&mut rasterizer, &reversed);
+        let alpha = rasterizer.accumulate();
+        assert_eq!(alpha[4 * 8 + 4], 0);
+        assert_eq!(alpha[2 * 8 + 2], 255);
+    }
+
+    #[test]
+    fn clipped_outside_image() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        polygon(
+            &mut rasterizer,
+            &[(-2.0, -2.0), (-2.0, 2.0), (2.0, 2.0), (2.0, -2.0)],
+        );
+        let alpha = rasterizer.accumulate();
+        assert_eq!(&alpha[0..4], &[255, 255, 0, 0]);
+        assert_eq!(&alpha[4..8], &[255, 255, 0, 0]);
+        assert!(alpha[8..].iter().all(|&a| a == 0));
+    }
+
+    #[test]
+    fn quad_area() {
+        // 弦と曲線に囲まれた部分の面積は三角形の 2/3
+        let mut rasterizer = Rasterizer::new(16, 16);
+        let (p0, p1, p2) = (
+            Point::new(0.0, 16.0),
+            Point::new(8.0, -16.0),
+            Point::new(16.0, 16.0),
+        );
+        rasterizer.draw_quad(p0, p1, p2);
+        rasterizer.draw_line(p2, p0);
+        let area: f32 = rasterizer
+            .accumulate()
+            .iter()
+            .map(|&a| a as f32 / 255.0)
+            .sum();
+        let expected = 2.0 / 3.0 * 16.0 * 32.0 / 2.0;
+        assert!((area / expected - 1.0).abs() < 0.01, "{area} {expected}");
+    }
+}
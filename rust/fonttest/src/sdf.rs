@@ -0,0 +1,526 @@
+//! グリフの輪郭から符号付き距離場 (SDF) と多チャンネル距離場 (MSDF) を作る
+//!
+//! 距離は輪郭の内側を正とし、`range` ピクセルの幅を 0 から 255 に対応させる。
+//! 輪郭上はちょうど 127.5 になる。
+
+use bezier_converter::{
+    path::{FillRule, Segment},
+    scalar::Point,
+};
+use image::{GrayImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage};
+use ttf_parser::{Face, GlyphId};
+
+use crate::raster::{Bounds, Outline};
+
+type Vec2 = Point<f32>;
+
+/// 角とみなす折れ角の閾値 (外積の大きさ)
+const CORNER_THRESHOLD: f32 = 0.141_12;
+/// 最近点を探すときの Newton 法の初期値の数
+const NEAREST_STARTS: usize = 4;
+const NEAREST_ITERATIONS: usize = 4;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SdfOptions {
+    /// 0 から 255 に対応させる距離の幅 (ピクセル)
+    pub range: f32,
+    /// グリフの周りに足す余白 (ピクセル)
+    pub padding: u32,
+}
+
+impl Default for SdfOptions {
+    fn default() -> Self {
+        Self {
+            range: 4.0,
+            padding: 2,
+        }
+    }
+}
+
+/// 距離場の画像と、その位置
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceField<P: Pixel<Subpixel = u8>> {
+    /// 原点から画像左端までのピクセル数
+    pub left: i32,
+    /// ベースラインから画像上端までのピクセル数 (上向きが正)
+    pub top: i32,
+    pub image: ImageBuffer<P, Vec<u8>>,
+}
+
+/// 1 チャンネルの符号付き距離場を作る。輪郭のないグリフは `None`
+pub fn sdf_glyph(
+    face: &Face,
+    glyph_id: GlyphId,
+    size_px: f32,
+    options: &SdfOptions,
+) -> Option<DistanceField<Luma<u8>>> {
+    let (outline, bounds) = Outline::glyph(face, glyph_id, size_px)?;
+    let field = Field::new(&outline);
+    let bounds = bounds.pad(options.padding);
+
+    let image = GrayImage::from_fn(bounds.width, bounds.height, |x, y| {
+        let p = pixel_center(bounds, x, y);
+        Luma([encode(field.distance(p), options.range)])
+    });
+    Some(DistanceField {
+        left: bounds.left,
+        top: bounds.top,
+        image,
+    })
+}
+
+/// 角の両側の辺を別のチャンネルに割り当てた多チャンネル距離場を作る
+///
+/// 3 チャンネルの中央値を取ると、角の鋭さを保ったまま輪郭を復元できる。
+pub fn msdf_glyph(
+    face: &Face,
+    glyph_id: GlyphId,
+    size_px: f32,
+    options: &SdfOptions,
+) -> Option<DistanceField<Rgb<u8>>> {
+    let (outline, bounds) = Outline::glyph(face, glyph_id, size_px)?;
+    let field = Field::new(&outline);
+    let bounds = bounds.pad(options.padding);
+
+    let image = RgbImage::from_fn(bounds.width, bounds.height, |x, y| {
+        let p = pixel_center(bounds, x, y);
+        let channels = field.channels(p);
+        Rgb(channels.map(|d| encode(d, options.range)))
+    });
+    Some(DistanceField {
+        left: bounds.left,
+        top: bounds.top,
+        image,
+    })
+}
+
+fn pixel_center(bounds: Bounds, x: u32, y: u32) -> Vec2 {
+    Vec2::new(
+        bounds.left as f32 + x as f32 + 0.5,
+        bounds.top as f32 - y as f32 - 0.5,
+    )
+}
+
+fn encode(distance: f32, range: f32) -> u8 {
+    ((distance / range + 0.5).clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// 直線か 2 次ベジエの辺
+#[derive(Clone, Copy, Debug)]
+enum Edge {
+    Line(Vec2, Vec2),
+    Quad(Vec2, Vec2, Vec2),
+}
+
+/// 辺までの距離。`dot` は最近点での接線と点の方向の内積の絶対値で、
+/// 角を共有する辺どうしで距離が等しいときに小さい (より直交している) 方を選ぶ
+#[derive(Clone, Copy, Debug)]
+struct SignedDistance {
+    distance: f32,
+    dot: f32,
+    t: f32,
+}
+
+impl SignedDistance {
+    const INFINITE: SignedDistance = SignedDistance {
+        distance: f32::INFINITY,
+        dot: 1.0,
+        t: 0.0,
+    };
+
+    fn is_closer(&self, other: &SignedDistance) -> bool {
+        let (a, b) = (self.distance.abs(), other.distance.abs());
+        a < b || (a == b && self.dot < other.dot)
+    }
+}
+
+impl Edge {
+    fn point(&self, t: f32) -> Vec2 {
+        match *self {
+            Edge::Line(p0, p1) => p0.lerp(p1, t),
+            Edge::Quad(p0, p1, p2) => p0.lerp(p1, t).lerp(p1.lerp(p2, t), t),
+        }
+    }
+
+    fn direction(&self, t: f32) -> Vec2 {
+        match *self {
+            Edge::Line(p0, p1) => p1 - p0,
+            Edge::Quad(p0, p1, p2) => {
+                let d = (p1 - p0).lerp(p2 - p1, t);
+                // 制御点が端点に重なっているときは弦の向きを使う
+                if d == Vec2::ZERO { p2 - p0 } else { d }
+            }
+        }
+    }
+
+    /// 3 等分する
+    fn split_thirds(&self) -> [Edge; 3] {
+        match *self {
+            Edge::Line(p0, p1) => {
+                let (a, b) = (p0.lerp(p1, 1.0 / 3.0), p0.lerp(p1, 2.0 / 3.0));
+                [Edge::Line(p0, a), Edge::Line(a, b), Edge::Line(b, p1)]
+            }
+            Edge::Quad(p0, p1, p2) => {
+                let (a, b) = (self.point(1.0 / 3.0), self.point(2.0 / 3.0));
+                let c0 = p0.lerp(p1, 1.0 / 3.0);
+                let c1 = p0.lerp(p1, 2.0 / 3.0).lerp(p1.lerp(p2, 2.0 / 3.0), 0.5);
+                let c2 = p1.lerp(p2, 2.0 / 3.0);
+                [
+                    Edge::Quad(p0, c0, a),
+                    Edge::Quad(a, c1, b),
+                    Edge::Quad(b, c2, p2),
+                ]
+            }
+        }
+    }
+
+    /// 符号付き距離。進行方向の左側を正とする
+    fn signed_distance(&self, p: Vec2) -> SignedDistance {
+        let t = match *self {
+            Edge::Line(p0, p1) => {
+                let d = p1 - p0;
+                ((p - p0).dot(d) / d.dot(d)).clamp(0.0, 1.0)
+            }
+            Edge::Quad(..) => self.nearest(p),
+        };
+        let q = self.point(t);
+        let direction = self.direction(t).normalize_or_zero();
+        let offset = p - q;
+        let distance = offset.length();
+        let sign = if direction.perp_dot(offset) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        SignedDistance {
+            distance: sign * distance,
+            dot: direction.dot(offset.normalize_or_zero()).abs(),
+            t,
+        }
+    }
+
+    /// 2 次ベジエ上で点に最も近い点のパラメータ
+    fn nearest(&self, p: Vec2) -> f32 {
+        let Edge::Quad(p0, p1, p2) = *self else {
+            unreachable!()
+        };
+        let a = p1 - p0;
+        let b = p2 - p1 * 2.0 + p0;
+        let w = p0 - p;
+        // (B(t) - p)・B'(t) / 2 = 0 の 3 次方程式
+        let c3 = b.dot(b);
+        let c2 = 3.0 * a.dot(b);
+        let c1 = 2.0 * a.dot(a) + w.dot(b);
+        let c0 = w.dot(a);
+
+        let mut best = (f32::INFINITY, 0.0);
+        for i in 0..=NEAREST_STARTS {
+            let mut t = i as f32 / NEAREST_STARTS as f32;
+            for _ in 0..NEAREST_ITERATIONS {
+                let f = ((c3 * t + c2) * t + c1) * t + c0;
+                let df = (3.0 * c3 * t + 2.0 * c2) * t + c1;
+                if df == 0.0 {
+                    break;
+                }
+                t = (t - f / df).clamp(0.0, 1.0);
+            }
+            let distance = (self.point(t) - p).length_squared();
+            if distance < best.0 {
+                best = (distance, t);
+            }
+        }
+        best.1
+    }
+
+    /// 最近点が端点のとき、接線を延長した直線までの距離に置き換える
+    fn pseudo_distance(&self, p: Vec2, sd: SignedDistance) -> f32 {
+        let (q, direction) = if sd.t == 0.0 {
+            (self.point(0.0), self.direction(0.0).normalize_or_zero())
+        } else if sd.t == 1.0 {
+            (self.point(1.0), self.direction(1.0).normalize_or_zero())
+        } else {
+            return sd.distance;
+        };
+        let offset = p - q;
+        let along = offset.dot(direction);
+        if (sd.t == 0.0 && along < 0.0) || (sd.t == 1.0 && along > 0.0) {
+            let pseudo = direction.perp_dot(offset);
+            if pseudo.abs() <= sd.distance.abs() {
+                return pseudo;
+            }
+        }
+        sd.distance
+    }
+}
+
+/// 距離を求めるために、輪郭を色付きの辺に分けたもの
+struct Field<'a> {
+    outline: &'a Outline,
+    edges: Vec<(Edge, u8)>,
+    /// 辺の左側が内側なら 1、右側なら -1
+    orientation: f32,
+}
+
+impl<'a> Field<'a> {
+    fn new(outline: &'a Outline) -> Self {
+        let mut edges = Vec::new();
+        let mut area = 0.0;
+        for contour in &outline.path.to_quadratic().contours {
+            let contour: Vec<Edge> = contour
+                .segments
+                .iter()
+                .filter_map(|segment| match segment {
+                    Segment::Line(l) => {
+                        Some(Edge::Line(Vec2::new(l.x0, l.y0), Vec2::new(l.x1, l.y1)))
+                    }
+                    Segment::Quadratic(q) => Some(Edge::Quad(
+                        Vec2::new(q.x0, q.y0),
+                        Vec2::new(q.cx0, q.cy0),
+                        Vec2::new(q.x1, q.y1),
+                    )),
+                    Segment::Cubic(_) => None,
+                })
+                .filter(|edge| edge.point(0.0) != edge.point(1.0))
+                .collect();
+            for edge in &contour {
+                let (p0, p1) = (edge.point(0.0), edge.point(1.0));
+                area += p0.perp_dot(p1);
+            }
+            edges.extend(color_edges(contour));
+        }
+        Self {
+            outline,
+            edges,
+            orientation: if area < 0.0 { -1.0 } else { 1.0 },
+        }
+    }
+
+    fn is_inside(&self, p: Vec2) -> bool {
+        self.outline.path.contains(p, FillRule::NonZero)
+    }
+
+    /// 最も近い辺までの距離 (内側が正)
+    fn distance(&self, p: Vec2) -> f32 {
+        let distance = self
+            .edges
+            .iter()
+            .map(|(edge, _)| edge.signed_distance(p).distance.abs())
+            .fold(f32::INFINITY, f32::min);
+        if self.is_inside(p) {
+            distance
+        } else {
+            -distance
+        }
+    }
+
+    /// チャンネルごとに、その色を持つ辺までの擬似距離 (内側が正)
+    fn channels(&self, p: Vec2) -> [f32; 3] {
+        let mut channels = [-f32::INFINITY; 3];
+        for (channel, bit) in [RED, GREEN, BLUE].into_iter().enumerate() {
+            let mut best = SignedDistance::INFINITE;
+            let mut best_edge = None;
+            for (edge, color) in &self.edges {
+                if color & bit == 0 {
+                    continue;
+                }
+                let sd = edge.signed_distance(p);
+                if sd.is_closer(&best) {
+                    best = sd;
+                    best_edge = Some(edge);
+                }
+            }
+            if let Some(edge) = best_edge {
+                channels[channel] = self.orientation * edge.pseudo_distance(p, best);
+            }
+        }
+
+        // 中央値の符号が実際の内外と食い違う点は、単一チャンネルの距離で埋める
+        let [r, g, b] = channels;
+        if (median(r, g, b) > 0.0) != self.is_inside(p) {
+            return [self.distance(p); 3];
+        }
+        channels
+    }
+}
+
+/// 角の前後で色が変わるように辺へ色を割り当てる
+///
+/// 隣り合う色は必ず 1 チャンネルを共有し、角ではどちらか一方にしか含まれない
+/// チャンネルができる。
+fn color_edges(mut edges: Vec<Edge>) -> Vec<(Edge, u8)> {
+    let n = edges.len();
+    let corners: Vec<usize> = (0..n)
+        .filter(|&i| {
+            let a = edges[(i + n - 1) % n].direction(1.0).normalize_or_zero();
+            let b = edges[i].direction(0.0).normalize_or_zero();
+            a.dot(b) <= 0.0 || a.perp_dot(b).abs() > CORNER_THRESHOLD
+        })
+        .collect();
+
+    match corners.len() {
+        0 => edges.into_iter().map(|edge| (edge, WHITE)).collect(),
+        1 => {
+            // 角が 1 つしかない輪郭は、角から 3 つに分けて塗り分ける
+            edges.rotate_left(corners[0]);
+            if edges.len() < 3 {
+                edges = edges.iter().flat_map(|edge| edge.split_thirds()).collect();
+            }
+            let m = edges.len();
+            edges
+                .into_iter()
+                .enumerate()
+                .map(|(i, edge)| (edge, [MAGENTA, WHITE, YELLOW][3 * i / m]))
+                .collect()
+        }
+        k => {
+            let palette = [CYAN, MAGENTA, YELLOW];
+            let mut colors: Vec<u8> = (0..k).map(|j| palette[j % 3]).collect();
+            if colors[k - 1] == colors[0] {
+                // 最後と最初の区間が同じ色にならないよう、残りの色にする
+                colors[k - 1] = palette
+                    .into_iter()
+                    .find(|&c| c != colors[0] && c != colors[k - 2])
+                    .unwrap();
+            }
+            edges.rotate_left(corners[0]);
+            let mut spline = 0;
+            let mut result = Vec::with_capacity(n);
+            for (i, edge) in edges.into_iter().enumerate() {
+                let index = (i + corners[0]) % n;
+                if i > 0 && corners.contains(&index) {
+                    spline += 1;
+                }
+                result.push((edge, colors[spline]));
+            }
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_font::{Contour, FontBuilder, circle, rect};
+
+    fn face_with(contours: Vec<Contour>) -> (Vec<u8>, u16) {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(None, 1000, contours);
+        (font.build(), id)
+    }
+
+    #[test]
+    fn rectangle_sdf() {
+        let (data, id) = face_with(vec![rect(0, 0, 1000, 500)]);
+        let face = Face::parse(&data, 0).unwrap();
+        let field = sdf_glyph(&face, GlyphId(id), 16.0, &SdfOptions::default()).unwrap();
+
+        assert_eq!((field.left, field.top), (-2, 10));
+        assert_eq!(field.image.dimensions(), (20, 12));
+        // 左辺から内側に 0.5、外側に 1.5 ピクセル
+        assert_eq!(field.image.get_pixel(2, 6).0, [159]);
+        assert_eq!(field.image.get_pixel(0, 6).0, [32]);
+        // 中心は range を超えるので最大値
+        assert_eq!(field.image.get_pixel(10, 6).0, [255]);
+    }
+
+    #[test]
+    fn hole_is_negative() {
+        let mut inner = rect(250, 100, 750, 400);
+        inner.reverse();
+        let (data, id) = face_with(vec![rect(0, 0, 1000, 500), inner]);
+        let face = Face::parse(&data, 0).unwrap();
+        let field = sdf_glyph(&face, GlyphId(id), 16.0, &SdfOptions::default()).unwrap();
+
+        // 穴の中は外側と同じく負になる
+        assert!(field.image.get_pixel(10, 6).0[0] < 16);
+        assert!(field.image.get_pixel(3, 6).0[0] > 128);
+    }
+
+    #[test]
+    fn rectangle_msdf_keeps_corners() {
+        let (data, id) = face_with(vec![rect(0, 0, 1000, 500)]);
+        let face = Face::parse(&data, 0).unwrap();
+        let options = SdfOptions::default();
+        let sdf = sdf_glyph(&face, GlyphId(id), 16.0, &options).unwrap();
+        let msdf = msdf_glyph(&face, GlyphId(id), 16.0, &options).unwrap();
+        assert_eq!(sdf.image.dimensions(), msdf.image.dimensions());
+
+        let median_of = |x, y| {
+            let [r, g, b] = msdf.image.get_pixel(x, y).0;
+            median(r as f32, g as f32, b as f32)
+        };
+        // 内外の判定は SDF と一致する
+        for (x, y, p) in sdf.image.enumerate_pixels() {
+            assert_eq!(median_of(x, y) > 127.5, p.0[0] > 127, "({x}, {y})");
+        }
+        // 左下の角の斜め外側では、SDF は角までの距離だが MSDF は辺までの距離になる
+        assert_eq!(sdf.image.get_pixel(1, 10).0, [82]);
+        assert_eq!(median_of(1, 10), 96.0);
+        assert_eq!(sdf.image.get_pixel(0, 11).0, [0]);
+        assert_eq!(median_of(0, 11), encode(-1.5, options.range) as f32);
+    }
+
+    #[test]
+    fn smooth_contour_is_white() {
+        let (data, id) = face_with(vec![circle(500, 500, 500, false)]);
+        let face = Face::parse(&data, 0).unwrap();
+        let options = SdfOptions::default();
+        let sdf = sdf_glyph(&face, GlyphId(id), 16.0, &options).unwrap();
+        let msdf = msdf_glyph(&face, GlyphId(id), 16.0, &options).unwrap();
+
+        for (x, y, p) in msdf.image.enumerate_pixels() {
+            let [r, g, b] = p.0;
+            assert!(r == g && g == b);
+            assert!(
+                r.abs_diff(sdf.image.get_pixel(x, y).0[0]) <= 1,
+                "({x}, {y})"
+            );
+        }
+    }
+
+    #[test]
+    fn edge_colors() {
+        let square = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)];
+        let edges: Vec<Edge> = (0..4)
+            .map(|i| {
+                let (a, b) = (square[i], square[(i + 1) % 4]);
+                Edge::Line(Vec2::new(a.0, a.1), Vec2::new(b.0, b.1))
+            })
+            .collect();
+        let colors: Vec<u8> = color_edges(edges).into_iter().map(|(_, c)| c).collect();
+        for i in 0..4 {
+            let (a, b) = (colors[i], colors[(i + 1) % 4]);
+            // 角の両側の辺は 1 チャンネルだけを共有する
+            assert_eq!((a & b).count_ones(), 1, "{colors:?}");
+        }
+
+        // 角が 1 つの輪郭は 3 色に分けられる
+        let teardrop = vec![
+            Edge::Quad(
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 2.0),
+                Vec2::new(0.0, 2.0),
+            ),
+            Edge::Quad(
+                Vec2::new(0.0, 2.0),
+                Vec2::new(-2.0, 2.0),
+                Vec2::new(0.0, 0.0),
+            ),
+        ];
+        let colors: Vec<u8> = color_edges(teardrop).into_iter().map(|(_, c)| c).collect();
+        assert_eq!(colors.len(), 6);
+        assert!(colors.contains(&MAGENTA) && colors.contains(&WHITE) && colors.contains(&YELLOW));
+    }
+}
@@ -0,0 +1,508 @@
+//! ペン位置の端数を 1/4 ピクセル単位で描き分け、描いたグリフを位置ごとに使い回す
+//!
+//! ヒンティングはしないので、端数を捨てると文字間が不揃いになる。
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+
+use image::{Rgb, RgbImage};
+use ttf_parser::{Face, GlyphId};
+
+use crate::{
+    Coverage,
+    gamma::blend_coverage,
+    layout::{Layout, PositionedGlyph},
+    rasterize_glyph_at,
+};
+
+/// 1 ピクセルを横に何段階に分けて描くか
+pub const SUBPIXEL_STEPS: u8 = 4;
+
+/// x 座標を、整数のピクセル位置と端数の段階 (0..SUBPIXEL_STEPS) に分ける
+pub fn quantize(x: f32) -> (i32, u8) {
+    let steps = SUBPIXEL_STEPS as i32;
+    let position = (x * steps as f32).round() as i32;
+    (position.div_euclid(steps), position.rem_euclid(steps) as u8)
+}
+
+/// キャッシュの既定の上限 (バイト)
+pub const DEFAULT_BUDGET: usize = 16 << 20;
+
+/// フェイスを区別する値
+///
+/// フォントのデータ全体から求めるので、フェイスごとに 1 度だけ求めて使い回す。
+/// 同じフォントでも、コレクション内の位置や可変軸の座標が違えば別のフェイスになる。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FaceId {
+    data: u64,
+    /// データの長さ・テーブルの一覧・可変軸の座標。渡されたフェイスとの照合に使う
+    shape: u64,
+}
+
+impl FaceId {
+    pub fn of(face: &Face) -> Self {
+        let mut hasher = DefaultHasher::new();
+        face.raw_face().data.hash(&mut hasher);
+        Self {
+            data: hasher.finish(),
+            shape: Self::shape(face),
+        }
+    }
+
+    fn shape(face: &Face) -> u64 {
+        let raw = face.raw_face();
+        let mut hasher = DefaultHasher::new();
+        raw.data.len().hash(&mut hasher);
+        for record in raw.table_records {
+            (record.tag.0, record.offset, record.length).hash(&mut hasher);
+        }
+        for coordinate in face.variation_coordinates() {
+            coordinate.get().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// `face` から求めた値と食い違わないか。データ全体は比べない
+    fn matches(&self, face: &Face) -> bool {
+        self.shape == Self::shape(face)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphKey {
+    pub face: FaceId,
+    pub glyph_id: GlyphId,
+    /// em の大きさ (ピクセル)
+    pub size_px: f32,
+    /// 原点の端数 (`quantize` の 2 つ目の値)
+    pub subpixel: u8,
+}
+
+impl GlyphKey {
+    fn bits(&self) -> (FaceId, u16, u32, u8) {
+        (
+            self.face,
+            self.glyph_id.0,
+            self.size_px.to_bits(),
+            self.subpixel,
+        )
+    }
+
+    fn rasterize(&self, face: &Face) -> Option<Coverage> {
+        let offset = self.subpixel as f32 / SUBPIXEL_STEPS as f32;
+        rasterize_glyph_at(face, self.glyph_id, self.size_px, offset)
+    }
+}
+
+impl PartialEq for GlyphKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for GlyphKey {}
+
+impl Hash for GlyphKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+/// キャッシュの利用状況
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    /// 描いたグリフの数
+    pub misses: usize,
+    pub evictions: usize,
+}
+
+struct Entry {
+    /// 輪郭のないグリフは `None`
+    coverage: Option<Arc<Coverage>>,
+    /// 最後に使った順番
+    used_at: u64,
+}
+
+impl Entry {
+    /// おおよそのメモリ使用量
+    fn cost(&self) -> usize {
+        size_of::<GlyphKey>()
+            + size_of::<Entry>()
+            + self
+                .coverage
+                .as_ref()
+                .map_or(0, |c| size_of::<Coverage>() + c.data.len())
+    }
+}
+
+/// フェイス・グリフ・大きさ・端数の組ごとにカバレッジを覚えておく
+///
+/// 使用量が上限を超えたら、最も長く使っていないものから捨てる。
+/// カバレッジは `Arc` で返すので、捨てられた後も手元の分はそのまま使える。
+pub struct GlyphCache {
+    budget: usize,
+    used: usize,
+    clock: u64,
+    glyphs: HashMap<GlyphKey, Entry>,
+    /// 最後に使った順番からキーを引く
+    order: BTreeMap<u64, GlyphKey>,
+    stats: CacheStats,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET)
+    }
+}
+
+impl GlyphCache {
+    /// 使用量の上限 `budget` (バイト) のキャッシュ
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            clock: 0,
+            glyphs: HashMap::new(),
+            order: BTreeMap::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// 覚えているグリフのおおよそのメモリ使用量 (バイト)
+    pub fn memory_used(&self) -> usize {
+        self.used
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// 覚えているグリフの数
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// `key.face` は `FaceId::of(face)` の値。まだ描いていなければ描いて覚える
+    pub fn get(&mut self, face: &Face, key: GlyphKey) -> Option<Arc<Coverage>> {
+        assert!(
+            key.face.matches(face),
+            "glyph key was made for a different face"
+        );
+        if let Some(coverage) = self.touch(&key) {
+            return coverage;
+        }
+        let coverage = key.rasterize(face).map(Arc::new);
+        self.insert(key, coverage.clone());
+        coverage
+    }
+
+    /// まだ覚えていないグリフをまとめて並列に描く
+    ///
+    /// キーの `face` に当たるフェイスを `faces` から探す。見つからなければ panic する。
+    pub fn prefetch(&mut self, faces: &[Face], keys: impl IntoIterator<Item = GlyphKey>) {
+        let mut missing: Vec<GlyphKey> = keys
+            .into_iter()
+            .filter(|key| !self.glyphs.contains_key(key))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let mut seen = HashSet::new();
+        missing.retain(|key| seen.insert(*key));
+        let faces: HashMap<FaceId, &Face> = faces.iter().map(|f| (FaceId::of(f), f)).collect();
+        let rendered: Vec<_> = missing
+            .into_par_iter()
+            .map(|key| {
+                let face = faces
+                    .get(&key.face)
+                    .expect("glyph key was made for a face that is not in the list");
+                (key, key.rasterize(face).map(Arc::new))
+            })
+            .collect();
+        for (key, coverage) in rendered {
+            self.insert(key, coverage);
+        }
+    }
+
+    /// 覚えていれば使った順番を更新して返す
+    fn touch(&mut self, key: &GlyphKey) -> Option<Option<Arc<Coverage>>> {
+        let entry = self.glyphs.get_mut(key)?;
+        self.clock += 1;
+        self.order.remove(&entry.used_at);
+        entry.used_at = self.clock;
+        self.order.insert(self.clock, *key);
+        self.stats.hits += 1;
+        Some(entry.coverage.clone())
+    }
+
+    fn insert(&mut self, key: GlyphKey, coverage: Option<Arc<Coverage>>) {
+        self.clock += 1;
+        let entry = Entry {
+            coverage,
+            used_at: self.clock,
+        };
+        self.used += entry.cost();
+        self.order.insert(self.clock, key);
+        self.glyphs.insert(key, entry);
+        self.stats.misses += 1;
+        // 上限より大きいグリフ 1 つだけなら残しておく
+        while self.used > self.budget && self.glyphs.len() > 1 {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.glyphs.remove(&oldest) {
+                self.used -= entry.cost();
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+/// レイアウトした文字列を `background` の上に `foreground` で描く
+///
+/// x 方向は 1/4 ピクセル単位で位置を合わせ、合成は線形の輝度で行う。
+/// キャッシュにないグリフは先にまとめて並列に描いておく。
+/// フェイスは並び順ではなく `FaceId` で区別するので、違うフォールバックの並びで同じキャッシュを使ってよい。
+pub fn render_layout_rgb(
+    faces: &[Face],
+    layout: &Layout,
+    cache: &mut GlyphCache,
+    foreground: Rgb<u8>,
+    background: Rgb<u8>,
+) -> RgbImage {
+    let mut image = RgbImage::from_pixel(
+        layout.width.ceil().max(1.0) as u32,
+        layout.height.ceil().max(1.0) as u32,
+        background,
+    );
+    let ids: Vec<FaceId> = faces.iter().map(FaceId::of).collect();
+    let key = |glyph: &PositionedGlyph| {
+        let (x, subpixel) = quantize(glyph.x);
+        let key = GlyphKey {
+            face: ids[glyph.face],
+            glyph_id: glyph.glyph_id,
+            size_px: layout.size_px,
+            subpixel,
+        };
+        (x, key)
+    };
+    let glyphs = layout.lines.iter().flat_map(|line| &line.glyphs);
+    cache.prefetch(faces, glyphs.map(|glyph| key(glyph).1));
+    for line in &layout.lines {
+        let y = line.baseline.round() as i32;
+        for glyph in &line.glyphs {
+            let (x, key) = key(glyph);
+            if let Some(coverage) = cache.get(&faces[glyph.face], key) {
+                blend_coverage(&mut image, &coverage, x, y, foreground);
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layout::{LayoutOptions, layout},
+        test_font::{FontBuilder, rect},
+    };
+
+    #[test]
+    fn quantize_positions() {
+        assert_eq!(quantize(2.0), (2, 0));
+        assert_eq!(quantize(2.3), (2, 1));
+        assert_eq!(quantize(2.5), (2, 2));
+        assert_eq!(quantize(2.9), (3, 0));
+        assert_eq!(quantize(-0.1), (0, 0));
+        assert_eq!(quantize(-0.25), (-1, 3));
+    }
+
+    /// 幅 1000、高さ 500 の長方形を 'a' に持つフォント
+    fn bar_font() -> (Vec<u8>, u16) {
+        let mut font = FontBuilder::new(1000);
+        let id = font.glyph(Some('a'), 1000, vec![rect(0, 0, 1000, 500)]);
+        (font.build(), id)
+    }
+
+    fn key(face: &Face, glyph_id: u16, size_px: f32, subpixel: u8) -> GlyphKey {
+        GlyphKey {
+            face: FaceId::of(face),
+            glyph_id: GlyphId(glyph_id),
+            size_px,
+            subpixel,
+        }
+    }
+
+    #[test]
+    fn variants_are_cached() {
+        let (data, id) = bar_font();
+        let face = Face::parse(&data, 0).unwrap();
+
+        let mut cache = GlyphCache::default();
+        let first = cache.get(&face, key(&face, id, 16.0, 0)).unwrap();
+        assert_eq!(first.width, 16);
+        let again = cache.get(&face, key(&face, id, 16.0, 0)).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(cache.len(), 1);
+        let shifted = cache.get(&face, key(&face, id, 16.0, 1)).unwrap();
+        assert_eq!(shifted.width, 17);
+        assert_eq!(shifted.get(0, 0), 191);
+        assert_eq!(shifted.get(16, 0), 64);
+        assert_eq!(cache.get(&face, key(&face, id, 8.0, 0)).unwrap().width, 8);
+        assert_eq!(cache.get(&face, key(&face, 0, 16.0, 0)), None);
+        assert_eq!(cache.get(&face, key(&face, 0, 16.0, 0)), None);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 4,
+                evictions: 0
+            }
+        );
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let (data, id) = bar_font();
+        let face = Face::parse(&data, 0).unwrap();
+
+        // 16x8 のカバレッジ 2 つ分だけ入る
+        let one = Entry {
+            coverage: Some(Arc::new(
+                rasterize_glyph_at(&face, GlyphId(id), 16.0, 0.0).unwrap(),
+            )),
+            used_at: 0,
+        }
+        .cost();
+        let mut cache = GlyphCache::new(one * 2);
+        // 同じフェイスに別の ID を付けて、同じ大きさのグリフを作る
+        let on = |data| GlyphKey {
+            face: FaceId {
+                data,
+                ..FaceId::of(&face)
+            },
+            ..key(&face, id, 16.0, 0)
+        };
+        let a = cache.get(&face, on(0)).unwrap();
+        cache.get(&face, on(1)).unwrap();
+        assert_eq!(cache.memory_used(), one * 2);
+        // 0 番を使い直したので、次に追い出されるのは 1 番
+        cache.get(&face, on(0)).unwrap();
+        let b = cache.get(&face, on(2)).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.memory_used(), one * 2);
+        cache.get(&face, on(0)).unwrap();
+        assert_eq!(cache.stats().misses, 3);
+        cache.get(&face, on(1)).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+        // 追い出された後も手元の分は使える
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert_eq!((a.width, b.width), (16, 16));
+
+        // 上限より大きいグリフも 1 つだけなら残す
+        let mut tiny = GlyphCache::new(1);
+        tiny.get(&face, key(&face, id, 64.0, 0)).unwrap();
+        tiny.get(&face, key(&face, id, 64.0, 0)).unwrap();
+        assert_eq!(tiny.len(), 1);
+        assert_eq!(tiny.stats().misses, 1);
+    }
+
+    #[test]
+    fn prefetch_rasterizes_each_glyph_once() {
+        let (data, id) = bar_font();
+        let faces = [Face::parse(&data, 0).unwrap()];
+        let keys: Vec<_> = (0..64)
+            .map(|i| key(&faces[0], id, 8.0 + (i % 16) as f32, (i % 4) as u8))
+            .collect();
+        let mut cache = GlyphCache::default();
+        cache.prefetch(&faces, keys.iter().copied());
+        cache.prefetch(&faces, keys.iter().copied());
+        assert_eq!(cache.len(), 16);
+        assert_eq!(cache.stats().misses, 16);
+        for key in keys {
+            let cached = cache.get(&faces[0], key).unwrap();
+            assert_eq!(*cached, key.rasterize(&faces[0]).unwrap());
+        }
+        assert_eq!(cache.stats().misses, 16);
+    }
+
+    /// 同じキャッシュを別のフェイスで使い回しても、フェイスの並び順で取り違えない
+    #[test]
+    fn faces_are_told_apart() {
+        let wide = bar_font().0;
+        let mut font = FontBuilder::new(1000);
+        font.glyph(Some('a'), 1000, vec![rect(0, 0, 500, 1000)]);
+        let tall = font.build();
+        let wide = Face::parse(&wide, 0).unwrap();
+        let tall = Face::parse(&tall, 0).unwrap();
+        assert_ne!(FaceId::of(&wide), FaceId::of(&tall));
+
+        let options = LayoutOptions {
+            size_px: 16.0,
+            ..LayoutOptions::default()
+        };
+        let black = Rgb([0, 0, 0]);
+        let white = Rgb([255, 255, 255]);
+        let mut cache = GlyphCache::default();
+        let mut images = vec![];
+        for face in [wide, tall] {
+            let layout = layout(&face, "a", &options);
+            let faces = [face];
+            let image = render_layout_rgb(&faces, &layout, &mut cache, black, white);
+            let fresh =
+                render_layout_rgb(&faces, &layout, &mut GlyphCache::default(), black, white);
+            assert_eq!(image, fresh);
+            images.push(image);
+        }
+        assert_ne!(images[0], images[1]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[test]
+    fn render_at_fractional_positions() {
+        let mut font = FontBuilder::new(1000);
+        font.glyph(Some('i'), 250, vec![rect(0, 0, 100, 500)]);
+        let data = font.build();
+        let face = Face::parse(&data, 0).unwrap();
+
+        // 送り幅 2.5 ピクセルなので、2 つ目の i は半ピクセルずれる
+        let options = LayoutOptions {
+            size_px: 10.0,
+            ..LayoutOptions::default()
+        };
+        let layout = layout(&face, "ii", &options);
+        let mut cache = GlyphCache::default();
+        let black = Rgb([0, 0, 0]);
+        let white = Rgb([255, 255, 255]);
+        let faces = [face];
+        let image = render_layout_rgb(&faces, &layout, &mut cache, black, white);
+        assert_eq!(cache.len(), 2);
+        // 2 度目はすべてキャッシュから描く
+        let again = render_layout_rgb(&faces, &layout, &mut cache, black, white);
+        assert_eq!(again, image);
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(image.get_pixel(0, 5), &black);
+        assert_eq!(image.get_pixel(1, 5), &white);
+        assert_eq!(image.get_pixel(2, 5), &Rgb([187, 187, 187]));
+        assert_eq!(image.get_pixel(3, 5), &Rgb([187, 187, 187]));
+        assert_eq!(image.get_pixel(2, 0), &white);
+    }
+}
@@ -0,0 +1,583 @@
+//! テスト用に最小限の TrueType フォントを組み立てる
+
+/// 輪郭の点 (x, y, 曲線上の点かどうか)
+pub(crate) type Contour = Vec<(i16, i16, bool)>;
+
+struct Glyph {
+    advance: u16,
+    contours: Vec<Contour>,
+}
+
+pub(crate) struct FontBuilder {
+    units_per_em: u16,
+    ascender: i16,
+    descender: i16,
+    glyphs: Vec<Glyph>,
+    cmap: Vec<(char, u16)>,
+    tables: Vec<([u8; 4], Vec<u8>)>,
+}
+
+impl FontBuilder {
+    /// 0 番に輪郭のない .notdef を持つフォント
+    pub(crate) fn new(units_per_em: u16) -> Self {
+        let em = units_per_em as i32;
+        Self {
+            units_per_em,
+            ascender: (em * 4 / 5) as i16,
+            descender: (-em / 5) as i16,
+            glyphs: vec![Glyph {
+                advance: units_per_em / 2,
+                contours: vec![],
+            }],
+            cmap: vec![],
+            tables: vec![],
+        }
+    }
+
+    /// グリフを追加してグリフ ID を返す
+    pub(crate) fn glyph(&mut self, c: Option<char>, advance: u16, contours: Vec<Contour>) -> u16 {
+        let id = self.glyphs.len() as u16;
+        self.glyphs.push(Glyph { advance, contours });
+        if let Some(c) = c {
+            self.cmap.push((c, id));
+        }
+        id
+    }
+
+    /// 任意のテーブルを追加する
+    pub(crate) fn table(&mut self, tag: &[u8; 4], data: Vec<u8>) -> &mut Self {
+        self.tables.push((*tag, data));
+        self
+    }
+
+    pub(crate) fn build(&self) -> Vec<u8> {
+        sfnt(&self.tables())
+    }
+
+    fn tables(&self) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut tables = self.tables.clone();
+        let (glyf, loca, bbox) = self.glyf();
+        tables.push((*b"head", self.head(bbox)));
+        tables.push((*b"hhea", self.hhea()));
+        tables.push((*b"maxp", self.maxp()));
+        tables.push((*b"hmtx", self.hmtx()));
+        tables.push((*b"cmap", self.cmap()));
+        tables.push((*b"loca", loca));
+        tables.push((*b"glyf", glyf));
+        tables.sort_by_key(|(tag, _)| *tag);
+        tables
+    }
+
+    fn head(&self, bbox: [i16; 4]) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.u32(0x0001_0000);
+        w.u32(0x0001_0000);
+        w.u32(0);
+        w.u32(0x5F0F_3CF5);
+        w.u16(0);
+        w.u16(self.units_per_em);
+        w.u64(0);
+        w.u64(0);
+        for v in bbox {
+            w.i16(v);
+        }
+        w.u16(0);
+        w.u16(8);
+        w.i16(2);
+        // loca は 32 bit
+        w.i16(1);
+        w.i16(0);
+        w.0
+    }
+
+    fn hhea(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.u32(0x0001_0000);
+        w.i16(self.ascender);
+        w.i16(self.descender);
+        w.i16(0);
+        w.u16(self.glyphs.iter().map(|g| g.advance).max().unwrap_or(0));
+        w.i16(0);
+        w.i16(0);
+        w.i16(0);
+        w.i16(1);
+        w.i16(0);
+        w.i16(0);
+        for _ in 0..4 {
+            w.i16(0);
+        }
+        w.i16(0);
+        w.u16(self.glyphs.len() as u16);
+        w.0
+    }
+
+    fn maxp(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.u32(0x0000_5000);
+        w.u16(self.glyphs.len() as u16);
+        w.0
+    }
+
+    fn hmtx(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        for glyph in &self.glyphs {
+            w.u16(glyph.advance);
+            let lsb = glyph
+                .contours
+                .iter()
+                .flatten()
+                .map(|p| p.0)
+                .min()
+                .unwrap_or(0);
+            w.i16(lsb);
+        }
+        w.0
+    }
+
+    fn cmap(&self) -> Vec<u8> {
+        let mut cmap = self.cmap.clone();
+        cmap.sort();
+        let mut w = Writer::default();
+        w.u16(0);
+        w.u16(1);
+        w.u16(3);
+        w.u16(10);
+        w.u32(12);
+        // format 12
+        w.u16(12);
+        w.u16(0);
+        w.u32(16 + 12 * cmap.len() as u32);
+        w.u32(0);
+        w.u32(cmap.len() as u32);
+        for (c, id) in cmap {
+            w.u32(c as u32);
+            w.u32(c as u32);
+            w.u32(id as u32);
+        }
+        w.0
+    }
+
+    fn glyf(&self) -> (Vec<u8>, Vec<u8>, [i16; 4]) {
+        let mut glyf = Writer::default();
+        let mut loca = Writer::default();
+        let mut bbox = [i16::MAX, i16::MAX, i16::MIN, i16::MIN];
+        for glyph in &self.glyphs {
+            loca.u32(glyf.0.len() as u32);
+            if glyph.contours.is_empty() {
+                continue;
+            }
+            let points: Vec<_> = glyph.contours.iter().flatten().copied().collect();
+            let x_min = points.iter().map(|p| p.0).min().unwrap();
+            let y_min = points.iter().map(|p| p.1).min().unwrap();
+            let x_max = points.iter().map(|p| p.0).max().unwrap();
+            let y_max = points.iter().map(|p| p.1).max().unwrap();
+            bbox = [
+                bbox[0].min(x_min),
+                bbox[1].min(y_min),
+                bbox[2].max(x_max),
+                bbox[3].max(y_max),
+            ];
+
+            glyf.i16(glyph.contours.len() as i16);
+            for v in [x_min, y_min, x_max, y_max] {
+                glyf.i16(v);
+            }
+            let mut end = 0;
+            for contour in &glyph.contours {
+                end += contour.len();
+                glyf.u16(end as u16 - 1);
+            }
+            glyf.u16(0);
+            for p in &points {
+                glyf.0.push(p.2 as u8);
+            }
+            let mut prev = 0;
+            for p in &points {
+                glyf.i16(p.0 - prev);
+                prev = p.0;
+            }
+            let mut prev = 0;
+            for p in &points {
+                glyf.i16(p.1 - prev);
+                prev = p.1;
+            }
+            while glyf.0.len() % 4 != 0 {
+                glyf.0.push(0);
+            }
+        }
+        loca.u32(glyf.0.len() as u32);
+        if bbox[0] > bbox[2] {
+            bbox = [0; 4];
+        }
+        (glyf.0, loca.0, bbox)
+    }
+}
+
+/// テーブルを並べて sfnt 形式のファイルにする
+fn sfnt(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut w = Writer::default();
+    directory(&mut w, tables, 12 + 16 * tables.len());
+    table_data(&mut w, tables);
+    w.0
+}
+
+/// 複数のフォントを 1 つの TrueType Collection にまとめる
+pub(crate) fn collection(fonts: &[&FontBuilder]) -> Vec<u8> {
+    let fonts: Vec<_> = fonts.iter().map(|font| font.tables()).collect();
+    let mut w = Writer::default();
+    w.0.extend(b"ttcf");
+    w.u32(0x0001_0000);
+    w.u32(fonts.len() as u32);
+    let mut offset = 12 + 4 * fonts.len();
+    for tables in &fonts {
+        w.u32(offset as u32);
+        offset += 12 + 16 * tables.len();
+    }
+    for tables in &fonts {
+        directory(&mut w, tables, offset);
+        offset += tables
+            .iter()
+            .map(|(_, data)| data.len().next_multiple_of(4))
+            .sum::<usize>();
+    }
+    for tables in &fonts {
+        table_data(&mut w, tables);
+    }
+    w.0
+}
+
+/// テーブルディレクトリ。テーブルの中身はファイル先頭から `offset` の位置に続ける
+fn directory(w: &mut Writer, tables: &[([u8; 4], Vec<u8>)], mut offset: usize) {
+    w.u32(0x0001_0000);
+    w.u16(tables.len() as u16);
+    w.u16(0);
+    w.u16(0);
+    w.u16(0);
+    for (tag, data) in tables {
+        w.0.extend(tag);
+        w.u32(0);
+        w.u32(offset as u32);
+        w.u32(data.len() as u32);
+        offset += data.len().next_multiple_of(4);
+    }
+}
+
+fn table_data(w: &mut Writer, tables: &[([u8; 4], Vec<u8>)]) {
+    for (_, data) in tables {
+        w.0.extend(data);
+        w.0.resize(w.0.len().next_multiple_of(4), 0);
+    }
+}
+
+/// (左, 右, 値) の組を持つ format 0 の `kern` テーブル
+pub(crate) fn kern(pairs: &[(u16, u16, i16)]) -> Vec<u8> {
+    let mut pairs = pairs.to_vec();
+    pairs.sort();
+    let mut w = Writer::default();
+    w.u16(0);
+    w.u16(1);
+    w.u16(0);
+    w.u16((14 + 6 * pairs.len()) as u16);
+    // 横書き、format 0
+    w.u16(0x0001);
+    w.u16(pairs.len() as u16);
+    w.u16(0);
+    w.u16(0);
+    w.u16(0);
+    for (left, right, value) in pairs {
+        w.u16(left);
+        w.u16(right);
+        w.i16(value);
+    }
+    w.0
+}
+
+/// `kern` フィーチャの PairPos format 1 だけを持つ `GPOS` テーブル
+pub(crate) fn gpos_kern(pairs: &[(u16, u16, i16)]) -> Vec<u8> {
+    let mut pairs = pairs.to_vec();
+    pairs.sort();
+    let mut firsts: Vec<u16> = pairs.iter().map(|p| p.0).collect();
+    firsts.dedup();
+
+    // PairPos format 1: ヘッダ、Coverage、PairSet の順に並べる
+    let mut subtable = Writer::default();
+    let header_len = 10 + 2 * firsts.len();
+    let coverage_len = 4 + 2 * firsts.len();
+    subtable.u16(1);
+    subtable.u16(header_len as u16);
+    // 1 つ目のグリフの x_advance だけを持つ
+    subtable.u16(0x0004);
+    subtable.u16(0);
+    subtable.u16(firsts.len() as u16);
+    let mut offset = header_len + coverage_len;
+    for first in &firsts {
+        subtable.u16(offset as u16);
+        offset += 2 + 4 * pairs.iter().filter(|p| p.0 == *first).count();
+    }
+    subtable.u16(1);
+    subtable.u16(firsts.len() as u16);
+    for first in &firsts {
+        subtable.u16(*first);
+    }
+    for first in &firsts {
+        let set: Vec<_> = pairs.iter().filter(|p| p.0 == *first).collect();
+        subtable.u16(set.len() as u16);
+        for (_, second, value) in set {
+            subtable.u16(*second);
+            subtable.i16(*value);
+        }
+    }
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.u16(0);
+    // ScriptList, FeatureList, LookupList
+    w.u16(10);
+    w.u16(12);
+    w.u16(26);
+    w.u16(0);
+    w.u16(1);
+    w.0.extend(b"kern");
+    w.u16(8);
+    w.u16(0);
+    w.u16(1);
+    w.u16(0);
+    w.u16(1);
+    w.u16(4);
+    w.u16(2);
+    w.u16(0);
+    w.u16(1);
+    w.u16(8);
+    w.0.extend(subtable.0);
+    w.0
+}
+
+/// (基底グリフ, [(レイヤーのグリフ, パレットの番号)]) を持つ version 0 の `COLR` テーブル
+pub(crate) fn colr(base_glyphs: &[(u16, Vec<(u16, u16)>)]) -> Vec<u8> {
+    let mut base_glyphs = base_glyphs.to_vec();
+    base_glyphs.sort();
+    let layer_count: usize = base_glyphs.iter().map(|(_, layers)| layers.len()).sum();
+    let mut w = Writer::default();
+    w.u16(0);
+    w.u16(base_glyphs.len() as u16);
+    w.u32(14);
+    w.u32(14 + 6 * base_glyphs.len() as u32);
+    w.u16(layer_count as u16);
+    let mut first = 0;
+    for (glyph, layers) in &base_glyphs {
+        w.u16(*glyph);
+        w.u16(first);
+        w.u16(layers.len() as u16);
+        first += layers.len() as u16;
+    }
+    for (glyph, palette_index) in base_glyphs.iter().flat_map(|(_, layers)| layers) {
+        w.u16(*glyph);
+        w.u16(*palette_index);
+    }
+    w.0
+}
+
+/// RGBA の色を並べたパレットを 1 つだけ持つ `CPAL` テーブル
+pub(crate) fn cpal(colors: &[[u8; 4]]) -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u16(0);
+    w.u16(colors.len() as u16);
+    w.u16(1);
+    w.u16(colors.len() as u16);
+    w.u32(14);
+    w.u16(0);
+    for [r, g, b, a] in colors {
+        // 色は BGRA の順
+        w.0.extend([*b, *g, *r, *a]);
+    }
+    w.0
+}
+
+/// (グリフ, 原点の x, 原点の y, PNG) を `pixels_per_em` のストライクに持つ `sbix` テーブル
+pub(crate) fn sbix(
+    glyph_count: u16,
+    pixels_per_em: u16,
+    images: &[(u16, i16, i16, Vec<u8>)],
+) -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u16(1);
+    w.u16(1);
+    w.u32(1);
+    w.u32(12);
+
+    // ストライクの先頭からのオフセット
+    let mut offset = 4 + 4 * (glyph_count as u32 + 1);
+    let mut data = Writer::default();
+    for glyph in 0..glyph_count {
+        w.u32(offset);
+        if let Some((_, x, y, png)) = images.iter().find(|image| image.0 == glyph) {
+            data.i16(*x);
+            data.i16(*y);
+            data.0.extend(b"png ");
+            data.0.extend(png);
+            offset += 8 + png.len() as u32;
+        }
+    }
+    w.u32(offset);
+    let mut strike = Writer::default();
+    strike.u16(pixels_per_em);
+    strike.u16(72);
+    strike.0.extend(w.0.drain(12..));
+    w.0.extend(strike.0);
+    w.0.extend(data.0);
+    w.0
+}
+
+/// (タグ, 最小, 既定, 最大) の軸を持ち、名前付きインスタンスのない `fvar` テーブル
+pub(crate) fn fvar(axes: &[(&[u8; 4], f32, f32, f32)]) -> Vec<u8> {
+    let fixed = |v: f32| (v * 65536.0).round() as i32 as u32;
+    let mut w = Writer::default();
+    w.u16(1);
+    w.u16(0);
+    w.u16(16);
+    w.u16(2);
+    w.u16(axes.len() as u16);
+    w.u16(20);
+    w.u16(0);
+    w.u16(4 + 4 * axes.len() as u16);
+    for (tag, min, default, max) in axes {
+        w.0.extend(*tag);
+        w.u32(fixed(*min));
+        w.u32(fixed(*default));
+        w.u32(fixed(*max));
+        w.u16(0);
+        w.u16(256);
+    }
+    w.0
+}
+
+/// (グリフ, 正規化した軸の座標での頂点, 各点の移動量)
+///
+/// 移動量は輪郭の点に続けて 4 つの phantom point (左端、送り幅、上端、下端) の分も並べる。
+pub(crate) type GlyphVariation = (u16, Vec<f32>, Vec<(i16, i16)>);
+
+/// グリフごとに 1 つの変化を持つ `gvar` テーブル
+pub(crate) fn gvar(axis_count: u16, glyph_count: u16, variations: &[GlyphVariation]) -> Vec<u8> {
+    let mut data = Writer::default();
+    let mut offsets = vec![];
+    for glyph in 0..glyph_count {
+        offsets.push(data.0.len() as u32);
+        let Some((_, peak, deltas)) = variations.iter().find(|v| v.0 == glyph) else {
+            continue;
+        };
+        // 移動量は 1 バイトの値 (64 個まで) と 2 バイトの値の連なりで書く
+        let mut packed = Writer::default();
+        for values in [
+            deltas.iter().map(|d| d.0).collect::<Vec<_>>(),
+            deltas.iter().map(|d| d.1).collect(),
+        ] {
+            for chunk in values.chunks(64) {
+                packed.0.push(0x40 | (chunk.len() - 1) as u8);
+                for value in chunk {
+                    packed.i16(*value);
+                }
+            }
+        }
+        // すべての点を動かす共有の点番号を使う
+        data.u16(0x8000 | 1);
+        data.u16(4 + 4 + 2 * axis_count);
+        data.u16(packed.0.len() as u16);
+        data.u16(0x8000);
+        for value in peak {
+            data.i16((value * 16384.0).round() as i16);
+        }
+        data.0.push(0);
+        data.0.extend(packed.0);
+        if data.0.len() % 2 == 1 {
+            data.0.push(0);
+        }
+    }
+    offsets.push(data.0.len() as u32);
+
+    let mut w = Writer::default();
+    w.u16(1);
+    w.u16(0);
+    w.u16(axis_count);
+    w.u16(0);
+    w.u32(20);
+    w.u16(glyph_count);
+    // オフセットは 32 bit
+    w.u16(1);
+    w.u32(20 + 4 * (glyph_count as u32 + 1));
+    for offset in offsets {
+        w.u32(offset);
+    }
+    w.0.extend(data.0);
+    w.0
+}
+
+/// Windows (UTF-16BE、英語) の名前だけを持つ format 0 の name テーブル
+pub(crate) fn name(names: &[(u16, &str)]) -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u16(0);
+    w.u16(names.len() as u16);
+    w.u16(6 + 12 * names.len() as u16);
+    let mut strings = vec![];
+    for &(name_id, value) in names {
+        let encoded: Vec<u8> = value.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        w.u16(3);
+        w.u16(1);
+        w.u16(0x409);
+        w.u16(name_id);
+        w.u16(encoded.len() as u16);
+        w.u16(strings.len() as u16);
+        strings.extend(encoded);
+    }
+    w.0.extend(strings);
+    w.0
+}
+
+/// 長方形の輪郭
+pub(crate) fn rect(x0: i16, y0: i16, x1: i16, y1: i16) -> Contour {
+    vec![
+        (x0, y0, true),
+        (x0, y1, true),
+        (x1, y1, true),
+        (x1, y0, true),
+    ]
+}
+
+/// 中心 (cx, cy) 半径 r の円。制御点を外接する正方形の角に置き、`reverse` なら逆回りにする
+pub(crate) fn circle(cx: i16, cy: i16, r: i16, reverse: bool) -> Contour {
+    let mut contour = vec![
+        (cx + r, cy, true),
+        (cx + r, cy - r, false),
+        (cx, cy - r, true),
+        (cx - r, cy - r, false),
+        (cx - r, cy, true),
+        (cx - r, cy + r, false),
+        (cx, cy + r, true),
+        (cx + r, cy + r, false),
+    ];
+    if reverse {
+        contour.reverse();
+    }
+    contour
+}
+
+/// ビッグエンディアンで書き込む
+#[derive(Default)]
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+    pub(crate) fn u16(&mut self, v: u16) {
+        self.0.extend(v.to_be_bytes());
+    }
+
+    pub(crate) fn i16(&mut self, v: i16) {
+        self.0.extend(v.to_be_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        self.0.extend(v.to_be_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.0.extend(v.to_be_bytes());
+    }
+}
@@ -0,0 +1,231 @@
+//! 可変フォントの軸 (`wght`、`wdth` など) の値を指定する
+//!
+//! `Face` に座標を設定しておけば、輪郭と送り幅はそのインスタンスのものになる。
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use ttf_parser::{Face, Tag, VariationAxis};
+
+/// 軸のタグとその値 (`wght=700` など)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Variation {
+    pub tag: Tag,
+    pub value: f32,
+}
+
+impl Variation {
+    pub fn new(tag: &[u8; 4], value: f32) -> Self {
+        Self {
+            tag: Tag::from_bytes(tag),
+            value,
+        }
+    }
+}
+
+impl Display for Variation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.tag, self.value)
+    }
+}
+
+impl FromStr for Variation {
+    type Err = VariationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let syntax = || VariationError::Syntax(s.to_string());
+        let (tag, value) = s.split_once('=').ok_or_else(syntax)?;
+        let tag = parse_tag(tag).ok_or_else(syntax)?;
+        let value = value.trim().parse().map_err(|_| syntax())?;
+        Ok(Self { tag, value })
+    }
+}
+
+/// 1〜4 文字の ASCII をタグにする。4 文字に満たなければ空白で埋める
+pub fn parse_tag(s: &str) -> Option<Tag> {
+    if s.is_empty() || s.len() > 4 || !s.is_ascii() {
+        return None;
+    }
+    let mut bytes = [b' '; 4];
+    bytes[..s.len()].copy_from_slice(s.as_bytes());
+    Some(Tag::from_bytes(&bytes))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VariationError {
+    /// `tag=value` の形になっていない
+    Syntax(String),
+    NotVariable,
+    UnknownAxis(Tag),
+}
+
+impl Display for VariationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariationError::Syntax(s) => {
+                write!(f, "expected a variation like wght=700, got {s:?}")
+            }
+            VariationError::NotVariable => write!(f, "font is not a variable font"),
+            VariationError::UnknownAxis(tag) => write!(f, "font has no {tag} axis"),
+        }
+    }
+}
+
+impl Error for VariationError {}
+
+/// 軸の値を `face` に設定する。範囲外の値は軸の最小値か最大値になる
+pub fn set_variations(face: &mut Face, variations: &[Variation]) -> Result<(), VariationError> {
+    if variations.is_empty() {
+        return Ok(());
+    }
+    if !face.is_variable() {
+        return Err(VariationError::NotVariable);
+    }
+    for variation in variations {
+        if axis(face, variation.tag).is_none() {
+            return Err(VariationError::UnknownAxis(variation.tag));
+        }
+        face.set_variation(variation.tag, variation.value)
+            .ok_or(VariationError::NotVariable)?;
+    }
+    Ok(())
+}
+
+pub fn axis(face: &Face, tag: Tag) -> Option<VariationAxis> {
+    face.variation_axes()
+        .into_iter()
+        .find(|axis| axis.tag == tag)
+}
+
+/// 軸の最小値から最大値までを `steps` 個に等分した値
+pub fn sweep(axis: &VariationAxis, steps: usize) -> Vec<f32> {
+    if steps < 2 {
+        return vec![axis.def_value];
+    }
+    (0..steps)
+        .map(|i| {
+            let t = i as f32 / (steps - 1) as f32;
+            axis.min_value + (axis.max_value - axis.min_value) * t
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        atlas::{AtlasOptions, build_atlas},
+        font_file::FontFile,
+        rasterize_glyph,
+        test_font::{FontBuilder, fvar, gvar, rect},
+    };
+    use ttf_parser::GlyphId;
+
+    /// 既定の太さで幅 100、wght=900 で幅 200 になる縦棒
+    fn variable_font() -> Vec<u8> {
+        let mut font = FontBuilder::new(1000);
+        font.glyph(Some('l'), 200, vec![rect(0, 0, 100, 500)]);
+        font.table(
+            b"fvar",
+            fvar(&[
+                (b"wght", 100.0, 400.0, 900.0),
+                (b"wdth", 50.0, 100.0, 100.0),
+            ]),
+        );
+        let deltas = vec![
+            (0, 0),
+            (0, 0),
+            (100, 0),
+            (100, 0),
+            (0, 0),
+            (100, 0),
+            (0, 0),
+            (0, 0),
+        ];
+        font.table(b"gvar", gvar(2, 2, &[(1, vec![1.0, 0.0], deltas)]));
+        font.build()
+    }
+
+    fn sum(face: &Face) -> f32 {
+        let coverage = rasterize_glyph(face, GlyphId(1), 10.0).unwrap();
+        coverage.data.iter().map(|&v| v as f32 / 255.0).sum()
+    }
+
+    #[test]
+    fn parse_variations() {
+        let variation: Variation = "wght=700".parse().unwrap();
+        assert_eq!(variation, Variation::new(b"wght", 700.0));
+        assert_eq!(variation.to_string(), "wght=700");
+        assert_eq!("opsz=9.5".parse::<Variation>().unwrap().value, 9.5);
+        assert_eq!(
+            "ab=1".parse::<Variation>().unwrap().tag,
+            Tag::from_bytes(b"ab  ")
+        );
+        for s in ["wght", "=700", "weight=700", "wght=bold"] {
+            assert!(
+                matches!(s.parse::<Variation>(), Err(VariationError::Syntax(_))),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn instance_changes_outline_and_advance() {
+        let data = variable_font();
+        let mut face = Face::parse(&data, 0).unwrap();
+        assert!((sum(&face) - 5.0).abs() < 1e-3);
+
+        set_variations(&mut face, &[Variation::new(b"wght", 900.0)]).unwrap();
+        assert!((sum(&face) - 10.0).abs() < 1e-3);
+        assert_eq!(face.glyph_hor_advance(GlyphId(1)), Some(300));
+
+        // 既定値との中間は移動量も半分
+        set_variations(&mut face, &[Variation::new(b"wght", 650.0)]).unwrap();
+        assert!((sum(&face) - 7.5).abs() < 0.02);
+
+        assert_eq!(
+            set_variations(&mut face, &[Variation::new(b"slnt", 0.0)]),
+            Err(VariationError::UnknownAxis(Tag::from_bytes(b"slnt")))
+        );
+        let data = FontBuilder::new(1000).build();
+        let mut face = Face::parse(&data, 0).unwrap();
+        assert_eq!(
+            set_variations(&mut face, &[Variation::new(b"wght", 900.0)]),
+            Err(VariationError::NotVariable)
+        );
+        assert_eq!(set_variations(&mut face, &[]), Ok(()));
+    }
+
+    #[test]
+    fn font_file_keeps_instance() {
+        let mut file = FontFile::from_vec(variable_font(), 0).unwrap();
+        file.set_variations(&[Variation::new(b"wght", 900.0)])
+            .unwrap();
+        assert_eq!(file.variations(), [Variation::new(b"wght", 900.0)]);
+        assert!((sum(&file.face()) - 10.0).abs() < 1e-3);
+
+        let atlas = build_atlas(&file.face(), ['l'], &AtlasOptions::default()).unwrap();
+        assert_eq!(atlas.manifest.glyphs[0].width, 7);
+        assert_eq!(atlas.manifest.glyphs[0].advance, 9.6);
+
+        assert!(
+            file.set_variations(&[Variation::new(b"ital", 1.0)])
+                .is_err()
+        );
+        assert_eq!(file.variations(), [Variation::new(b"wght", 900.0)]);
+    }
+
+    #[test]
+    fn sweep_axis() {
+        let data = variable_font();
+        let face = Face::parse(&data, 0).unwrap();
+        let weight = axis(&face, Tag::from_bytes(b"wght")).unwrap();
+        assert_eq!(sweep(&weight, 5), [100.0, 300.0, 500.0, 700.0, 900.0]);
+        assert_eq!(sweep(&weight, 1), [400.0]);
+        let width = axis(&face, Tag::from_bytes(b"wdth")).unwrap();
+        assert_eq!(sweep(&width, 2), [50.0, 100.0]);
+    }
+}
@@ -0,0 +1,331 @@
+//! レイアウトした文字列を、ビットマップにせずに輪郭のまま SVG と PDF に書き出す
+//!
+//! 輪郭はフォント単位のまま書き、グリフごとの拡大と位置は変換行列で指定する。
+//! ラスタライザと同じ輪郭を使うので、文書に埋め込んだ文字と描画結果が一致する。
+
+use std::fmt::Write;
+
+use bezier_converter::{
+    path::{Path, Segment},
+    svg::to_path_data,
+};
+use image::Rgb;
+use ttf_parser::Face;
+
+use crate::{layout::Layout, raster::Outline};
+
+/// 輪郭のあるグリフ 1 つ
+struct PlacedGlyph {
+    glyph_id: u16,
+    /// フォント単位 (y は上向き) の輪郭
+    path: Path,
+    /// フォント単位からピクセルへの倍率
+    scale: f32,
+    x: f32,
+    baseline: f32,
+}
+
+fn placed_glyphs(faces: &[Face], layout: &Layout) -> Vec<PlacedGlyph> {
+    let mut glyphs = vec![];
+    for line in &layout.lines {
+        for glyph in &line.glyphs {
+            let face = &faces[glyph.face];
+            let mut outline = Outline::new(1.0);
+            if face.outline_glyph(glyph.glyph_id, &mut outline).is_none() {
+                continue;
+            }
+            glyphs.push(PlacedGlyph {
+                glyph_id: glyph.glyph_id.0,
+                path: outline.path,
+                scale: layout.size_px / face.units_per_em() as f32,
+                x: glyph.x,
+                baseline: line.baseline,
+            });
+        }
+    }
+    glyphs
+}
+
+fn page_size(layout: &Layout) -> (f32, f32) {
+    (layout.width.ceil().max(1.0), layout.height.ceil().max(1.0))
+}
+
+fn hex(color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// グリフごとに 1 つの `<path>` を持つ SVG。大きさはピクセル単位で、輪郭のないグリフは省く
+pub fn render_layout_svg(
+    faces: &[Face],
+    layout: &Layout,
+    foreground: Rgb<u8>,
+    background: Option<Rgb<u8>>,
+) -> String {
+    let (width, height) = page_size(layout);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    );
+    if let Some(background) = background {
+        writeln!(
+            svg,
+            "  <rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>",
+            hex(background)
+        )
+        .unwrap();
+    }
+    writeln!(svg, "  <g fill=\"{}\">", hex(foreground)).unwrap();
+    for glyph in placed_glyphs(faces, layout) {
+        let s = glyph.scale;
+        writeln!(
+            svg,
+            "    <path data-glyph-id=\"{}\" transform=\"matrix({s} 0 0 {} {} {})\" d=\"{}\"/>",
+            glyph.glyph_id,
+            -s,
+            glyph.x,
+            glyph.baseline,
+            to_path_data(&glyph.path)
+        )
+        .unwrap();
+    }
+    svg.push_str("  </g>\n</svg>\n");
+    svg
+}
+
+/// 1 ページの PDF。1 ピクセルを 1 ポイントとし、グリフごとに変換行列を設定して nonzero で塗る
+pub fn render_layout_pdf(
+    faces: &[Face],
+    layout: &Layout,
+    foreground: Rgb<u8>,
+    background: Option<Rgb<u8>>,
+) -> Vec<u8> {
+    let (width, height) = page_size(layout);
+    let rgb = |color: Rgb<u8>| color.0.map(|c| (c as f32 / 255.0).to_string()).join(" ");
+
+    let mut content = String::new();
+    if let Some(background) = background {
+        writeln!(content, "{} rg 0 0 {width} {height} re f", rgb(background)).unwrap();
+    }
+    writeln!(content, "{} rg", rgb(foreground)).unwrap();
+    for glyph in placed_glyphs(faces, layout) {
+        let s = glyph.scale;
+        writeln!(
+            content,
+            "q {s} 0 0 {s} {} {} cm",
+            glyph.x,
+            height - glyph.baseline
+        )
+        .unwrap();
+        write_pdf_path(&mut content, &glyph.path);
+        content.push_str("f Q\n");
+    }
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width} {height}] \
+             /Resources << >> /Contents 4 0 R >>"
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ),
+    ];
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{object}\nendobj\n", i + 1).bytes());
+    }
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        writeln!(trailer, "{offset:010} 00000 n ").unwrap();
+    }
+    write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    )
+    .unwrap();
+    pdf.extend(trailer.bytes());
+    pdf
+}
+
+/// PDF の演算子で輪郭を書く。2 次ベジエは同じ形の 3 次ベジエにする
+fn write_pdf_path(out: &mut String, path: &Path) {
+    let point = |x: f32, y: f32| format!("{x} {y}");
+    for contour in &path.contours {
+        let Some(first) = contour.segments.first() else {
+            continue;
+        };
+        let start = first.start();
+        writeln!(out, "{} m", point(start.x, start.y)).unwrap();
+        for segment in &contour.segments {
+            match segment {
+                Segment::Line(l) => writeln!(out, "{} l", point(l.x1, l.y1)),
+                Segment::Quadratic(q) => {
+                    let c0 = (
+                        q.x0 + (q.cx0 - q.x0) * 2.0 / 3.0,
+                        q.y0 + (q.cy0 - q.y0) * 2.0 / 3.0,
+                    );
+                    let c1 = (
+                        q.x1 + (q.cx0 - q.x1) * 2.0 / 3.0,
+                        q.y1 + (q.cy0 - q.y1) * 2.0 / 3.0,
+                    );
+                    writeln!(
+                        out,
+                        "{} {} {} c",
+                        point(c0.0, c0.1),
+                        point(c1.0, c1.1),
+                        point(q.x1, q.y1)
+                    )
+                }
+                Segment::Cubic(c) => writeln!(
+                    out,
+                    "{} {} {} c",
+                    point(c.cx0, c.cy0),
+                    point(c.cx1, c.cy1),
+                    point(c.x1, c.y1)
+                ),
+            }
+            .unwrap();
+        }
+        if contour.closed {
+            out.push_str("h\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bezier_converter::{svg::parse_path, transform::Affine};
+
+    use super::*;
+    use crate::{
+        layout::{LayoutOptions, layout},
+        test_font::{FontBuilder, rect},
+    };
+
+    fn sample() -> (Vec<u8>, LayoutOptions) {
+        let mut font = FontBuilder::new(1000);
+        font.glyph(Some('i'), 250, vec![rect(50, 0, 200, 700)]);
+        font.glyph(Some(' '), 250, vec![]);
+        font.glyph(
+            Some('o'),
+            500,
+            vec![vec![
+                (50, 0, true),
+                (450, 0, false),
+                (450, 500, true),
+                (50, 500, true),
+            ]],
+        );
+        let options = LayoutOptions {
+            size_px: 20.0,
+            ..LayoutOptions::default()
+        };
+        (font.build(), options)
+    }
+
+    #[test]
+    fn svg_paths_match_layout() {
+        let (data, options) = sample();
+        let face = Face::parse(&data, 0).unwrap();
+        let layout = layout(&face, "i o", &options);
+        let svg = render_layout_svg(&[face], &layout, Rgb([0, 0, 0]), None);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\""));
+        assert!(svg.contains("<g fill=\"#000000\">"));
+        assert!(!svg.contains("<rect"));
+
+        let paths: Vec<&str> = svg.lines().filter(|l| l.contains("<path")).collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].contains("data-glyph-id=\"1\""));
+        assert!(paths[0].contains("transform=\"matrix(0.02 0 0 -0.02 0 16)\""));
+        assert!(paths[1].contains("transform=\"matrix(0.02 0 0 -0.02 10 16)\""));
+        assert!(paths[1].contains('Q'));
+
+        // 変換した輪郭がレイアウトの位置に来る
+        let d = paths[0]
+            .split(" d=\"")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches("\"/>");
+        let path = parse_path::<f32>(d).unwrap();
+        let m = Affine::scale(0.02, -0.02).then(&Affine::translate(0.0, 16.0));
+        let corners: Vec<_> = path.transform(&m).contours[0]
+            .segments
+            .iter()
+            .map(|s| (s.start().x, s.start().y))
+            .collect();
+        assert!(corners.contains(&(1.0, 16.0)));
+        assert!(corners.contains(&(4.0, 2.0)));
+
+        let face = Face::parse(&data, 0).unwrap();
+        let svg = render_layout_svg(&[face], &layout, Rgb([255, 0, 0]), Some(Rgb([0, 0, 255])));
+        assert!(svg.contains("<rect width=\"20\" height=\"20\" fill=\"#0000ff\"/>"));
+        assert!(svg.contains("<g fill=\"#ff0000\">"));
+    }
+
+    #[test]
+    fn pdf_structure() {
+        let (data, options) = sample();
+        let face = Face::parse(&data, 0).unwrap();
+        let layout = layout(&face, "io", &options);
+        let pdf = render_layout_pdf(&[face], &layout, Rgb([0, 0, 0]), None);
+        let text = String::from_utf8(pdf).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/MediaBox [0 0 15 20]"));
+        assert!(text.contains("q 0.02 0 0 0.02 0 4 cm\n50 0 m\n50 700 l\n"));
+        assert!(text.contains("q 0.02 0 0 0.02 5 4 cm\n"));
+        // 2 次ベジエ (50, 0) → (450, 0) → (450, 500) を 3 次ベジエにする
+        let curve: Vec<f32> = text
+            .lines()
+            .find(|l| l.ends_with(" c"))
+            .unwrap()
+            .split(' ')
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        assert_eq!(curve.len(), 6);
+        let expected = [950.0 / 3.0, 0.0, 450.0, 500.0 / 3.0, 450.0, 500.0];
+        assert!(
+            curve
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-3)
+        );
+        assert_eq!(text.matches("f Q\n").count(), 2);
+
+        // 相互参照表の位置がそれぞれのオブジェクトを指す
+        let xref = text.find("\nxref\n").unwrap() + 1;
+        let startxref: usize = text
+            .split("startxref\n")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(startxref, xref);
+        for (i, line) in text[xref..].lines().skip(3).take(4).enumerate() {
+            assert_eq!(line.len(), 19);
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+        let length: usize = text
+            .split("/Length ")
+            .nth(1)
+            .unwrap()
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let stream = text.find("stream\n").unwrap() + "stream\n".len();
+        assert_eq!(&text[stream + length..stream + length + 9], "endstream");
+    }
+}